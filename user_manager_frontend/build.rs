@@ -0,0 +1,60 @@
+use std::{env, fs, path::PathBuf};
+
+// Bundles the files of the local content source (see `src/helpers/local_content.rs`) into the app:
+// `site.json` and `posts/*.md` from `content/`, or from the directory `CONTENT_DIR` names.
+// Only builds with the `local-content` feature use them.
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+    if env::var_os("CARGO_FEATURE_LOCAL_CONTENT").is_none() {
+        return;
+    }
+
+    let manifest_dir = PathBuf::from(env::var("CARGO_MANIFEST_DIR").expect("set by cargo"));
+    let content_dir =
+        manifest_dir.join(env::var("CONTENT_DIR").unwrap_or_else(|_| "content".to_string()));
+    println!("cargo:rerun-if-env-changed=CONTENT_DIR");
+    println!("cargo:rerun-if-changed={}", content_dir.display());
+
+    let site = content_dir.join("site.json");
+    println!("cargo:rerun-if-changed={}", site.display());
+    // Without a `site.json` there is simply no content.
+    let site_json = if site.exists() {
+        format!("include_str!({:?})", site.display().to_string())
+    } else {
+        "\"{}\"".to_string()
+    };
+
+    let posts_dir = content_dir.join("posts");
+    println!("cargo:rerun-if-changed={}", posts_dir.display());
+    let mut posts = fs::read_dir(&posts_dir)
+        .map(|entries| {
+            entries
+                .filter_map(Result::ok)
+                .map(|entry| entry.path())
+                .filter(|path| path.extension().is_some_and(|extension| extension == "md"))
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+    posts.sort();
+    let post_files = posts
+        .iter()
+        .map(|path| {
+            let slug = path.file_stem().unwrap_or_default().to_string_lossy();
+            format!(
+                "({:?}, include_str!({:?})),",
+                slug,
+                path.display().to_string()
+            )
+        })
+        .collect::<String>();
+
+    let out = PathBuf::from(env::var("OUT_DIR").expect("set by cargo")).join("local_content.rs");
+    fs::write(
+        out,
+        format!(
+            "const SITE_JSON: &str = {};\nconst POST_FILES: &[(&str, &str)] = &[{}];\n",
+            site_json, post_files
+        ),
+    )
+    .expect("Couldn't write the bundled content");
+}
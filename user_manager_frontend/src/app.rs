@@ -1,3 +1,4 @@
+use serde::{Deserialize, Serialize};
 use yew::{function_component, html, Html};
 
 // From `yew_router`, we import:
@@ -6,9 +7,18 @@ use yew::{function_component, html, Html};
 // - `Switch` to render components based on the current route.
 use yew_router::{BrowserRouter, Routable, Switch};
 
-// We import our custom pages: `ArticlePage`, `HomePage`, and `LoginPage`.
+// The guard every page that needs a login is wrapped in, and the provider of the shared login state.
+use crate::components::{RequireAuth, SessionProvider};
+// The roles a page can require.
+use crate::helpers::roles::Role;
+// We import our custom pages: `ArticlePage`, `AuthorPage`, `HomePage`, `LoginPage`, `RegisterPage`,
+// `ProfilePage`, `ResetPasswordPage`, `AuthCallbackPage`, `SearchPage`, `TopicPage` and the admin pages
+// `AdminUsersPage` and `UserFormPage`.
 // These are components that will be displayed when their routes are matched.
-use crate::pages::{ArticlePage, HomePage, LoginPage};
+use crate::pages::{
+    AdminUsersPage, ArticlePage, AuthCallbackPage, AuthorPage, HomePage, LoginPage, ProfilePage,
+    RegisterPage, ResetPasswordPage, SearchPage, Topic, TopicPage, UserFormPage,
+};
 
 // Define an enum `Route` that implements `Routable`. Each variant corresponds
 // to a possible path (URL). The `#[at("/some/path")]` attributes indicate which
@@ -20,8 +30,31 @@ pub enum Route {
     Home,
     #[at("/login")]
     Login,
+    #[at("/register")]
+    Register,
+    #[at("/reset/:token")]
+    ResetPassword { token: String },
+    // Where the identity provider sends the browser back to after single sign-on.
+    #[at("/auth/callback")]
+    AuthCallback,
     #[at("/article/:slug")]
     Article { slug: String },
+    #[at("/profile")]
+    Profile,
+    #[at("/search")]
+    Search,
+    #[at("/tag/:tag")]
+    Tag { tag: String },
+    #[at("/category/:slug")]
+    Category { slug: String },
+    #[at("/author/:slug")]
+    Author { slug: String },
+    #[at("/admin/users")]
+    AdminUsers,
+    #[at("/admin/users/new")]
+    AdminNewUser,
+    #[at("/admin/users/:username/edit")]
+    AdminEditUser { username: String },
     // If no other routes match, we return a NotFound variant.
     // The user will see the NotFound page if they navigate to a path that doesn't exist.
     #[not_found]
@@ -29,12 +62,60 @@ pub enum Route {
     NotFound,
 }
 
+// The query string of `Route::Home`: the page of posts to show (starting at 1), or `infinite`
+// to keep loading older posts while scrolling instead.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct HomeQuery {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub page: Option<u32>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub infinite: bool,
+}
+
+// The query string of `Route::Search`: what was searched for.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SearchQuery {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub q: Option<String>,
+}
+
+// The query string of `Route::Login`. `next` is the page to return to after logging in.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct LoginQuery {
+    pub next: Option<String>,
+}
+
+// Wraps a page that only logged-in users may see.
+fn protected(page: Html) -> Html {
+    restricted(Role::Reader, page)
+}
+
+// Wraps a page that only logged-in users with at least `role` may see. Others get a forbidden view.
+fn restricted(role: Role, page: Html) -> Html {
+    html! { <RequireAuth role={role}>{ page }</RequireAuth> }
+}
+
 // This function decides which component to render based on the `Route` enum value.
 fn switch(route: Route) -> Html {
     match route {
-        Route::Home => html! { <HomePage /> },
+        Route::Home => protected(html! { <HomePage /> }),
         Route::Login => html! { <LoginPage /> },
-        Route::Article { slug } => html! { <ArticlePage slug={slug} /> },
+        Route::Register => html! { <RegisterPage /> },
+        Route::ResetPassword { token } => html! { <ResetPasswordPage token={token} /> },
+        Route::AuthCallback => html! { <AuthCallbackPage /> },
+        Route::Article { slug } => protected(html! { <ArticlePage slug={slug} /> }),
+        Route::Profile => protected(html! { <ProfilePage /> }),
+        Route::Search => protected(html! { <SearchPage /> }),
+        Route::Tag { tag } => protected(html! { <TopicPage topic={Topic::Tag(tag)} /> }),
+        Route::Category { slug } => {
+            protected(html! { <TopicPage topic={Topic::Category(slug)} /> })
+        }
+        Route::Author { slug } => protected(html! { <AuthorPage slug={slug} /> }),
+        Route::AdminUsers => restricted(Role::Admin, html! { <AdminUsersPage /> }),
+        Route::AdminNewUser => restricted(Role::Admin, html! { <UserFormPage /> }),
+        Route::AdminEditUser { username } => {
+            restricted(Role::Admin, html! { <UserFormPage username={username} /> })
+        }
         Route::NotFound => html! { <h1>{ "You did something wrong!" }</h1> },
     }
 }
@@ -43,10 +124,13 @@ fn switch(route: Route) -> Html {
 #[function_component]
 pub fn App() -> Html {
     html! {
-        <BrowserRouter>
-           // The `Switch` component will call the `switch` function,
-            // passing it the current route, and then render the component returned by `switch`.
-            <Switch<Route> render={switch} />
-        </BrowserRouter>
+        // `SessionProvider` lets every page find out who is logged in through `use_auth()`.
+        <SessionProvider>
+            <BrowserRouter>
+               // The `Switch` component will call the `switch` function,
+                // passing it the current route, and then render the component returned by `switch`.
+                <Switch<Route> render={switch} />
+            </BrowserRouter>
+        </SessionProvider>
     }
 }
@@ -0,0 +1,82 @@
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use yew::{function_component, html, Html, Properties};
+use yew_router::prelude::*;
+
+use crate::{app::Route, helpers::dates::format_date};
+
+// The author of a post, as far as a byline shows them.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct AuthorSummary {
+    pub name: String,
+    // Authors without a slug have no page to link to.
+    pub slug: Option<String>,
+    #[serde(rename = "imageUrl")]
+    pub image_url: Option<String>,
+}
+
+#[derive(PartialEq, Properties)]
+pub struct BylineProps {
+    pub author: Option<AuthorSummary>,
+    pub published_at: Option<DateTime<Utc>>,
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+// Who wrote a post and when, e.g. "By Jane Doe · 17 October 2026 · Updated 20 October 2026".
+// The update is only mentioned if it happened on a later day.
+#[function_component]
+pub fn Byline(props: &BylineProps) -> Html {
+    let BylineProps {
+        author,
+        published_at,
+        updated_at,
+    } = props;
+
+    let published = published_at.map(format_date);
+    let updated = updated_at
+        .map(format_date)
+        .filter(|updated| Some(updated) != published.as_ref());
+
+    let mut parts = Vec::new();
+    if let Some(author) = author {
+        let name = match &author.slug {
+            Some(slug) => html! {
+                <Link<Route> to={Route::Author { slug: slug.clone() }} classes="text-blue-600 font-medium hover:underline">
+                    { &author.name }
+                </Link<Route>>
+            },
+            None => html! { <span class="font-medium">{ &author.name }</span> },
+        };
+        parts.push(html! { <span>{ "By " }{ name }</span> });
+    }
+    if let (Some(published), Some(published_at)) = (published, published_at) {
+        parts.push(html! { <time datetime={published_at.to_rfc3339()}>{ published }</time> });
+    }
+    if let Some(updated) = updated {
+        parts.push(html! { <span>{ format!("Updated {}", updated) }</span> });
+    }
+    if parts.is_empty() {
+        return html! {};
+    }
+
+    html! {
+        <div class="flex flex-wrap items-center gap-2 text-sm text-gray-600 mb-4">
+            {
+                match author.as_ref().and_then(|author| author.image_url.as_ref().map(|url| (author, url))) {
+                    Some((author, url)) => html! {
+                        <img src={url.clone()} alt={author.name.clone()} class="w-8 h-8 rounded-full object-cover" loading="lazy" />
+                    },
+                    None => html! {},
+                }
+            }
+            {
+                for parts.into_iter().enumerate().map(|(i, part)| html! {
+                    <>
+                        { if i > 0 { html! { <span aria-hidden="true">{ "·" }</span> } } else { html! {} } }
+                        { part }
+                    </>
+                })
+            }
+        </div>
+    }
+}
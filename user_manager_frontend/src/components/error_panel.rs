@@ -0,0 +1,29 @@
+use yew::{function_component, html, Callback, Html, MouseEvent, Properties};
+
+use crate::helpers::fetch::FetchError;
+
+#[derive(PartialEq, Properties)]
+pub struct ErrorPanelProps {
+    pub error: FetchError,
+    // Called when the user clicks "Try again".
+    pub on_retry: Callback<MouseEvent>,
+}
+
+// Shows what went wrong while loading content, with a button to try again.
+#[function_component]
+pub fn ErrorPanel(props: &ErrorPanelProps) -> Html {
+    let ErrorPanelProps { error, on_retry } = props;
+
+    html! {
+        <div class="max-w-xl mx-auto my-8 p-6 bg-red-50 border border-red-200 rounded-lg text-center">
+            <h2 class="text-xl text-red-800 font-semibold mb-2">{ "Something went wrong" }</h2>
+            <p class="text-red-700 mb-4">{ error.to_string() }</p>
+            <button
+                onclick={on_retry.clone()}
+                class="px-4 py-2 bg-red-600 text-white rounded-md shadow-sm hover:bg-red-700 transition-colors"
+            >
+                { "Try again" }
+            </button>
+        </div>
+    }
+}
@@ -0,0 +1,31 @@
+use yew::{function_component, html, Html, Properties};
+use yew_router::prelude::*;
+
+use crate::{app::Route, helpers::roles::Role};
+
+#[derive(PartialEq, Properties)]
+pub struct ForbiddenProps {
+    // The role the page needs.
+    pub required: Role,
+}
+
+// Shown instead of a page the logged-in user doesn't have the role for.
+#[function_component]
+pub fn Forbidden(props: &ForbiddenProps) -> Html {
+    let ForbiddenProps { required } = props;
+
+    html! {
+        <div class="max-w-xl mx-auto my-8 p-6 bg-white shadow-md rounded-lg text-center">
+            <h1 class="text-3xl text-gray-800 font-bold mb-2">{ "Access denied" }</h1>
+            <p class="text-gray-600 mb-4">
+                { format!("You need the {} role to see this page.", required) }
+            </p>
+            <Link<Route>
+                to={Route::Home}
+                classes="inline-block px-4 py-2 bg-blue-500 text-white rounded-md shadow-sm hover:bg-blue-600 transition-colors"
+            >
+                { "Back to the front page" }
+            </Link<Route>>
+        </div>
+    }
+}
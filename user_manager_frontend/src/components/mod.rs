@@ -0,0 +1,49 @@
+// Declare a module named `portable_text` in the file "portable_text.rs".
+mod portable_text;
+// Re-export the `PortableText` component and the block types it renders.
+pub use portable_text::{ArticleBody, PortableText, UnknownMark};
+// The parts of a block, which the local content source builds from Markdown.
+#[cfg(feature = "local-content")]
+pub use portable_text::{ArticleBodyAsset, ArticleBodyChild, MarkDef};
+
+// Declare a module named `error_panel` in the file "error_panel.rs".
+mod error_panel;
+// Re-export the `ErrorPanel` shown when loading content fails.
+pub use error_panel::ErrorPanel;
+
+// Declare a module named `not_found` in the file "not_found.rs".
+mod not_found;
+// Re-export the `NotFound` view for content that doesn't exist.
+pub use not_found::NotFound;
+
+// Declare a module named `forbidden` in the file "forbidden.rs".
+mod forbidden;
+// Re-export the `Forbidden` view for pages the user doesn't have the role for.
+pub use forbidden::Forbidden;
+
+// Declare a module named `require_auth` in the file "require_auth.rs".
+mod require_auth;
+// Re-export the `RequireAuth` guard that wraps every page that needs a login or a role.
+pub use require_auth::RequireAuth;
+
+// Declare a module named `session_provider` in the file "session_provider.rs".
+mod session_provider;
+// Re-export the `SessionProvider` that shares the `AuthContext` with the whole app.
+pub use session_provider::SessionProvider;
+
+// Declare a module named `post_card` in the file "post_card.rs".
+mod post_card;
+// Re-export the `PostCard` for one post and the `PostGrid` of them, and the `BlogPost` each card shows.
+pub use post_card::{BlogPost, PostCard, PostGrid, POST_GRID_CLASSES};
+#[cfg(feature = "local-content")]
+pub use post_card::BlogSlug;
+
+// Declare a module named `topic_chips` in the file "topic_chips.rs".
+mod topic_chips;
+// Re-export the `TopicChips` linking to a post's categories and tags, and the `Category` type.
+pub use topic_chips::{Category, TopicChips};
+
+// Declare a module named `byline` in the file "byline.rs".
+mod byline;
+// Re-export the `Byline` naming a post's author and dates, and the `AuthorSummary` it shows.
+pub use byline::{AuthorSummary, Byline};
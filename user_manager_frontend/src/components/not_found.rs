@@ -0,0 +1,29 @@
+use yew::{function_component, html, Html, Properties};
+use yew_router::prelude::*;
+
+use crate::app::Route;
+
+#[derive(PartialEq, Properties)]
+pub struct NotFoundProps {
+    // Explains what couldn't be found, e.g. "We couldn't find that article."
+    pub message: String,
+}
+
+// A friendly "not found" view with a way back to the home page.
+#[function_component]
+pub fn NotFound(props: &NotFoundProps) -> Html {
+    let NotFoundProps { message } = props;
+
+    html! {
+        <div class="max-w-xl mx-auto my-8 p-6 bg-white shadow-md rounded-lg text-center">
+            <h1 class="text-3xl text-gray-800 font-bold mb-2">{ "Not found" }</h1>
+            <p class="text-gray-600 mb-4">{ message }</p>
+            <Link<Route>
+                to={Route::Home}
+                classes="inline-block px-4 py-2 bg-blue-500 text-white rounded-md shadow-sm hover:bg-blue-600 transition-colors"
+            >
+                { "Back to the front page" }
+            </Link<Route>>
+        </div>
+    }
+}
@@ -0,0 +1,512 @@
+use serde::Deserialize;
+use serde_json::{Map, Value};
+use yew::{function_component, html, Callback, Html, Properties};
+
+// Represents one block of the article's body (e.g., text, images, etc.).
+// Each block can have a style, children, or an asset if it's an image.
+// This follows the Portable Text format that Sanity Studio produces.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct ArticleBody {
+    // `_type` in the JSON is renamed to `content_type` in Rust.
+    #[serde(rename = "_type")]
+    pub content_type: String,
+    // "normal", "h1"–"h6" or "blockquote" for text blocks.
+    pub style: Option<String>,
+    // `_key` in the JSON becomes `key`.
+    #[serde(rename = "_key")]
+    pub key: String,
+    pub asset: Option<ArticleBodyAsset>,
+    pub alt: Option<String>,
+    pub children: Option<Vec<ArticleBodyChild>>,
+    // Set to "bullet" or "number" when the block is part of a list.
+    #[serde(rename = "listItem")]
+    pub list_item: Option<String>,
+    // How deep a list item is nested, starting at 1.
+    pub level: Option<u32>,
+    // Annotations (like links) that the spans of this block refer to by `_key`.
+    #[serde(rename = "markDefs", default)]
+    pub mark_defs: Vec<MarkDef>,
+}
+
+// An annotation referenced from `ArticleBodyChild::marks`, e.g. a link with an `href`.
+// Fields we don't know about are kept in `extra` so custom mark handlers can use them.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct MarkDef {
+    #[serde(rename = "_key")]
+    pub key: String,
+    #[serde(rename = "_type")]
+    pub content_type: String,
+    pub href: Option<String>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+// Everything a custom mark handler needs to render a mark we don't support ourselves.
+// `definition` is set when the mark is an annotation key found in `markDefs`.
+pub struct UnknownMark {
+    pub mark: String,
+    pub definition: Option<MarkDef>,
+    pub children: Html,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+// Represents a child element of the article body (e.g., a span of text).
+pub struct ArticleBodyChild {
+    #[serde(rename = "_type")]
+    pub content_type: String,
+    #[serde(rename = "_key")]
+    pub key: String,
+    pub text: String,
+    pub marks: Vec<String>,
+}
+
+// Represents an asset (like an image) embedded in the body.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct ArticleBodyAsset {
+    pub url: String,
+    #[serde(rename = "mimeType")]
+    pub mime_type: String,
+}
+
+#[derive(PartialEq, Properties)]
+pub struct PortableTextProps {
+    pub blocks: Vec<ArticleBody>,
+    // Called for marks that aren't a known decorator or link. Without it the text is rendered unmarked.
+    #[prop_or_default]
+    pub mark_handler: Option<Callback<UnknownMark, Html>>,
+}
+
+// Renders a list of Portable Text blocks as HTML.
+// Consecutive list items are grouped into (possibly nested) lists,
+// everything else is rendered one block at a time.
+#[function_component]
+pub fn PortableText(props: &PortableTextProps) -> Html {
+    let PortableTextProps {
+        blocks,
+        mark_handler,
+    } = props;
+
+    let mut rendered = Vec::new();
+    let mut i = 0;
+    while i < blocks.len() {
+        if blocks[i].list_item.is_some() {
+            // Collect the whole run of list items so nesting can be worked out from `level`.
+            let start = i;
+            while i < blocks.len() && blocks[i].list_item.is_some() {
+                i += 1;
+            }
+            let items = blocks[start..i].iter().collect::<Vec<_>>();
+            rendered.push(render_lists(group_list(&items), mark_handler));
+        } else {
+            rendered.push(render_block(&blocks[i], mark_handler));
+            i += 1;
+        }
+    }
+
+    html! { <>{ for rendered }</> }
+}
+
+// Renders a single non-list block.
+fn render_block(block: &ArticleBody, mark_handler: &Option<Callback<UnknownMark, Html>>) -> Html {
+    match block.content_type.as_str() {
+        "block" => {
+            let children = render_children(block, mark_handler);
+            match block.style.as_deref().unwrap_or("normal") {
+                "h1" => {
+                    html! { <h1 class="text-4xl font-bold text-gray-900 mt-8 mb-4">{ children }</h1> }
+                }
+                "h2" => {
+                    html! { <h2 class="text-3xl font-bold text-gray-900 mt-8 mb-4">{ children }</h2> }
+                }
+                "h3" => {
+                    html! { <h3 class="text-2xl font-semibold text-gray-900 mt-6 mb-3">{ children }</h3> }
+                }
+                "h4" => {
+                    html! { <h4 class="text-xl font-semibold text-gray-900 mt-6 mb-3">{ children }</h4> }
+                }
+                "h5" => {
+                    html! { <h5 class="text-lg font-semibold text-gray-900 mt-4 mb-2">{ children }</h5> }
+                }
+                "h6" => {
+                    html! { <h6 class="text-base font-semibold text-gray-900 mt-4 mb-2">{ children }</h6> }
+                }
+                "blockquote" => html! {
+                    <blockquote class="border-l-4 border-gray-300 pl-4 italic text-gray-600 mb-4">
+                        { children }
+                    </blockquote>
+                },
+                _ => html! { <p class="mb-4 text-gray-700">{ children }</p> },
+            }
+        }
+        "image" => match &block.asset {
+            Some(asset) => html! {
+                <figure class="my-6">
+                    <img
+                        src={asset.url.clone()}
+                        alt={block.alt.clone().unwrap_or_default()}
+                        class="w-full h-auto rounded-lg shadow-md"
+                        loading="lazy"
+                    />
+                </figure>
+            },
+            None => html! {},
+        },
+        _ => html! {},
+    }
+}
+
+// A span's text, or a mark wrapping a run of neighbouring spans that share it.
+#[derive(Debug, PartialEq)]
+enum MarkNode {
+    Text(String),
+    Mark(String, Vec<MarkNode>),
+}
+
+// Renders all spans of a block together, so they end up inside the same element.
+fn render_children(
+    block: &ArticleBody,
+    mark_handler: &Option<Callback<UnknownMark, Html>>,
+) -> Html {
+    let spans = block
+        .children
+        .iter()
+        .flatten()
+        .filter(|c| c.content_type == "span")
+        .collect::<Vec<_>>();
+
+    html! {
+        { for build_mark_tree(&spans).into_iter().map(|node| render_mark_node(node, block, mark_handler)) }
+    }
+}
+
+// Groups spans into a tree of marks, so that e.g. a link covering a bold and a plain span
+// becomes one `<a>` instead of two. Marks that last for more spans are opened first.
+fn build_mark_tree(spans: &[&ArticleBodyChild]) -> Vec<MarkNode> {
+    // Each entry is an open mark and the nodes collected inside it so far. The first entry is the root.
+    let mut stack: Vec<(Option<String>, Vec<MarkNode>)> = vec![(None, Vec::new())];
+
+    for (i, span) in spans.iter().enumerate() {
+        let run_length = |mark: &String| {
+            spans[i..]
+                .iter()
+                .take_while(|s| s.marks.contains(mark))
+                .count()
+        };
+        let mut marks = span.marks.clone();
+        marks.sort_by_key(|mark| std::cmp::Reverse(run_length(mark)));
+
+        // Keep open marks as long as this span still has them, close the rest.
+        let keep = stack[1..]
+            .iter()
+            .take_while(|(mark, _)| mark.as_ref().is_some_and(|m| span.marks.contains(m)))
+            .count()
+            + 1;
+        while stack.len() > keep {
+            close_mark(&mut stack);
+        }
+
+        for mark in marks {
+            if !stack.iter().any(|(open, _)| open.as_ref() == Some(&mark)) {
+                stack.push((Some(mark), Vec::new()));
+            }
+        }
+
+        if let Some((_, nodes)) = stack.last_mut() {
+            nodes.push(MarkNode::Text(span.text.clone()));
+        }
+    }
+
+    while stack.len() > 1 {
+        close_mark(&mut stack);
+    }
+    stack.pop().map(|(_, nodes)| nodes).unwrap_or_default()
+}
+
+fn close_mark(stack: &mut Vec<(Option<String>, Vec<MarkNode>)>) {
+    if let Some((Some(mark), nodes)) = stack.pop() {
+        if let Some((_, parent)) = stack.last_mut() {
+            parent.push(MarkNode::Mark(mark, nodes));
+        }
+    }
+}
+
+fn render_mark_node(
+    node: MarkNode,
+    block: &ArticleBody,
+    mark_handler: &Option<Callback<UnknownMark, Html>>,
+) -> Html {
+    let (mark, nodes) = match node {
+        MarkNode::Text(text) => return html! { { text } },
+        MarkNode::Mark(mark, nodes) => (mark, nodes),
+    };
+    let children = html! {
+        { for nodes.into_iter().map(|node| render_mark_node(node, block, mark_handler)) }
+    };
+
+    // Decorators are stored by name, annotations by the `_key` of their entry in `markDefs`.
+    match mark.as_str() {
+        "strong" => return html! { <strong class="font-semibold">{ children }</strong> },
+        "em" => return html! { <em>{ children }</em> },
+        "code" => {
+            return html! {
+                <code class="px-1 py-0.5 bg-gray-100 rounded text-sm font-mono">{ children }</code>
+            }
+        }
+        "strike-through" => return html! { <s>{ children }</s> },
+        "underline" => return html! { <u>{ children }</u> },
+        _ => {}
+    }
+
+    let definition = block.mark_defs.iter().find(|def| def.key == mark).cloned();
+    if let Some(MarkDef {
+        content_type,
+        href: Some(href),
+        ..
+    }) = &definition
+    {
+        if content_type == "link" && is_safe_href(href) {
+            return html! {
+                <a href={href.clone()} class="text-blue-600 underline hover:text-blue-800">{ children }</a>
+            };
+        }
+    }
+
+    match mark_handler {
+        Some(handler) => handler.emit(UnknownMark {
+            mark,
+            definition,
+            children,
+        }),
+        None => children,
+    }
+}
+
+// Only let through links that can't run script when clicked.
+fn is_safe_href(href: &str) -> bool {
+    let href = href.trim().to_ascii_lowercase();
+    ["http://", "https://", "mailto:", "tel:", "/", "#"]
+        .iter()
+        .any(|prefix| href.starts_with(prefix))
+}
+
+fn list_level(block: &ArticleBody) -> u32 {
+    block.level.unwrap_or(1)
+}
+
+// A list of one type ("bullet" or "number") and its items.
+struct List<'a> {
+    kind: Option<String>,
+    items: Vec<ListItem<'a>>,
+}
+
+// An item of a `List`, with the lists nested in it. `block` is `None` for an item that only holds
+// deeper items, as when a run of items starts deeper than it goes on.
+struct ListItem<'a> {
+    block: Option<&'a ArticleBody>,
+    nested: Vec<List<'a>>,
+}
+
+// Turns a run of list items into nested lists.
+// Items deeper than the current level go inside the item that precedes them.
+fn group_list<'a>(items: &[&'a ArticleBody]) -> Vec<List<'a>> {
+    let level = items.iter().map(|b| list_level(b)).min().unwrap_or(1);
+
+    let mut lists = Vec::new();
+    let mut i = 0;
+    while i < items.len() {
+        let kind = items[i].list_item.clone();
+        let mut entries = Vec::new();
+
+        while i < items.len() {
+            let item = items[i];
+            let at_level = list_level(item) == level;
+            // A different list type on the same level starts a new list.
+            if at_level && item.list_item != kind {
+                break;
+            }
+
+            let block = if at_level {
+                i += 1;
+                Some(item)
+            } else {
+                None
+            };
+
+            let start = i;
+            while i < items.len() && list_level(items[i]) > level {
+                i += 1;
+            }
+            entries.push(ListItem {
+                block,
+                nested: group_list(&items[start..i]),
+            });
+        }
+
+        lists.push(List {
+            kind,
+            items: entries,
+        });
+    }
+    lists
+}
+
+// Renders grouped lists as nested `<ul>`/`<ol>` elements.
+fn render_lists(lists: Vec<List>, mark_handler: &Option<Callback<UnknownMark, Html>>) -> Html {
+    let rendered = lists.into_iter().map(|list| {
+        let entries = list.items.into_iter().map(|item| {
+            let content = match item.block {
+                Some(block) => render_children(block, mark_handler),
+                None => html! {},
+            };
+            let nested = render_lists(item.nested, mark_handler);
+            html! {
+                <li class="mb-1">{ content }{ nested }</li>
+            }
+        });
+        match list.kind.as_deref() {
+            Some("number") => html! {
+                <ol class="list-decimal pl-6 mb-4 text-gray-700">{ for entries }</ol>
+            },
+            _ => html! {
+                <ul class="list-disc pl-6 mb-4 text-gray-700">{ for entries }</ul>
+            },
+        }
+    });
+
+    html! { <>{ for rendered }</> }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn span(text: &str, marks: &[&str]) -> ArticleBodyChild {
+        ArticleBodyChild {
+            content_type: "span".to_string(),
+            key: text.to_string(),
+            text: text.to_string(),
+            marks: marks.iter().map(|mark| mark.to_string()).collect(),
+        }
+    }
+
+    fn text(text: &str) -> MarkNode {
+        MarkNode::Text(text.to_string())
+    }
+
+    fn mark(mark: &str, nodes: Vec<MarkNode>) -> MarkNode {
+        MarkNode::Mark(mark.to_string(), nodes)
+    }
+
+    fn item(text: &str, list_item: &str, level: u32) -> ArticleBody {
+        ArticleBody {
+            content_type: "block".to_string(),
+            style: Some("normal".to_string()),
+            key: text.to_string(),
+            asset: None,
+            alt: None,
+            children: Some(vec![span(text, &[])]),
+            list_item: Some(list_item.to_string()),
+            level: Some(level),
+            mark_defs: Vec::new(),
+        }
+    }
+
+    // The lists as e.g. `bullet[a number[b] c]`, for items whose only span is their key.
+    fn outline(lists: &[List]) -> String {
+        lists
+            .iter()
+            .map(|list| {
+                let items = list
+                    .items
+                    .iter()
+                    .map(|item| {
+                        let text = item.block.map_or("_", |block| block.key.as_str());
+                        match item.nested.as_slice() {
+                            [] => text.to_string(),
+                            nested => format!("{} {}", text, outline(nested)),
+                        }
+                    })
+                    .collect::<Vec<_>>();
+                format!(
+                    "{}[{}]",
+                    list.kind.as_deref().unwrap_or(""),
+                    items.join(" ")
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    fn grouped(blocks: &[ArticleBody]) -> String {
+        outline(&group_list(&blocks.iter().collect::<Vec<_>>()))
+    }
+
+    #[test]
+    fn a_mark_shared_by_neighbouring_spans_wraps_them_once() {
+        let spans = [
+            span("a ", &["link"]),
+            span("bold", &["strong", "link"]),
+            span(" b", &[]),
+        ];
+        assert_eq!(
+            build_mark_tree(&spans.iter().collect::<Vec<_>>()),
+            vec![
+                mark("link", vec![text("a "), mark("strong", vec![text("bold")])]),
+                text(" b"),
+            ]
+        );
+    }
+
+    #[test]
+    fn longer_marks_are_opened_first() {
+        let spans = [span("a", &["em", "strong"]), span("b", &["strong"])];
+        assert_eq!(
+            build_mark_tree(&spans.iter().collect::<Vec<_>>()),
+            vec![mark("strong", vec![mark("em", vec![text("a")]), text("b")])]
+        );
+    }
+
+    #[test]
+    fn overlapping_marks_are_split() {
+        let spans = [
+            span("a", &["em"]),
+            span("b", &["em", "strong"]),
+            span("c", &["strong"]),
+        ];
+        assert_eq!(
+            build_mark_tree(&spans.iter().collect::<Vec<_>>()),
+            vec![
+                mark("em", vec![text("a"), mark("strong", vec![text("b")])]),
+                mark("strong", vec![text("c")]),
+            ]
+        );
+    }
+
+    #[test]
+    fn deeper_items_nest_in_the_item_before_them() {
+        let blocks = [
+            item("a", "bullet", 1),
+            item("b", "number", 2),
+            item("c", "number", 2),
+            item("d", "bullet", 3),
+            item("e", "bullet", 1),
+        ];
+        assert_eq!(grouped(&blocks), "bullet[a number[b c bullet[d]] e]");
+    }
+
+    #[test]
+    fn another_list_type_starts_another_list() {
+        let blocks = [
+            item("a", "bullet", 1),
+            item("b", "number", 1),
+            item("c", "number", 1),
+        ];
+        assert_eq!(grouped(&blocks), "bullet[a] number[b c]");
+    }
+
+    #[test]
+    fn items_starting_deeper_get_an_empty_parent() {
+        let blocks = [item("a", "bullet", 2), item("b", "bullet", 1)];
+        assert_eq!(grouped(&blocks), "bullet[_ bullet[a] b]");
+    }
+}
@@ -0,0 +1,110 @@
+use serde::Deserialize;
+use yew::{function_component, html, Html, Properties};
+use yew_router::prelude::*;
+
+use super::topic_chips::{Category, TopicChips};
+use crate::app::Route;
+
+// The classes of the grid the cards are laid out in.
+pub const POST_GRID_CLASSES: &str =
+    "grid grid-cols-[repeat(auto-fill,_minmax(300px,_1fr))] gap-6 list-none p-0 m-0";
+
+// This struct represents individual blog posts, as far as a card shows them.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct BlogPost {
+    pub slug: BlogSlug,
+    pub title: String,
+    #[serde(rename = "logoUrl")]
+    pub logo_url: Option<String>,
+    #[serde(default)]
+    pub categories: Vec<Category>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct BlogSlug {
+    pub current: String,
+}
+
+#[derive(PartialEq, Properties)]
+pub struct PostCardProps {
+    pub post: BlogPost,
+    // Shown between the title and the link, e.g. an excerpt.
+    #[prop_or_default]
+    pub children: Html,
+}
+
+// One post in a list: its logo, title and a link to the article.
+#[function_component]
+pub fn PostCard(props: &PostCardProps) -> Html {
+    let post = &props.post;
+
+    html! {
+        <li
+            class="
+                bg-white
+                p-6
+                rounded-xl
+                shadow-lg
+                transition-transform
+                duration-300
+                ease-in-out
+                cursor-pointer
+                hover:shadow-xl
+            "
+        >
+            {
+                if let Some(url) = &post.logo_url {
+                    html! {
+                        <img
+                            src={url.clone()}
+                            alt="Blog image"
+                            class="w-full h-auto rounded-xl mb-4"
+                        />
+                    }
+                } else {
+                    html! {}
+                }
+            }
+            <h3 class="text-lg text-gray-900 font-bold mb-3 mt-0">
+                { &post.title }
+            </h3>
+            <TopicChips categories={post.categories.clone()} tags={post.tags.clone()} />
+            { props.children.clone() }
+            <Link<Route>
+                to={Route::Article { slug: post.slug.current.clone() }}
+                classes="
+                    inline-block
+                    px-4
+                    py-2
+                    bg-blue-500
+                    text-white
+                    text-sm
+                    font-medium
+                    rounded-md
+                    shadow-sm
+                    hover:bg-blue-600
+                    transition-colors
+                "
+            >
+                { "Read More" }
+            </Link<Route>>
+        </li>
+    }
+}
+
+#[derive(PartialEq, Properties)]
+pub struct PostGridProps {
+    pub posts: Vec<BlogPost>,
+}
+
+// The posts as a grid of cards.
+#[function_component]
+pub fn PostGrid(props: &PostGridProps) -> Html {
+    html! {
+        <ul class={POST_GRID_CLASSES}>
+            { for props.posts.iter().map(|post| html! { <PostCard post={post.clone()} /> }) }
+        </ul>
+    }
+}
@@ -0,0 +1,77 @@
+use gloo_console::{error, log};
+use yew::{function_component, html, use_effect_with, Callback, Children, Html, Properties};
+use yew_router::prelude::*;
+
+use super::Forbidden;
+use crate::{
+    app::{LoginQuery, Route},
+    helpers::{auth_context::use_auth, roles::Role, session::use_idle_timeout},
+};
+
+#[derive(PartialEq, Properties)]
+pub struct RequireAuthProps {
+    // The protected page.
+    pub children: Children,
+    // The least role that may see it. Every logged-in user is at least a reader.
+    #[prop_or_default]
+    pub role: Role,
+}
+
+// The page the user is on, including its query string.
+fn current_path() -> String {
+    web_sys::window()
+        .map(|window| window.location())
+        .and_then(|location| {
+            Some(format!(
+                "{}{}",
+                location.pathname().ok()?,
+                location.search().ok()?
+            ))
+        })
+        .unwrap_or_else(|| "/".to_string())
+}
+
+// Send the user to the login page, asking it to bring them back to the current page afterwards.
+fn redirect_to_login(navigator: &Navigator) {
+    let query = LoginQuery {
+        next: Some(current_path()),
+    };
+    if let Err(err) = navigator.replace_with_query(&Route::Login, &query) {
+        error!("Couldn't redirect to login", err.to_string());
+        navigator.replace(&Route::Login);
+    }
+}
+
+// Only renders its children for logged-in users. Everyone else is sent to the login page,
+// which returns them here once they have logged in. Users without `role` see a forbidden view. Also logs out users who have been idle for too long.
+#[function_component]
+pub fn RequireAuth(props: &RequireAuthProps) -> Html {
+    let navigator = use_navigator().expect("Couldn't get the navigator");
+    let auth = use_auth();
+    let logged_in = auth.is_logged_in();
+    let allowed = auth.has_role(props.role);
+
+    {
+        let navigator = navigator.clone();
+        use_effect_with(logged_in, move |logged_in| {
+            if !*logged_in {
+                log!("Not logged in");
+                redirect_to_login(&navigator);
+            }
+            || ()
+        });
+    }
+    // Once the session runs out, send the user back to log in again and then return here.
+    use_idle_timeout(Callback::from(move |_| {
+        auth.expire();
+        redirect_to_login(&navigator);
+    }));
+
+    if allowed {
+        html! { <>{ props.children.clone() }</> }
+    } else if logged_in {
+        html! { <Forbidden required={props.role} /> }
+    } else {
+        html! {}
+    }
+}
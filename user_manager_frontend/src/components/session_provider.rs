@@ -0,0 +1,20 @@
+use yew::{function_component, html, Children, ContextProvider, Html, Properties};
+
+use crate::helpers::auth_context::{use_auth_state, AuthContext};
+
+#[derive(PartialEq, Properties)]
+pub struct SessionProviderProps {
+    pub children: Children,
+}
+
+// Makes the `AuthContext` available to everything inside it, so any component can call `use_auth()`.
+#[function_component]
+pub fn SessionProvider(props: &SessionProviderProps) -> Html {
+    let auth = use_auth_state();
+
+    html! {
+        <ContextProvider<AuthContext> context={auth}>
+            { props.children.clone() }
+        </ContextProvider<AuthContext>>
+    }
+}
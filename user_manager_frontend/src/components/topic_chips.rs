@@ -0,0 +1,55 @@
+use serde::Deserialize;
+use yew::{function_component, html, Html, Properties};
+use yew_router::prelude::*;
+
+use crate::app::Route;
+
+// A category a post is filed under.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Category {
+    pub title: String,
+    pub slug: String,
+}
+
+#[derive(PartialEq, Properties)]
+pub struct TopicChipsProps {
+    pub categories: Vec<Category>,
+    pub tags: Vec<String>,
+}
+
+// The categories and tags of a post, each linking to the other posts about it.
+#[function_component]
+pub fn TopicChips(props: &TopicChipsProps) -> Html {
+    if props.categories.is_empty() && props.tags.is_empty() {
+        return html! {};
+    }
+
+    html! {
+        <ul class="flex flex-wrap gap-2 list-none p-0 mb-4">
+            {
+                for props.categories.iter().map(|category| html! {
+                    <li>
+                        <Link<Route>
+                            to={Route::Category { slug: category.slug.clone() }}
+                            classes="inline-block px-3 py-1 bg-blue-100 text-blue-800 text-xs font-semibold rounded-full hover:bg-blue-200"
+                        >
+                            { &category.title }
+                        </Link<Route>>
+                    </li>
+                })
+            }
+            {
+                for props.tags.iter().map(|tag| html! {
+                    <li>
+                        <Link<Route>
+                            to={Route::Tag { tag: tag.clone() }}
+                            classes="inline-block px-3 py-1 bg-gray-100 text-gray-700 text-xs rounded-full hover:bg-gray-200"
+                        >
+                            { format!("#{}", tag) }
+                        </Link<Route>>
+                    </li>
+                })
+            }
+        </ul>
+    }
+}
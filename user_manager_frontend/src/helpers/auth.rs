@@ -0,0 +1,1478 @@
+use std::{cell::RefCell, fmt, future::Future, pin::Pin, rc::Rc};
+
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use gloo_console::{error, log};
+use gloo_net::http::{Request, RequestBuilder, Response};
+use gloo_storage::{LocalStorage, Storage};
+use serde::{Deserialize, Serialize};
+
+use super::{
+    fetch::FetchError,
+    login_throttle::describe_wait,
+    oidc::{IdTokenClaims, OidcError},
+    password::{check_no_password, hash_token, random_token, PasswordCheck},
+    roles::Role,
+    totp::TotpSetup,
+    user_manager::{UserManager, UserProfile},
+    user_repository::{ManagedUser, UserPage, UserRepository},
+};
+
+/// The future returned by every `AuthProvider` operation.
+pub type AuthFuture<'a, T> = Pin<Box<dyn Future<Output = Result<T, AuthError>> + 'a>>;
+
+/// Everything that can go wrong while talking to an auth provider.
+#[derive(Debug, Clone, PartialEq)]
+pub enum AuthError {
+    /// Wrong email or password, or a token the provider no longer accepts.
+    InvalidCredentials,
+    /// We don't have a token to send, so there is nothing to refresh or log out of.
+    NotLoggedIn,
+    /// Someone already signed up with that email or username.
+    AlreadyRegistered,
+    /// Too many failed logins for that email. It may try again after the wait.
+    TooManyAttempts(Duration),
+    /// An admin disabled the account.
+    AccountDisabled,
+    /// There is no account with that username.
+    UnknownUser,
+    /// The two-factor code or recovery code is wrong or was already used.
+    InvalidCode,
+    /// The password reset link is unknown, was already used or has expired.
+    InvalidResetToken,
+    /// Logged in, but without the role this needs.
+    Forbidden(Role),
+    /// The change would take away the admin's own access, or leave no enabled admin.
+    AdminLockout,
+    /// Logging in through the identity provider didn't work out.
+    SingleSignOn(OidcError),
+    /// The provider couldn't be reached or answered with something unexpected.
+    Request(FetchError),
+}
+
+impl fmt::Display for AuthError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AuthError::InvalidCredentials => write!(f, "Login failed: Invalid email or password."),
+            AuthError::NotLoggedIn => write!(f, "You are not logged in."),
+            AuthError::AlreadyRegistered => {
+                write!(f, "An account with that email or username already exists.")
+            }
+            AuthError::TooManyAttempts(wait) => write!(
+                f,
+                "Too many failed login attempts. Please try again in {}.",
+                describe_wait(*wait)
+            ),
+            AuthError::AccountDisabled => write!(f, "This account has been disabled."),
+            AuthError::UnknownUser => write!(f, "There is no such user."),
+            AuthError::InvalidCode => write!(f, "That code isn't right. Please try again."),
+            AuthError::InvalidResetToken => {
+                write!(
+                    f,
+                    "This reset link is invalid or has expired. Please ask for a new one."
+                )
+            }
+            AuthError::Forbidden(role) => {
+                write!(f, "You need the {} role to do that.", role)
+            }
+            AuthError::AdminLockout => write!(
+                f,
+                "You can't demote, disable or delete yourself or the last admin."
+            ),
+            AuthError::SingleSignOn(err) => write!(f, "{}", err),
+            AuthError::Request(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl From<FetchError> for AuthError {
+    fn from(err: FetchError) -> Self {
+        AuthError::Request(err)
+    }
+}
+
+impl From<OidcError> for AuthError {
+    fn from(err: OidcError) -> Self {
+        AuthError::SingleSignOn(err)
+    }
+}
+
+impl From<gloo_net::Error> for AuthError {
+    fn from(err: gloo_net::Error) -> Self {
+        AuthError::Request(err.into())
+    }
+}
+
+/// What a provider hands back after a successful login or refresh.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AuthSession {
+    pub token: String,
+    pub user: UserProfile,
+    /// When the token stops being accepted, if the provider tells us.
+    #[serde(default)]
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+/// What a provider answers a correct password with.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum LoginOutcome {
+    /// The account has two-factor authentication on. Pass `challenge` and the user's code to
+    /// `AuthProvider::verify_second_factor` to get the session.
+    SecondFactor {
+        challenge: String,
+        user: UserProfile,
+    },
+    /// Logged in.
+    Session(AuthSession),
+}
+
+/// A backend that can check credentials and hand out tokens.
+pub trait AuthProvider {
+    /// Create a new account that can log in with `profile.email` and `password`.
+    fn register<'a>(&'a self, profile: &'a UserProfile, password: &'a str) -> AuthFuture<'a, ()>;
+
+    /// Check the credentials and start a new session, or ask for a second factor first.
+    fn login<'a>(&'a self, email: &'a str, password: &'a str) -> AuthFuture<'a, LoginOutcome>;
+
+    /// Finish a login that needed a second factor. `code` is a TOTP code or an unused recovery code.
+    fn verify_second_factor<'a>(
+        &'a self,
+        challenge: &'a str,
+        code: &'a str,
+    ) -> AuthFuture<'a, AuthSession>;
+
+    /// Start a session for someone an identity provider vouched for with `id_token`, whose
+    /// `claims` have already been checked against this login. Providers find the account by the
+    /// claims' `iss` and `sub`, or make a reader account for people they haven't seen before.
+    fn login_with_id_token<'a>(
+        &'a self,
+        id_token: &'a str,
+        claims: &'a IdTokenClaims,
+    ) -> AuthFuture<'a, AuthSession>;
+
+    /// Whether the account `token` belongs to has two-factor authentication on.
+    fn two_factor_enabled<'a>(&'a self, token: &'a str) -> AuthFuture<'a, bool>;
+
+    /// Make a new TOTP secret for the account `token` belongs to. It only takes effect once
+    /// `confirm_two_factor` is called with a code for it.
+    fn begin_two_factor_setup<'a>(&'a self, token: &'a str) -> AuthFuture<'a, TotpSetup>;
+
+    /// Turn on two-factor authentication with the secret from `begin_two_factor_setup`, if `code`
+    /// matches it. Answers with new recovery codes, which are only ever shown this once.
+    fn confirm_two_factor<'a>(
+        &'a self,
+        token: &'a str,
+        code: &'a str,
+    ) -> AuthFuture<'a, Vec<String>>;
+
+    /// Turn two-factor authentication off, if `code` is a current code or a recovery code.
+    fn disable_two_factor<'a>(&'a self, token: &'a str, code: &'a str) -> AuthFuture<'a, ()>;
+
+    /// End the session belonging to `token`.
+    fn logout<'a>(&'a self, token: &'a str) -> AuthFuture<'a, ()>;
+
+    /// Save new details for the account that `token` belongs to. The username and role stay as they are.
+    /// Answers with the updated session, which may carry a new token.
+    fn update_profile<'a>(
+        &'a self,
+        token: &'a str,
+        profile: &'a UserProfile,
+    ) -> AuthFuture<'a, AuthSession>;
+
+    /// Replace the password of the account `token` belongs to, if `current_password` is right.
+    /// Ends the account's other sessions and answers with a new one for this browser.
+    fn change_password<'a>(
+        &'a self,
+        token: &'a str,
+        current_password: &'a str,
+        new_password: &'a str,
+    ) -> AuthFuture<'a, AuthSession>;
+
+    /// Send the owner of `email` a link to reset their password.
+    /// Succeeds whether or not there is such an account, so it can't be used to find accounts.
+    fn request_password_reset<'a>(&'a self, email: &'a str) -> AuthFuture<'a, ()>;
+
+    /// Set a new password with the token from a reset link. Each token works once.
+    /// Every session of the account ends.
+    fn reset_password<'a>(&'a self, reset_token: &'a str, password: &'a str) -> AuthFuture<'a, ()>;
+
+    /// Look up the account that `token` belongs to.
+    fn current_user<'a>(&'a self, token: &'a str) -> AuthFuture<'a, UserProfile>;
+
+    /// Exchange `token` for a fresh session.
+    /// Providers that don't rotate tokens can keep this default, which only re-checks the token.
+    fn refresh<'a>(&'a self, token: &'a str) -> AuthFuture<'a, AuthSession> {
+        Box::pin(async move {
+            Ok(AuthSession {
+                token: token.to_string(),
+                user: self.current_user(token).await?,
+                expires_at: None,
+            })
+        })
+    }
+}
+
+/// The provider the app uses. Builds that set `AUTH_ENDPOINT` talk to that server,
+/// everything else uses the local provider with the built-in test account, which is not secure
+/// (see `LocalAuthProvider`). Production builds must set `AUTH_ENDPOINT`.
+pub fn default_provider() -> Rc<dyn AuthProvider> {
+    match option_env!("AUTH_ENDPOINT") {
+        Some(endpoint) => Rc::new(HttpAuthProvider::new(endpoint)),
+        None => Rc::new(LocalAuthProvider::default()),
+    }
+}
+
+// LocalStorage key for the accounts of the local provider.
+const LOCAL_ACCOUNTS_KEY: &str = "local_accounts";
+// LocalStorage key for the password resets it has handed out.
+const LOCAL_RESETS_KEY: &str = "local_password_resets";
+// How long a password reset link works.
+const RESET_LINK_MINUTES: i64 = 60;
+// The local account to make an admin, named by `LOCAL_ADMIN_EMAIL` at build time. Nobody is an admin
+// otherwise, and release builds ignore it.
+const LOCAL_ADMIN_EMAIL: Option<&str> = if cfg!(debug_assertions) {
+    option_env!("LOCAL_ADMIN_EMAIL")
+} else {
+    None
+};
+
+/// A password reset the local provider has handed out. Only the hash of the token is kept.
+#[derive(Clone, Serialize, Deserialize)]
+struct PasswordReset {
+    email: String,
+    token_hash: String,
+    expires_at: DateTime<Utc>,
+}
+
+/// Checks credentials in the browser against accounts kept in LocalStorage.
+/// Also the `UserRepository` for those accounts. Password reset links are written to the console
+/// instead of being emailed.
+///
+/// Not secure, and never to be the provider of a production build: the password hashes, emails,
+/// birthdays and two-factor secrets of every account are in LocalStorage, where any script on the
+/// page and anyone with the browser can read and change them. Tokens are random and only their
+/// hashes are stored, but whoever can change the storage can still add their own.
+/// Meant for development and tests.
+pub struct LocalAuthProvider {
+    accounts: RefCell<Vec<UserManager>>,
+    resets: RefCell<Vec<PasswordReset>>,
+    /// Where the accounts are saved. `None` keeps them in memory only.
+    storage_key: Option<&'static str>,
+}
+
+impl Default for LocalAuthProvider {
+    /// The accounts registered in this browser, or the single test account this app
+    /// has always shipped with if nobody has signed up yet. The test account is a reader.
+    /// Development builds make the account named by `LOCAL_ADMIN_EMAIL` an admin.
+    fn default() -> Self {
+        let provider = match LocalStorage::get::<Vec<UserManager>>(LOCAL_ACCOUNTS_KEY) {
+            Ok(accounts) => {
+                let provider = Self::new(accounts).stored_at(LOCAL_ACCOUNTS_KEY);
+                if let Ok(resets) = LocalStorage::get(LOCAL_RESETS_KEY) {
+                    provider.resets.replace(resets);
+                }
+                provider
+            }
+            Err(_) => {
+                // Hashing is deliberately slow, so the test account is saved instead of being hashed on every use.
+                let provider = Self::new(vec![UserManager::new(
+                    "TestUser".to_string(),
+                    "test@example.com".to_string(),
+                    "password123".to_string(),
+                    "Test Person".to_string(),
+                    NaiveDate::from_ymd_opt(1990, 1, 1).expect("Couldn't parse naive date"),
+                )])
+                .stored_at(LOCAL_ACCOUNTS_KEY);
+                provider.save();
+                provider
+            }
+        };
+
+        if let Some(email) = LOCAL_ADMIN_EMAIL {
+            if provider.make_admin(email) {
+                log!("Made the account from LOCAL_ADMIN_EMAIL an admin.");
+            }
+        }
+        provider
+    }
+}
+
+impl LocalAuthProvider {
+    /// A provider for `accounts` that only keeps them in memory, e.g. for tests.
+    pub fn new(accounts: Vec<UserManager>) -> Self {
+        Self {
+            accounts: RefCell::new(accounts),
+            resets: RefCell::default(),
+            storage_key: None,
+        }
+    }
+
+    /// Save every change to the accounts in LocalStorage under `key`. Password resets are saved too.
+    pub fn stored_at(self, key: &'static str) -> Self {
+        Self {
+            storage_key: Some(key),
+            ..self
+        }
+    }
+
+    /// Make the account with `email` an admin, unless it already is one.
+    /// Answers whether anything changed.
+    fn make_admin(&self, email: &str) -> bool {
+        let username = self
+            .accounts
+            .borrow()
+            .iter()
+            .map(|account| account.profile())
+            .find(|profile| {
+                profile.email.eq_ignore_ascii_case(email.trim())
+                    && !profile.role.includes(Role::Admin)
+            })
+            .map(|profile| profile.username);
+        let Some(username) = username else {
+            return false;
+        };
+        self.modify(&username, |account| {
+            account.set_profile(UserProfile {
+                role: Role::Admin,
+                ..account.profile()
+            })
+        })
+        .is_ok()
+    }
+
+    fn save(&self) {
+        let Some(key) = self.storage_key else {
+            return;
+        };
+        if let Err(err) = LocalStorage::set(key, &*self.accounts.borrow()) {
+            error!("Couldn't save local accounts:", err.to_string());
+        }
+        if let Err(err) = LocalStorage::set(LOCAL_RESETS_KEY, &*self.resets.borrow()) {
+            error!("Couldn't save local password resets:", err.to_string());
+        }
+    }
+
+    /// Start a session for the account. The caller saves the accounts.
+    fn start_session(account: &mut UserManager) -> AuthSession {
+        AuthSession {
+            token: account.start_session(),
+            user: account.profile(),
+            expires_at: None,
+        }
+    }
+
+    /// The account `token` belongs to. Tokens of disabled accounts are no longer accepted.
+    fn profile_for(&self, token: &str) -> Result<UserProfile, AuthError> {
+        self.accounts
+            .borrow()
+            .iter()
+            .find(|account| account.has_session(token) && !account.is_disabled())
+            .map(|account| account.profile())
+            .ok_or(AuthError::InvalidCredentials)
+    }
+
+    /// The profile of the admin `token` belongs to.
+    fn require_admin(&self, token: &str) -> Result<UserProfile, AuthError> {
+        let profile = self.profile_for(token)?;
+        if profile.role.includes(Role::Admin) {
+            Ok(profile)
+        } else {
+            Err(AuthError::Forbidden(Role::Admin))
+        }
+    }
+
+    /// Refuse to let `admin` take the account called `username` out of the admins (by demoting,
+    /// disabling or deleting it) if that is their own account or the last enabled admin.
+    fn keep_an_admin(&self, admin: &UserProfile, username: &str) -> Result<(), AuthError> {
+        let other_admins = self.accounts.borrow().iter().any(|account| {
+            let profile = account.profile();
+            profile.username != username
+                && profile.role.includes(Role::Admin)
+                && !account.is_disabled()
+        });
+        if admin.username == username || !other_admins {
+            Err(AuthError::AdminLockout)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Whether another account than the one called `except` already uses the email or username.
+    fn is_taken(&self, profile: &UserProfile, except: Option<&str>) -> bool {
+        self.accounts.borrow().iter().any(|account| {
+            let existing = account.profile();
+            Some(existing.username.as_str()) != except
+                && (existing.email.eq_ignore_ascii_case(&profile.email)
+                    || existing.username.eq_ignore_ascii_case(&profile.username))
+        })
+    }
+
+    fn managed(account: &UserManager) -> ManagedUser {
+        ManagedUser {
+            profile: account.profile(),
+            disabled: account.is_disabled(),
+        }
+    }
+
+    /// Change the account called `username` and save the accounts.
+    fn modify<T>(
+        &self,
+        username: &str,
+        change: impl FnOnce(&mut UserManager) -> T,
+    ) -> Result<T, AuthError> {
+        let mut accounts = self.accounts.borrow_mut();
+        let account = accounts
+            .iter_mut()
+            .find(|account| account.profile().username == username)
+            .ok_or(AuthError::UnknownUser)?;
+        let result = change(account);
+        drop(accounts);
+        self.save();
+        Ok(result)
+    }
+
+    fn add_account(&self, profile: &UserProfile, password: &str) -> Result<ManagedUser, AuthError> {
+        if self.is_taken(profile, None) {
+            return Err(AuthError::AlreadyRegistered);
+        }
+
+        let account = UserManager::new(
+            profile.username.clone(),
+            profile.email.clone(),
+            password.to_string(),
+            profile.name.clone(),
+            profile.birthday,
+        )
+        .with_role(profile.role);
+        let user = Self::managed(&account);
+        self.accounts.borrow_mut().push(account);
+        self.save();
+        Ok(user)
+    }
+}
+
+impl AuthProvider for LocalAuthProvider {
+    fn register<'a>(&'a self, profile: &'a UserProfile, password: &'a str) -> AuthFuture<'a, ()> {
+        Box::pin(async move {
+            // Signing up never makes anyone more than a reader.
+            let profile = UserProfile {
+                role: Role::default(),
+                ..profile.clone()
+            };
+            self.add_account(&profile, password)?;
+            Ok(())
+        })
+    }
+
+    fn login<'a>(&'a self, email: &'a str, password: &'a str) -> AuthFuture<'a, LoginOutcome> {
+        Box::pin(async move {
+            let email = email.trim();
+            let mut accounts = self.accounts.borrow_mut();
+            let Some(account) = accounts
+                .iter_mut()
+                .find(|account| account.get_email().eq_ignore_ascii_case(email))
+            else {
+                // Hash anyway, so unknown emails take as long to refuse as wrong passwords.
+                check_no_password(password);
+                return Err(AuthError::InvalidCredentials);
+            };
+
+            match account.check_password(password) {
+                PasswordCheck::Invalid => return Err(AuthError::InvalidCredentials),
+                // Only said once the password is right, so it doesn't reveal which accounts exist.
+                _ if account.is_disabled() => return Err(AuthError::AccountDisabled),
+                PasswordCheck::Valid => {}
+                // Old or too cheap hash: we have the plain password right now, so store a fresh hash.
+                PasswordCheck::ValidNeedsRehash => account.set_password(password),
+            }
+
+            let outcome = if account.two_factor().is_enabled() {
+                LoginOutcome::SecondFactor {
+                    challenge: account.two_factor_mut().begin_login(Utc::now()),
+                    user: account.profile(),
+                }
+            } else {
+                LoginOutcome::Session(Self::start_session(account))
+            };
+            drop(accounts);
+            self.save();
+            Ok(outcome)
+        })
+    }
+
+    fn verify_second_factor<'a>(
+        &'a self,
+        challenge: &'a str,
+        code: &'a str,
+    ) -> AuthFuture<'a, AuthSession> {
+        Box::pin(async move {
+            let now = Utc::now();
+            // Unknown, expired and used-up challenges all mean logging in again.
+            let username = self
+                .accounts
+                .borrow()
+                .iter()
+                .find(|account| {
+                    !account.is_disabled()
+                        && account.two_factor().has_login_challenge(challenge, now)
+                })
+                .map(|account| account.profile().username)
+                .ok_or(AuthError::InvalidCredentials)?;
+            self.modify(&username, |account| {
+                if account.two_factor_mut().finish_login(challenge, code, now) {
+                    Ok(Self::start_session(account))
+                } else {
+                    Err(AuthError::InvalidCode)
+                }
+            })?
+        })
+    }
+
+    fn login_with_id_token<'a>(
+        &'a self,
+        _id_token: &'a str,
+        claims: &'a IdTokenClaims,
+    ) -> AuthFuture<'a, AuthSession> {
+        Box::pin(async move {
+            let identity = claims.identity();
+            let linked = self
+                .accounts
+                .borrow()
+                .iter()
+                .find(|account| account.identity() == Some(&identity))
+                .map(|account| (account.is_disabled(), account.profile().username));
+            match linked {
+                Some((true, _)) => return Err(AuthError::AccountDisabled),
+                Some((false, username)) => return self.modify(&username, Self::start_session),
+                None => {}
+            }
+
+            let email = claims.verified_email()?;
+            let existing = self
+                .accounts
+                .borrow()
+                .iter()
+                .find(|account| account.get_email().eq_ignore_ascii_case(&email))
+                .map(|account| {
+                    // Only accounts made through single sign-on before their identity was kept are
+                    // linked by email. Logging in to one with another identity, a password or a
+                    // second factor has to go through those.
+                    let linkable = account.identity().is_none()
+                        && !account.has_password()
+                        && !account.two_factor().is_enabled();
+                    (account.is_disabled(), linkable, account.profile().username)
+                });
+            match existing {
+                Some((true, _, _)) => Err(AuthError::AccountDisabled),
+                Some((false, false, _)) => Err(OidcError::AccountExists.into()),
+                Some((false, true, username)) => self.modify(&username, |account| {
+                    account.set_identity(identity);
+                    Self::start_session(account)
+                }),
+                None => {
+                    let profile = claims.profile()?;
+                    if self.is_taken(&profile, None) {
+                        return Err(AuthError::AlreadyRegistered);
+                    }
+                    // These accounts have no password, so they can only log in through the identity provider.
+                    let mut account = UserManager::without_password(profile);
+                    account.set_identity(identity);
+                    let session = Self::start_session(&mut account);
+                    self.accounts.borrow_mut().push(account);
+                    self.save();
+                    log!("Made an account for a new single sign-on user.");
+                    Ok(session)
+                }
+            }
+        })
+    }
+
+    fn two_factor_enabled<'a>(&'a self, token: &'a str) -> AuthFuture<'a, bool> {
+        Box::pin(async move {
+            let username = self.profile_for(token)?.username;
+            Ok(self.accounts.borrow().iter().any(|account| {
+                account.profile().username == username && account.two_factor().is_enabled()
+            }))
+        })
+    }
+
+    fn begin_two_factor_setup<'a>(&'a self, token: &'a str) -> AuthFuture<'a, TotpSetup> {
+        Box::pin(async move {
+            let profile = self.profile_for(token)?;
+            self.modify(&profile.username, |account| {
+                account.two_factor_mut().begin_setup(&profile.email)
+            })
+        })
+    }
+
+    fn confirm_two_factor<'a>(
+        &'a self,
+        token: &'a str,
+        code: &'a str,
+    ) -> AuthFuture<'a, Vec<String>> {
+        Box::pin(async move {
+            let username = self.profile_for(token)?.username;
+            self.modify(&username, |account| {
+                account
+                    .two_factor_mut()
+                    .confirm_setup(code)
+                    .ok_or(AuthError::InvalidCode)
+            })?
+        })
+    }
+
+    fn disable_two_factor<'a>(&'a self, token: &'a str, code: &'a str) -> AuthFuture<'a, ()> {
+        Box::pin(async move {
+            let username = self.profile_for(token)?.username;
+            self.modify(&username, |account| {
+                let two_factor = account.two_factor_mut();
+                if !two_factor.verify(code) {
+                    return Err(AuthError::InvalidCode);
+                }
+                two_factor.disable();
+                Ok(())
+            })?
+        })
+    }
+
+    fn logout<'a>(&'a self, token: &'a str) -> AuthFuture<'a, ()> {
+        Box::pin(async move {
+            // Disabled accounts can still log out, so this doesn't go through `profile_for`.
+            let username = self
+                .accounts
+                .borrow()
+                .iter()
+                .find(|account| account.has_session(token))
+                .map(|account| account.profile().username)
+                .ok_or(AuthError::InvalidCredentials)?;
+            self.modify(&username, |account| account.end_session(token))
+        })
+    }
+
+    fn update_profile<'a>(
+        &'a self,
+        token: &'a str,
+        profile: &'a UserProfile,
+    ) -> AuthFuture<'a, AuthSession> {
+        Box::pin(async move {
+            let current = self.profile_for(token)?;
+            if self.is_taken(profile, Some(&current.username)) {
+                return Err(AuthError::AlreadyRegistered);
+            }
+            self.modify(&current.username, |account| {
+                account.set_profile(UserProfile {
+                    username: current.username.clone(),
+                    role: current.role,
+                    ..profile.clone()
+                });
+                AuthSession {
+                    token: token.to_string(),
+                    user: account.profile(),
+                    expires_at: None,
+                }
+            })
+        })
+    }
+
+    fn change_password<'a>(
+        &'a self,
+        token: &'a str,
+        current_password: &'a str,
+        new_password: &'a str,
+    ) -> AuthFuture<'a, AuthSession> {
+        Box::pin(async move {
+            let username = self.profile_for(token)?.username;
+            self.modify(&username, |account| {
+                if account.check_password(current_password) == PasswordCheck::Invalid {
+                    return Err(AuthError::InvalidCredentials);
+                }
+                account.set_password(new_password);
+                account.end_sessions();
+                Ok(Self::start_session(account))
+            })?
+        })
+    }
+
+    fn request_password_reset<'a>(&'a self, email: &'a str) -> AuthFuture<'a, ()> {
+        Box::pin(async move {
+            let exists = self
+                .accounts
+                .borrow()
+                .iter()
+                .any(|account| account.get_email().eq_ignore_ascii_case(email));
+            if !exists {
+                log!("No local account for that email, so no reset link was made.");
+                return Ok(());
+            }
+
+            let token = random_token();
+            let now = Utc::now();
+            let mut resets = self.resets.borrow_mut();
+            resets.retain(|reset| reset.expires_at > now);
+            resets.push(PasswordReset {
+                email: email.to_string(),
+                token_hash: hash_token(&token),
+                expires_at: now + Duration::minutes(RESET_LINK_MINUTES),
+            });
+            drop(resets);
+            self.save();
+
+            // There is no mail server, so the link goes to the console instead.
+            let origin = web_sys::window()
+                .and_then(|window| window.location().origin().ok())
+                .unwrap_or_default();
+            log!(
+                "Password reset link:",
+                format!("{}/reset/{}", origin, token)
+            );
+            Ok(())
+        })
+    }
+
+    fn reset_password<'a>(&'a self, reset_token: &'a str, password: &'a str) -> AuthFuture<'a, ()> {
+        Box::pin(async move {
+            let token_hash = hash_token(reset_token);
+            let now = Utc::now();
+            let mut resets = self.resets.borrow_mut();
+            let reset = resets
+                .iter()
+                .position(|reset| reset.token_hash == token_hash && reset.expires_at > now)
+                .map(|index| resets.remove(index));
+            drop(resets);
+            let reset = reset.ok_or(AuthError::InvalidResetToken)?;
+
+            let mut accounts = self.accounts.borrow_mut();
+            let account = accounts
+                .iter_mut()
+                .find(|account| account.get_email().eq_ignore_ascii_case(&reset.email))
+                .ok_or(AuthError::InvalidResetToken)?;
+            account.set_password(password);
+            account.end_sessions();
+            drop(accounts);
+            self.save();
+            Ok(())
+        })
+    }
+
+    fn current_user<'a>(&'a self, token: &'a str) -> AuthFuture<'a, UserProfile> {
+        Box::pin(async move { self.profile_for(token) })
+    }
+}
+
+impl UserRepository for LocalAuthProvider {
+    fn list_users<'a>(
+        &'a self,
+        token: &'a str,
+        page: usize,
+        per_page: usize,
+    ) -> AuthFuture<'a, UserPage> {
+        Box::pin(async move {
+            self.require_admin(token)?;
+            let accounts = self.accounts.borrow();
+            Ok(UserPage {
+                users: accounts
+                    .iter()
+                    .skip(page * per_page)
+                    .take(per_page)
+                    .map(Self::managed)
+                    .collect(),
+                total: accounts.len(),
+            })
+        })
+    }
+
+    fn get_user<'a>(&'a self, token: &'a str, username: &'a str) -> AuthFuture<'a, ManagedUser> {
+        Box::pin(async move {
+            self.require_admin(token)?;
+            self.accounts
+                .borrow()
+                .iter()
+                .find(|account| account.profile().username == username)
+                .map(Self::managed)
+                .ok_or(AuthError::UnknownUser)
+        })
+    }
+
+    fn create_user<'a>(
+        &'a self,
+        token: &'a str,
+        profile: &'a UserProfile,
+        password: &'a str,
+    ) -> AuthFuture<'a, ManagedUser> {
+        Box::pin(async move {
+            self.require_admin(token)?;
+            self.add_account(profile, password)
+        })
+    }
+
+    fn update_user<'a>(
+        &'a self,
+        token: &'a str,
+        username: &'a str,
+        profile: &'a UserProfile,
+    ) -> AuthFuture<'a, ManagedUser> {
+        Box::pin(async move {
+            let admin = self.require_admin(token)?;
+            if !profile.role.includes(Role::Admin) {
+                self.keep_an_admin(&admin, username)?;
+            }
+            if self.is_taken(profile, Some(username)) {
+                return Err(AuthError::AlreadyRegistered);
+            }
+            self.modify(username, |account| {
+                account.set_profile(profile.clone());
+                Self::managed(account)
+            })
+        })
+    }
+
+    fn set_disabled<'a>(
+        &'a self,
+        token: &'a str,
+        username: &'a str,
+        disabled: bool,
+    ) -> AuthFuture<'a, ()> {
+        Box::pin(async move {
+            let admin = self.require_admin(token)?;
+            if disabled {
+                self.keep_an_admin(&admin, username)?;
+            }
+            self.modify(username, |account| account.set_disabled(disabled))
+        })
+    }
+
+    fn delete_user<'a>(&'a self, token: &'a str, username: &'a str) -> AuthFuture<'a, ()> {
+        Box::pin(async move {
+            let admin = self.require_admin(token)?;
+            self.keep_an_admin(&admin, username)?;
+            let mut accounts = self.accounts.borrow_mut();
+            let count = accounts.len();
+            accounts.retain(|account| account.profile().username != username);
+            if accounts.len() == count {
+                return Err(AuthError::UnknownUser);
+            }
+            drop(accounts);
+            self.save();
+            Ok(())
+        })
+    }
+}
+
+#[derive(Serialize)]
+struct LoginRequest<'a> {
+    email: &'a str,
+    password: &'a str,
+}
+
+#[derive(Serialize)]
+struct SecondFactorRequest<'a> {
+    challenge: &'a str,
+    code: &'a str,
+}
+
+#[derive(Serialize)]
+struct IdTokenRequest<'a> {
+    id_token: &'a str,
+}
+
+#[derive(Serialize)]
+struct CodeRequest<'a> {
+    code: &'a str,
+}
+
+#[derive(Deserialize)]
+struct TwoFactorStatus {
+    enabled: bool,
+}
+
+#[derive(Deserialize)]
+struct RecoveryCodes {
+    recovery_codes: Vec<String>,
+}
+
+#[derive(Serialize)]
+struct ChangePasswordRequest<'a> {
+    current_password: &'a str,
+    new_password: &'a str,
+}
+
+#[derive(Serialize)]
+struct ForgotPasswordRequest<'a> {
+    email: &'a str,
+}
+
+#[derive(Serialize)]
+struct ResetPasswordRequest<'a> {
+    token: &'a str,
+    password: &'a str,
+}
+
+#[derive(Serialize)]
+struct RegisterRequest<'a> {
+    #[serde(flatten)]
+    profile: &'a UserProfile,
+    password: &'a str,
+}
+
+/// Talks to an identity service over HTTP.
+///
+/// Expects `POST {endpoint}/register` (JSON profile fields plus `password`),
+/// `POST {endpoint}/login` (JSON `email`/`password`), `POST {endpoint}/logout`,
+/// `POST {endpoint}/refresh`, `GET {endpoint}/me` and `PUT {endpoint}/me` (JSON profile fields).
+/// Passwords are changed with `POST {endpoint}/password` (JSON `current_password`/`new_password`),
+/// and reset with `POST {endpoint}/password/forgot` (JSON `email`) and `POST {endpoint}/password/reset`
+/// (JSON `token`/`password`, answered with 401 for tokens that don't work).
+/// Everything but login, register and the reset requests sends the token as a bearer token;
+/// login, refresh and `PUT me` answer with an `AuthSession` as JSON.
+///
+/// Login answers with `challenge` and `user` instead for accounts with two-factor authentication,
+/// finished with `POST {endpoint}/login/verify` (JSON `challenge`/`code`). Two-factor authentication
+/// is managed with `GET {endpoint}/2fa` (JSON `enabled`), `POST {endpoint}/2fa/setup` (a `TotpSetup`),
+/// `POST {endpoint}/2fa/confirm` (JSON `code`, answered with `recovery_codes`) and
+/// `POST {endpoint}/2fa/disable` (JSON `code`). Wrong codes are answered with 422.
+/// Logins through an identity provider are exchanged for a session with `POST {endpoint}/login/oidc`
+/// (JSON `id_token`); the server verifies the token's signature and answers with an `AuthSession`.
+/// The server decides each user's `role`; the one sent on register is only a request.
+///
+/// As a `UserRepository` it uses `GET {endpoint}/users?page=&per_page=` (a `UserPage`),
+/// `POST {endpoint}/users` (profile fields plus `password`) and `GET`, `PUT` or `DELETE`
+/// `{endpoint}/users/{username}`, and `POST {endpoint}/users/{username}/disable` or `/enable`.
+pub struct HttpAuthProvider {
+    endpoint: String,
+}
+
+impl HttpAuthProvider {
+    pub fn new(endpoint: &str) -> Self {
+        Self {
+            endpoint: endpoint.trim_end_matches('/').to_string(),
+        }
+    }
+
+    fn url(&self, path: &str) -> String {
+        format!("{}/{}", self.endpoint, path)
+    }
+
+    fn with_token(request: RequestBuilder, token: &str) -> RequestBuilder {
+        request.header("Authorization", &format!("Bearer {}", token))
+    }
+
+    // Usernames only contain letters, digits, '.', '_' and '-', so they are safe in a path.
+    fn user_url(&self, username: &str) -> String {
+        self.url(&format!("users/{}", username))
+    }
+
+    /// Send the request and turn 401/403, 409, 429 and other failures into an `AuthError`.
+    async fn send(request: Request) -> Result<Response, AuthError> {
+        Self::check(request.send().await?)
+    }
+
+    /// The wait the server asked for in `Retry-After`, or a minute if it didn't say.
+    /// Only the number of seconds is understood, not an HTTP date.
+    fn retry_after(response: &Response) -> Duration {
+        response
+            .headers()
+            .get("Retry-After")
+            .and_then(|value| value.trim().parse().ok())
+            .map(Duration::seconds)
+            .unwrap_or_else(|| Duration::minutes(1))
+    }
+
+    fn check(response: Response) -> Result<Response, AuthError> {
+        match response.status() {
+            401 | 403 => Err(AuthError::InvalidCredentials),
+            409 => Err(AuthError::AlreadyRegistered),
+            429 => Err(AuthError::TooManyAttempts(Self::retry_after(&response))),
+            status if !response.ok() => Err(FetchError::Status(status).into()),
+            _ => Ok(response),
+        }
+    }
+
+    /// Like `send`, for requests that carry a two-factor code: there 422 means the code is wrong.
+    async fn send_code(request: Request) -> Result<Response, AuthError> {
+        let response = request.send().await?;
+        match response.status() {
+            422 => Err(AuthError::InvalidCode),
+            _ => Self::check(response),
+        }
+    }
+
+    /// Like `send`, for the `users` endpoints: there 403 means the user isn't an admin,
+    /// and 404 that there is no such user.
+    async fn send_admin(request: Request) -> Result<Response, AuthError> {
+        let response = request.send().await?;
+        match response.status() {
+            403 => Err(AuthError::Forbidden(Role::Admin)),
+            404 => Err(AuthError::UnknownUser),
+            _ => Self::check(response),
+        }
+    }
+}
+
+impl AuthProvider for HttpAuthProvider {
+    fn register<'a>(&'a self, profile: &'a UserProfile, password: &'a str) -> AuthFuture<'a, ()> {
+        Box::pin(async move {
+            let request = Request::post(&self.url("register"))
+                .json(&RegisterRequest { profile, password })?;
+            Self::send(request).await?;
+            Ok(())
+        })
+    }
+
+    fn login<'a>(&'a self, email: &'a str, password: &'a str) -> AuthFuture<'a, LoginOutcome> {
+        Box::pin(async move {
+            let request =
+                Request::post(&self.url("login")).json(&LoginRequest { email, password })?;
+            Ok(Self::send(request).await?.json::<LoginOutcome>().await?)
+        })
+    }
+
+    fn verify_second_factor<'a>(
+        &'a self,
+        challenge: &'a str,
+        code: &'a str,
+    ) -> AuthFuture<'a, AuthSession> {
+        Box::pin(async move {
+            let request = Request::post(&self.url("login/verify"))
+                .json(&SecondFactorRequest { challenge, code })?;
+            Ok(Self::send_code(request)
+                .await?
+                .json::<AuthSession>()
+                .await?)
+        })
+    }
+
+    fn login_with_id_token<'a>(
+        &'a self,
+        id_token: &'a str,
+        _claims: &'a IdTokenClaims,
+    ) -> AuthFuture<'a, AuthSession> {
+        Box::pin(async move {
+            // The server has to check the token's signature itself; the claims are only our reading of it.
+            let request =
+                Request::post(&self.url("login/oidc")).json(&IdTokenRequest { id_token })?;
+            Ok(Self::send(request).await?.json::<AuthSession>().await?)
+        })
+    }
+
+    fn two_factor_enabled<'a>(&'a self, token: &'a str) -> AuthFuture<'a, bool> {
+        Box::pin(async move {
+            let request = Self::with_token(Request::get(&self.url("2fa")), token).build()?;
+            let status = Self::send(request).await?.json::<TwoFactorStatus>().await?;
+            Ok(status.enabled)
+        })
+    }
+
+    fn begin_two_factor_setup<'a>(&'a self, token: &'a str) -> AuthFuture<'a, TotpSetup> {
+        Box::pin(async move {
+            let request = Self::with_token(Request::post(&self.url("2fa/setup")), token).build()?;
+            Ok(Self::send(request).await?.json::<TotpSetup>().await?)
+        })
+    }
+
+    fn confirm_two_factor<'a>(
+        &'a self,
+        token: &'a str,
+        code: &'a str,
+    ) -> AuthFuture<'a, Vec<String>> {
+        Box::pin(async move {
+            let request = Self::with_token(Request::post(&self.url("2fa/confirm")), token)
+                .json(&CodeRequest { code })?;
+            let codes = Self::send_code(request)
+                .await?
+                .json::<RecoveryCodes>()
+                .await?;
+            Ok(codes.recovery_codes)
+        })
+    }
+
+    fn disable_two_factor<'a>(&'a self, token: &'a str, code: &'a str) -> AuthFuture<'a, ()> {
+        Box::pin(async move {
+            let request = Self::with_token(Request::post(&self.url("2fa/disable")), token)
+                .json(&CodeRequest { code })?;
+            Self::send_code(request).await?;
+            Ok(())
+        })
+    }
+
+    fn logout<'a>(&'a self, token: &'a str) -> AuthFuture<'a, ()> {
+        Box::pin(async move {
+            Self::send(Self::with_token(Request::post(&self.url("logout")), token).build()?)
+                .await?;
+            Ok(())
+        })
+    }
+
+    fn refresh<'a>(&'a self, token: &'a str) -> AuthFuture<'a, AuthSession> {
+        Box::pin(async move {
+            let request = Self::with_token(Request::post(&self.url("refresh")), token).build()?;
+            Ok(Self::send(request).await?.json::<AuthSession>().await?)
+        })
+    }
+
+    fn update_profile<'a>(
+        &'a self,
+        token: &'a str,
+        profile: &'a UserProfile,
+    ) -> AuthFuture<'a, AuthSession> {
+        Box::pin(async move {
+            let request = Self::with_token(Request::put(&self.url("me")), token).json(profile)?;
+            Ok(Self::send(request).await?.json::<AuthSession>().await?)
+        })
+    }
+
+    fn change_password<'a>(
+        &'a self,
+        token: &'a str,
+        current_password: &'a str,
+        new_password: &'a str,
+    ) -> AuthFuture<'a, AuthSession> {
+        Box::pin(async move {
+            let request = Self::with_token(Request::post(&self.url("password")), token).json(
+                &ChangePasswordRequest {
+                    current_password,
+                    new_password,
+                },
+            )?;
+            Ok(Self::send(request).await?.json::<AuthSession>().await?)
+        })
+    }
+
+    fn request_password_reset<'a>(&'a self, email: &'a str) -> AuthFuture<'a, ()> {
+        Box::pin(async move {
+            let request = Request::post(&self.url("password/forgot"))
+                .json(&ForgotPasswordRequest { email })?;
+            Self::send(request).await?;
+            Ok(())
+        })
+    }
+
+    fn reset_password<'a>(&'a self, reset_token: &'a str, password: &'a str) -> AuthFuture<'a, ()> {
+        Box::pin(async move {
+            let request =
+                Request::post(&self.url("password/reset")).json(&ResetPasswordRequest {
+                    token: reset_token,
+                    password,
+                })?;
+            match Self::send(request).await {
+                Err(AuthError::InvalidCredentials) => Err(AuthError::InvalidResetToken),
+                result => result.map(|_| ()),
+            }
+        })
+    }
+
+    fn current_user<'a>(&'a self, token: &'a str) -> AuthFuture<'a, UserProfile> {
+        Box::pin(async move {
+            let request = Self::with_token(Request::get(&self.url("me")), token).build()?;
+            Ok(Self::send(request).await?.json::<UserProfile>().await?)
+        })
+    }
+}
+
+impl UserRepository for HttpAuthProvider {
+    fn list_users<'a>(
+        &'a self,
+        token: &'a str,
+        page: usize,
+        per_page: usize,
+    ) -> AuthFuture<'a, UserPage> {
+        Box::pin(async move {
+            let request = Self::with_token(Request::get(&self.url("users")), token)
+                .query([
+                    ("page", page.to_string()),
+                    ("per_page", per_page.to_string()),
+                ])
+                .build()?;
+            Ok(Self::send_admin(request).await?.json::<UserPage>().await?)
+        })
+    }
+
+    fn get_user<'a>(&'a self, token: &'a str, username: &'a str) -> AuthFuture<'a, ManagedUser> {
+        Box::pin(async move {
+            let request =
+                Self::with_token(Request::get(&self.user_url(username)), token).build()?;
+            Ok(Self::send_admin(request)
+                .await?
+                .json::<ManagedUser>()
+                .await?)
+        })
+    }
+
+    fn create_user<'a>(
+        &'a self,
+        token: &'a str,
+        profile: &'a UserProfile,
+        password: &'a str,
+    ) -> AuthFuture<'a, ManagedUser> {
+        Box::pin(async move {
+            let request = Self::with_token(Request::post(&self.url("users")), token)
+                .json(&RegisterRequest { profile, password })?;
+            Ok(Self::send_admin(request)
+                .await?
+                .json::<ManagedUser>()
+                .await?)
+        })
+    }
+
+    fn update_user<'a>(
+        &'a self,
+        token: &'a str,
+        username: &'a str,
+        profile: &'a UserProfile,
+    ) -> AuthFuture<'a, ManagedUser> {
+        Box::pin(async move {
+            let request =
+                Self::with_token(Request::put(&self.user_url(username)), token).json(profile)?;
+            Ok(Self::send_admin(request)
+                .await?
+                .json::<ManagedUser>()
+                .await?)
+        })
+    }
+
+    fn set_disabled<'a>(
+        &'a self,
+        token: &'a str,
+        username: &'a str,
+        disabled: bool,
+    ) -> AuthFuture<'a, ()> {
+        Box::pin(async move {
+            let action = if disabled { "disable" } else { "enable" };
+            let url = format!("{}/{}", self.user_url(username), action);
+            Self::send_admin(Self::with_token(Request::post(&url), token).build()?).await?;
+            Ok(())
+        })
+    }
+
+    fn delete_user<'a>(&'a self, token: &'a str, username: &'a str) -> AuthFuture<'a, ()> {
+        Box::pin(async move {
+            let request =
+                Self::with_token(Request::delete(&self.user_url(username)), token).build()?;
+            Self::send_admin(request).await?;
+            Ok(())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::task::{Context, Poll, Waker};
+
+    use super::*;
+    use crate::helpers::{oidc::Audience, user_manager::MAX_SESSIONS};
+
+    /// The local provider's futures never wait, so polling them once is enough.
+    fn run<T>(mut future: AuthFuture<'_, T>) -> Result<T, AuthError> {
+        match future
+            .as_mut()
+            .poll(&mut Context::from_waker(Waker::noop()))
+        {
+            Poll::Ready(result) => result,
+            Poll::Pending => panic!("The local provider had to wait"),
+        }
+    }
+
+    fn account(username: &str, role: Role) -> UserManager {
+        UserManager::without_password(UserProfile {
+            username: username.to_string(),
+            email: format!("{}@example.com", username.to_lowercase()),
+            name: username.to_string(),
+            birthday: NaiveDate::from_ymd_opt(1990, 1, 1).unwrap(),
+            role,
+        })
+    }
+
+    /// A provider with the admins Ada and Grace and the reader Linus, and Ada's token.
+    fn provider() -> (LocalAuthProvider, String) {
+        let mut ada = account("Ada", Role::Admin);
+        let token = ada.start_session();
+        let provider = LocalAuthProvider::new(vec![
+            ada,
+            account("Grace", Role::Admin),
+            account("Linus", Role::default()),
+        ]);
+        (provider, token)
+    }
+
+    fn profile_as(provider: &LocalAuthProvider, username: &str, role: Role) -> UserProfile {
+        let accounts = provider.accounts.borrow();
+        let account = accounts
+            .iter()
+            .find(|account| account.profile().username == username)
+            .unwrap();
+        UserProfile {
+            role,
+            ..account.profile()
+        }
+    }
+
+    #[test]
+    fn admins_cant_lock_themselves_out() {
+        let (provider, token) = provider();
+        let profile = profile_as(&provider, "Ada", Role::default());
+        assert_eq!(
+            run(provider.update_user(&token, "Ada", &profile)),
+            Err(AuthError::AdminLockout)
+        );
+        assert_eq!(
+            run(provider.set_disabled(&token, "Ada", true)),
+            Err(AuthError::AdminLockout)
+        );
+        assert_eq!(
+            run(provider.delete_user(&token, "Ada")),
+            Err(AuthError::AdminLockout)
+        );
+        assert_eq!(run(provider.list_users(&token, 0, 10)).unwrap().total, 3);
+    }
+
+    #[test]
+    fn admins_can_manage_everyone_else() {
+        let (provider, token) = provider();
+        run(provider.set_disabled(&token, "Linus", true)).unwrap();
+        run(provider.delete_user(&token, "Linus")).unwrap();
+        let profile = profile_as(&provider, "Grace", Role::default());
+        run(provider.update_user(&token, "Grace", &profile)).unwrap();
+        assert_eq!(
+            run(provider.get_user(&token, "Grace"))
+                .unwrap()
+                .profile
+                .role,
+            Role::default()
+        );
+        // Ada is the last admin now, and may still edit her own details.
+        let profile = UserProfile {
+            name: "Ada Lovelace".to_string(),
+            ..profile_as(&provider, "Ada", Role::Admin)
+        };
+        run(provider.update_user(&token, "Ada", &profile)).unwrap();
+    }
+
+    #[test]
+    fn changing_the_password_ends_the_other_sessions() {
+        let (provider, old_token) = provider();
+        provider.accounts.borrow_mut()[0].set_password("password123");
+        assert_eq!(
+            run(provider.change_password(&old_token, "password12", "password456")),
+            Err(AuthError::InvalidCredentials)
+        );
+        let session =
+            run(provider.change_password(&old_token, "password123", "password456")).unwrap();
+        assert_ne!(session.token, old_token);
+        assert_eq!(
+            run(provider.current_user(&old_token)),
+            Err(AuthError::InvalidCredentials)
+        );
+        assert_eq!(
+            run(provider.current_user(&session.token)).unwrap().username,
+            "Ada"
+        );
+    }
+
+    #[test]
+    fn emails_log_in_in_any_case() {
+        let (provider, _) = provider();
+        provider.accounts.borrow_mut()[0].set_password("password123");
+        let outcome = run(provider.login(" ADA@example.com ", "password123")).unwrap();
+        assert!(
+            matches!(outcome, LoginOutcome::Session(session) if session.user.username == "Ada")
+        );
+    }
+
+    #[test]
+    fn logging_out_ends_only_that_session() {
+        let (provider, token) = provider();
+        let other = provider.accounts.borrow_mut()[0].start_session();
+        run(provider.logout(&token)).unwrap();
+        assert_eq!(
+            run(provider.current_user(&token)),
+            Err(AuthError::InvalidCredentials)
+        );
+        assert_eq!(run(provider.current_user(&other)).unwrap().username, "Ada");
+        assert_eq!(
+            run(provider.logout(&token)),
+            Err(AuthError::InvalidCredentials)
+        );
+    }
+
+    #[test]
+    fn only_the_hashes_of_tokens_are_stored() {
+        let (provider, token) = provider();
+        let stored = serde_json::to_string(&*provider.accounts.borrow()).unwrap();
+        assert!(!stored.contains(&token));
+        assert_eq!(run(provider.current_user(&token)).unwrap().username, "Ada");
+        assert_eq!(
+            run(provider.current_user("local:ada@example.com:")),
+            Err(AuthError::InvalidCredentials)
+        );
+    }
+
+    #[test]
+    fn only_the_newest_sessions_are_kept() {
+        let (provider, first) = provider();
+        let tokens = (0..MAX_SESSIONS)
+            .map(|_| provider.accounts.borrow_mut()[0].start_session())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            run(provider.current_user(&first)),
+            Err(AuthError::InvalidCredentials)
+        );
+        assert!(tokens
+            .iter()
+            .all(|token| run(provider.current_user(token)).is_ok()));
+    }
+
+    #[test]
+    fn make_admin_promotes_only_the_named_account() {
+        let (provider, _) = provider();
+        assert!(provider.make_admin(" LINUS@example.com"));
+        assert!(!provider.make_admin("linus@example.com"));
+        assert!(!provider.make_admin("nobody@example.com"));
+        assert_eq!(provider.accounts.borrow()[2].profile().role, Role::Admin);
+    }
+
+    /// What an identity provider says about its user `sub` with the verified `email`.
+    fn claims(sub: &str, email: &str) -> IdTokenClaims {
+        IdTokenClaims {
+            iss: "https://id.example.com".to_string(),
+            sub: sub.to_string(),
+            aud: Audience::One("blog".to_string()),
+            exp: 0,
+            nonce: None,
+            email: Some(email.to_string()),
+            email_verified: Some(true),
+            name: None,
+            preferred_username: None,
+            birthdate: Some("1990-01-01".to_string()),
+        }
+    }
+
+    #[test]
+    fn single_sign_on_finds_accounts_by_issuer_and_subject() {
+        let (provider, _) = provider();
+        // Linus signed up through single sign-on before identities were kept.
+        let linus = claims("1", "linus@example.com");
+        let session = run(provider.login_with_id_token("", &linus)).unwrap();
+        assert_eq!(session.user.username, "Linus");
+
+        // From now on the identity finds him, whatever email the identity provider has.
+        let moved = claims("1", "linus@example.org");
+        let session = run(provider.login_with_id_token("", &moved)).unwrap();
+        assert_eq!(session.user.username, "Linus");
+
+        // Someone else there can't take his account over by using his email.
+        let other = claims("2", "linus@example.com");
+        assert_eq!(
+            run(provider.login_with_id_token("", &other)),
+            Err(OidcError::AccountExists.into())
+        );
+    }
+
+    #[test]
+    fn only_admins_manage_users() {
+        let (provider, _) = provider();
+        let token = provider.accounts.borrow_mut()[2].start_session();
+        assert_eq!(
+            run(provider.delete_user(&token, "Grace")),
+            Err(AuthError::Forbidden(Role::Admin))
+        );
+    }
+}
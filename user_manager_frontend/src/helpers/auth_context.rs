@@ -0,0 +1,309 @@
+use std::rc::Rc;
+
+use chrono::NaiveDate;
+use gloo_console::{error, log};
+use gloo_events::EventListener;
+use web_sys::{
+    wasm_bindgen::{JsCast, JsValue},
+    BroadcastChannel, MessageEvent,
+};
+use yew::{hook, use_context, use_effect_with, use_memo, use_state, UseStateHandle};
+
+use super::{
+    auth::{default_provider, AuthError},
+    oidc::{self, default_issuer, OidcError},
+    roles::Role,
+    session::Session,
+    user_manager::{Authorized, RegistrationError, UserManager, UserState},
+};
+
+// Name of the BroadcastChannel the tabs of this app tell each other about logins and logouts on.
+const AUTH_CHANNEL: &str = "auth";
+// The messages sent on it.
+const LOGGED_IN: &str = "logged-in";
+const LOGGED_OUT: &str = "logged-out";
+const PROFILE_UPDATED: &str = "profile-updated";
+const PASSWORD_CHANGED: &str = "password-changed";
+
+/// How far `AuthContext::login` got.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LoginProgress {
+    LoggedIn,
+    /// The password was right; the account also needs a code from `verify_second_factor`.
+    NeedsSecondFactor,
+}
+
+/// Who is logged in, shared with every component from `App`.
+///
+/// The stored `Session` is known as soon as the page loads; the full `UserState` follows once the
+/// provider has confirmed the session. Logins, logouts and profile changes are passed on to the
+/// app's other tabs.
+/// Use `use_auth()` to get it.
+#[derive(Clone, PartialEq)]
+pub struct AuthContext {
+    user: UseStateHandle<Option<UserState>>,
+    session: UseStateHandle<Option<Session>>,
+    /// `None` in browsers without BroadcastChannel; tabs are then only in sync after a reload.
+    channel: Rc<Option<BroadcastChannel>>,
+}
+
+impl AuthContext {
+    /// Whether there is a live session, even if the account hasn't been loaded yet.
+    pub fn is_logged_in(&self) -> bool {
+        self.session.is_some()
+    }
+
+    /// The logged-in user, once the provider has confirmed the session.
+    pub fn user(&self) -> Option<UserManager<Authorized>> {
+        match &*self.user {
+            Some(UserState::Authorized(user)) => Some(user.clone()),
+            _ => None,
+        }
+    }
+
+    /// The token of the live session, for requests made on the user's behalf.
+    pub fn token(&self) -> Option<String> {
+        self.session.as_ref().map(|session| session.token.clone())
+    }
+
+    /// The name to greet the logged-in user with.
+    pub fn display_name(&self) -> Option<String> {
+        match (&*self.user, &*self.session) {
+            (Some(UserState::Authorized(user)), _) => Some(user.get_name().to_string()),
+            (_, Some(session)) => Some(session.display_name.clone()),
+            _ => None,
+        }
+    }
+
+    /// The role of the logged-in user, taken from the stored session until the account is loaded.
+    pub fn role(&self) -> Option<Role> {
+        match (&*self.user, &*self.session) {
+            (Some(UserState::Authorized(user)), _) => Some(user.role()),
+            (_, Some(session)) => Some(session.role),
+            _ => None,
+        }
+    }
+
+    /// Whether the logged-in user has at least the `required` role.
+    pub fn has_role(&self, required: Role) -> bool {
+        match &*self.user {
+            Some(UserState::Authorized(user)) => user.require_role(required).is_ok(),
+            _ => self.role().is_some_and(|role| role.includes(required)),
+        }
+    }
+
+    /// The email of whoever logged out last in this tab, to fill in the login form.
+    pub fn last_email(&self) -> Option<String> {
+        match &*self.user {
+            Some(UserState::Unauthorized(user)) => Some(user.get_email().to_string()),
+            _ => None,
+        }
+    }
+
+    /// Log in through the default provider. With `remember`, the session outlives the browser tab.
+    /// Accounts with two-factor authentication are only logged in after `verify_second_factor`.
+    pub async fn login(
+        &self,
+        email: &str,
+        password: &str,
+        remember: bool,
+    ) -> Result<LoginProgress, AuthError> {
+        let state = UserManager::login(&*default_provider(), email, password, remember).await?;
+        if let UserState::PendingSecondFactor(_) = state {
+            self.user.set(Some(state));
+            return Ok(LoginProgress::NeedsSecondFactor);
+        }
+        self.finish_login(state);
+        Ok(LoginProgress::LoggedIn)
+    }
+
+    /// Finish a login that is waiting for a two-factor code or recovery code.
+    pub async fn verify_second_factor(&self, code: &str, remember: bool) -> Result<(), AuthError> {
+        let Some(UserState::PendingSecondFactor(pending)) = &*self.user else {
+            return Err(AuthError::NotLoggedIn);
+        };
+        let state = pending
+            .verify_second_factor(&*default_provider(), code, remember)
+            .await?;
+        self.finish_login(state);
+        Ok(())
+    }
+
+    /// Give up on a login that is waiting for a second factor.
+    pub fn cancel_second_factor(&self) {
+        if let Some(UserState::PendingSecondFactor(_)) = &*self.user {
+            self.user.set(None);
+        }
+    }
+
+    /// Finish logging in through the identity provider with the `code` and `state` it sent the
+    /// browser back with. Answers with the path to go to now.
+    pub async fn finish_oidc_login(&self, code: &str, state: &str) -> Result<String, AuthError> {
+        let issuer = default_issuer().ok_or(OidcError::NoLoginInProgress)?;
+        let login = oidc::finish_login(&*issuer, code, state).await?;
+        let state = UserManager::login_with_oidc(&*default_provider(), &login).await?;
+        self.finish_login(state);
+        Ok(login.next)
+    }
+
+    fn finish_login(&self, state: UserState) {
+        self.session.set(Session::load());
+        self.user.set(Some(state));
+        self.announce(LOGGED_IN);
+    }
+
+    /// Save the user's name, email and birthday. Every invalid field is reported at once, and
+    /// nothing is saved unless all of them are valid.
+    pub async fn update_profile(
+        &self,
+        name: &str,
+        email: &str,
+        birthday: Option<NaiveDate>,
+    ) -> Result<(), RegistrationError> {
+        let mut user = self
+            .user()
+            .ok_or(RegistrationError::Auth(AuthError::NotLoggedIn))?;
+        user.update_profile(&*default_provider(), name, email, birthday)
+            .await?;
+
+        self.session.set(Session::load());
+        self.user.set(Some(UserState::Authorized(user)));
+        self.announce(PROFILE_UPDATED);
+        Ok(())
+    }
+
+    /// Change the logged-in user's password, if `current_password` is right. Sessions elsewhere end;
+    /// this one and the other tabs' carry on.
+    pub async fn change_password(
+        &self,
+        current_password: &str,
+        new_password: &str,
+    ) -> Result<(), RegistrationError> {
+        let mut user = self
+            .user()
+            .ok_or(RegistrationError::Auth(AuthError::NotLoggedIn))?;
+        user.change_password(&*default_provider(), current_password, new_password)
+            .await?;
+
+        self.session.set(Session::load());
+        self.user.set(Some(UserState::Authorized(user)));
+        self.announce(PASSWORD_CHANGED);
+        Ok(())
+    }
+
+    /// Log out, telling the provider if we already know the account.
+    pub async fn logout(&self) {
+        match (*self.user).clone() {
+            Some(UserState::Authorized(manager)) => {
+                self.user
+                    .set(Some(manager.logout(&*default_provider()).await));
+            }
+            // The account hasn't been loaded yet, so just forget the stored session.
+            _ => Session::clear(),
+        }
+        self.session.set(None);
+        self.announce(LOGGED_OUT);
+    }
+
+    /// Forget a session that ended on its own, e.g. after being idle for too long.
+    pub fn expire(&self) {
+        Session::clear();
+        self.session.set(None);
+        self.user.set(None);
+        self.announce(LOGGED_OUT);
+    }
+
+    /// Tell the other tabs. A channel never receives its own messages, so this tab isn't told.
+    fn announce(&self, message: &str) {
+        if let Some(channel) = &*self.channel {
+            if let Err(err) = channel.post_message(&JsValue::from_str(message)) {
+                error!("Couldn't tell the other tabs:", err);
+            }
+        }
+    }
+}
+
+// Ask the provider for a fresh token and the account details of the stored session.
+// Sessions the provider rejects are dropped.
+fn resume(user: UseStateHandle<Option<UserState>>, session: UseStateHandle<Option<Session>>) {
+    wasm_bindgen_futures::spawn_local(async move {
+        match UserManager::resume(&*default_provider()).await {
+            Ok(new_state) => {
+                session.set(Session::load());
+                user.set(Some(new_state));
+            }
+            // The provider couldn't be reached. Keep the session and try again next time.
+            Err(AuthError::Request(err)) => {
+                error!("Couldn't refresh the session", err.to_string());
+            }
+            Err(_) => {
+                Session::clear();
+                session.set(None);
+            }
+        }
+    });
+}
+
+/// Create the `AuthContext` for the whole app. Picks up the stored session and resumes it once,
+/// then follows logins and logouts in the app's other tabs.
+#[hook]
+pub fn use_auth_state() -> AuthContext {
+    let user = use_state(|| None::<UserState>);
+    let session = use_state(Session::load);
+    let channel = use_memo((), |_| BroadcastChannel::new(AUTH_CHANNEL).ok());
+
+    {
+        let user = user.clone();
+        let session = session.clone();
+        let channel = channel.clone();
+        use_effect_with((), move |_| {
+            if session.is_some() {
+                resume(user.clone(), session.clone());
+            }
+
+            let listener = (*channel).as_ref().map(|channel| {
+                EventListener::new(channel, "message", move |event| {
+                    let message = event
+                        .dyn_ref::<MessageEvent>()
+                        .and_then(|event| event.data().as_string());
+                    match message.as_deref() {
+                        // Only sessions the other tab remembered are shared; tab-only ones stay there.
+                        Some(LOGGED_IN) if Session::load().is_some() => {
+                            log!("Logged in in another tab.");
+                            session.set(Session::load());
+                            resume(user.clone(), session.clone());
+                        }
+                        // Both may have replaced the token too, so load the session and account again.
+                        Some(PROFILE_UPDATED | PASSWORD_CHANGED) if Session::load().is_some() => {
+                            log!("Account changed in another tab.");
+                            session.set(Session::load());
+                            resume(user.clone(), session.clone());
+                        }
+                        Some(LOGGED_OUT) => {
+                            log!("Logged out in another tab.");
+                            Session::clear();
+                            session.set(None);
+                            user.set(None);
+                        }
+                        _ => {}
+                    }
+                })
+            });
+
+            move || drop(listener)
+        });
+    }
+
+    AuthContext {
+        user,
+        session,
+        channel,
+    }
+}
+
+/// The `AuthContext` provided by `App`.
+#[hook]
+pub fn use_auth() -> AuthContext {
+    use_context::<AuthContext>()
+        .expect("No AuthContext found. Is the app wrapped in a SessionProvider?")
+}
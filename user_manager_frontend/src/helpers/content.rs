@@ -0,0 +1,135 @@
+use std::{future::Future, pin::Pin, rc::Rc};
+
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+
+#[cfg(feature = "local-content")]
+use super::local_content::LocalContent;
+use super::{fetch::FetchError, sanity::SanityClient};
+use crate::components::{ArticleBody, AuthorSummary, BlogPost, Category};
+
+/// The future returned by every `ContentSource` operation.
+pub type ContentFuture<'a, T> = Pin<Box<dyn Future<Output = Result<T, FetchError>> + 'a>>;
+
+/// Which posts a list shows.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PostFilter {
+    All,
+    /// The posts with this tag.
+    Tag(String),
+    /// The posts in the category with this slug.
+    Category(String),
+    /// The posts by the author with this slug.
+    Author(String),
+}
+
+/// One slice of the posts a filter lets through.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct PostSlice {
+    pub posts: Vec<BlogPost>,
+    /// How many posts there are across all slices.
+    pub total: u32,
+}
+
+/// A post that matched a search, with its text to take a snippet from.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct SearchResult {
+    #[serde(flatten)]
+    pub post: BlogPost,
+    pub text: Option<String>,
+}
+
+/// An entire article, including its body, title and logo.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Article {
+    pub body: Vec<ArticleBody>,
+    pub title: Option<String>,
+    pub logo: Option<ArticleLogo>,
+    #[serde(default)]
+    pub categories: Vec<Category>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    pub author: Option<AuthorSummary>,
+    #[serde(rename = "publishedAt")]
+    pub published_at: Option<DateTime<Utc>>,
+    #[serde(rename = "_updatedAt")]
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct ArticleLogo {
+    pub asset: ArticleLogoAsset,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct ArticleLogoAsset {
+    pub url: String,
+}
+
+/// An author's profile.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Author {
+    pub name: String,
+    #[serde(rename = "imageUrl")]
+    pub image_url: Option<String>,
+    /// Portable Text, like an article's body.
+    #[serde(default)]
+    pub bio: Vec<ArticleBody>,
+}
+
+/// The title and description of a category.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct CategoryDetails {
+    pub title: String,
+    pub description: Option<String>,
+}
+
+/// Where the posts, authors and categories of the blog come from.
+///
+/// Lookups by slug fail with `FetchError::NotFound` when there is nothing with that slug.
+pub trait ContentSource {
+    /// `count` of the posts `filter` lets through, newest first, after skipping the `start` newest.
+    fn list_posts<'a>(
+        &'a self,
+        filter: &'a PostFilter,
+        start: u32,
+        count: u32,
+    ) -> ContentFuture<'a, PostSlice>;
+
+    fn get_post_by_slug<'a>(&'a self, slug: &'a str) -> ContentFuture<'a, Article>;
+
+    /// Up to `limit` posts whose title or text contains every word of `query` (see `search_terms`),
+    /// best matches first.
+    fn search<'a>(&'a self, query: &'a str, limit: u32) -> ContentFuture<'a, Vec<SearchResult>>;
+
+    fn get_author<'a>(&'a self, slug: &'a str) -> ContentFuture<'a, Author>;
+
+    fn get_category<'a>(&'a self, slug: &'a str) -> ContentFuture<'a, CategoryDetails>;
+}
+
+#[cfg(feature = "local-content")]
+thread_local! {
+    // The bundled content, parsed on first use and shared by every request after that.
+    static BUNDLED: Rc<LocalContent> = Rc::new(LocalContent::bundled());
+}
+
+/// The source the app uses. Builds with the `local-content` feature read the files bundled from
+/// `content/`, unless `CONTENT_SOURCE=sanity` at build time says otherwise; everything else reads
+/// the Sanity project.
+pub fn default_source() -> Rc<dyn ContentSource> {
+    #[cfg(feature = "local-content")]
+    if option_env!("CONTENT_SOURCE") != Some("sanity") {
+        return BUNDLED.with(Rc::clone);
+    }
+    Rc::new(SanityClient::default())
+}
+
+/// The words of a search, lowercased. Each matches a whole word or the start of a longer one
+/// ("rus" finds "Rust").
+pub fn search_terms(query: &str) -> Vec<String> {
+    query
+        .split_whitespace()
+        .map(|term| term.trim_matches('*').to_lowercase())
+        .filter(|term| !term.is_empty())
+        .collect()
+}
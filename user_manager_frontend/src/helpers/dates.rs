@@ -0,0 +1,122 @@
+use chrono::{DateTime, Local, Locale, NaiveDate, Utc};
+
+// Used when chrono doesn't know the browser's language.
+const FALLBACK_LOCALE: Locale = Locale::en_US;
+
+// The region a bare language is most likely meant for, e.g. "nb" for Norway.
+const DEFAULT_REGIONS: &[(&str, Locale)] = &[
+    ("ar", Locale::ar_SA),
+    ("cs", Locale::cs_CZ),
+    ("da", Locale::da_DK),
+    ("de", Locale::de_DE),
+    ("el", Locale::el_GR),
+    ("en", Locale::en_US),
+    ("es", Locale::es_ES),
+    ("et", Locale::et_EE),
+    ("fi", Locale::fi_FI),
+    ("fr", Locale::fr_FR),
+    ("he", Locale::he_IL),
+    ("hi", Locale::hi_IN),
+    ("hu", Locale::hu_HU),
+    ("it", Locale::it_IT),
+    ("ja", Locale::ja_JP),
+    ("ko", Locale::ko_KR),
+    ("nb", Locale::nb_NO),
+    ("nl", Locale::nl_NL),
+    ("nn", Locale::nn_NO),
+    ("no", Locale::nb_NO),
+    ("pl", Locale::pl_PL),
+    ("pt", Locale::pt_PT),
+    ("ru", Locale::ru_RU),
+    ("sk", Locale::sk_SK),
+    ("sl", Locale::sl_SI),
+    ("sv", Locale::sv_SE),
+    ("tr", Locale::tr_TR),
+    ("uk", Locale::uk_UA),
+    ("zh", Locale::zh_CN),
+];
+
+/// The locale of the browser's preferred language.
+pub fn browser_locale() -> Locale {
+    web_sys::window()
+        .and_then(|window| window.navigator().language())
+        .map_or(FALLBACK_LOCALE, |language| locale_for(&language))
+}
+
+/// The locale of a language tag like "en-GB" or "zh-Hant-TW". A bare language like "de" gets the
+/// region it is most likely meant for.
+fn locale_for(language: &str) -> Locale {
+    let mut tags = language.split(['-', '_']).filter(|tag| !tag.is_empty());
+    let Some(language) = tags.next().map(str::to_lowercase) else {
+        return FALLBACK_LOCALE;
+    };
+    // Scripts like "Hant" come before the region and are left out.
+    let region = tags.find(|tag| tag.len() == 2 && tag.chars().all(|c| c.is_ascii_alphabetic()));
+    region
+        .and_then(|region| {
+            Locale::try_from(format!("{}_{}", language, region.to_uppercase()).as_str()).ok()
+        })
+        .or_else(|| {
+            DEFAULT_REGIONS
+                .iter()
+                .find(|(bare, _)| *bare == language)
+                .map(|(_, locale)| *locale)
+        })
+        .unwrap_or(FALLBACK_LOCALE)
+}
+
+/// How a date is written out in `locale`, e.g. "%B %-d, %Y" for "October 17, 2026" in the US.
+fn date_pattern(locale: Locale) -> &'static str {
+    let name = locale.to_string();
+    match name.split_once('_').unwrap_or((&name, "")) {
+        ("en", "US" | "PH") => "%B %-d, %Y",
+        ("cs" | "da" | "de" | "et" | "fi" | "nb" | "nn" | "sk" | "sl", _) => "%-d. %B %Y",
+        ("es" | "pt", _) => "%-d de %B de %Y",
+        ("hu", _) => "%Y. %B %-d.",
+        ("ja" | "zh", _) => "%Y年%-m月%-d日",
+        ("ko", _) => "%Y년 %-m월 %-d일",
+        _ => "%-d %B %Y",
+    }
+}
+
+/// `date` written out the way `locale` does.
+fn format_in(date: NaiveDate, locale: Locale) -> String {
+    date.format_localized(date_pattern(locale), locale)
+        .to_string()
+}
+
+/// A date like "October 17, 2026" or "17. Oktober 2026", the way the browser's language writes it,
+/// in its time zone.
+pub fn format_date(date: DateTime<Utc>) -> String {
+    format_in(date.with_timezone(&Local).date_naive(), browser_locale())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn languages_get_a_real_region() {
+        assert_eq!(locale_for("de"), Locale::de_DE);
+        assert_eq!(locale_for("nb"), Locale::nb_NO);
+        assert_eq!(locale_for("en"), Locale::en_US);
+        assert_eq!(locale_for("en-GB"), Locale::en_GB);
+        assert_eq!(locale_for("pt-BR"), Locale::pt_BR);
+        assert_eq!(locale_for("zh-Hant-TW"), Locale::zh_TW);
+        // An unknown region falls back to the language's own.
+        assert_eq!(locale_for("de-XX"), Locale::de_DE);
+        assert_eq!(locale_for("tlh"), FALLBACK_LOCALE);
+        assert_eq!(locale_for(""), FALLBACK_LOCALE);
+    }
+
+    #[test]
+    fn dates_are_written_the_locales_way() {
+        let date = NaiveDate::from_ymd_opt(2026, 10, 7).unwrap();
+        assert_eq!(format_in(date, Locale::en_US), "October 7, 2026");
+        assert_eq!(format_in(date, Locale::en_GB), "7 October 2026");
+        assert_eq!(format_in(date, Locale::de_DE), "7. Oktober 2026");
+        assert_eq!(format_in(date, Locale::nb_NO), "7. oktober 2026");
+        assert_eq!(format_in(date, Locale::es_ES), "7 de octubre de 2026");
+        assert_eq!(format_in(date, Locale::ja_JP), "2026年10月7日");
+    }
+}
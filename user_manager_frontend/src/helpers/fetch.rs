@@ -0,0 +1,123 @@
+use std::{fmt, future::Future, ops::Deref};
+
+use yew::{hook, use_effect_with, use_mut_ref, use_state, UseStateHandle};
+
+/// Everything that can go wrong while loading content from the API.
+#[derive(Debug, Clone, PartialEq)]
+pub enum FetchError {
+    /// The request never got a response (offline, DNS, CORS, ...).
+    Network(String),
+    /// The server answered with a non-success HTTP status code.
+    Status(u16),
+    /// The response body wasn't the JSON we expected.
+    Decode(String),
+    /// The request succeeded, but the thing we asked for doesn't exist.
+    NotFound,
+}
+
+impl fmt::Display for FetchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FetchError::Network(err) => write!(f, "Couldn't reach the server: {}", err),
+            FetchError::Status(status) => write!(f, "The server responded with status {}", status),
+            FetchError::Decode(err) => write!(f, "Couldn't read the server response: {}", err),
+            FetchError::NotFound => write!(f, "The requested content was not found"),
+        }
+    }
+}
+
+impl From<gloo_net::Error> for FetchError {
+    fn from(err: gloo_net::Error) -> Self {
+        match err {
+            gloo_net::Error::SerdeError(err) => FetchError::Decode(err.to_string()),
+            err => FetchError::Network(err.to_string()),
+        }
+    }
+}
+
+/// Where an asynchronous request currently is.
+#[derive(Debug, Clone, PartialEq)]
+pub enum FetchState<T> {
+    /// Nothing has been requested yet.
+    Idle,
+    /// A request is in flight.
+    Loading,
+    /// The request finished and produced a value.
+    Ok(T),
+    /// The request failed.
+    Err(FetchError),
+}
+
+/// Handle returned by [`use_fetch`]. Dereferences to the current [`FetchState`].
+pub struct UseFetchHandle<T> {
+    state: UseStateHandle<FetchState<T>>,
+    attempt: UseStateHandle<u32>,
+}
+
+impl<T> UseFetchHandle<T> {
+    /// Run the request again with the same dependencies, e.g. after an error.
+    pub fn retry(&self) {
+        self.attempt.set(*self.attempt + 1);
+    }
+}
+
+impl<T> Deref for UseFetchHandle<T> {
+    type Target = FetchState<T>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.state
+    }
+}
+
+impl<T> Clone for UseFetchHandle<T> {
+    fn clone(&self) -> Self {
+        Self {
+            state: self.state.clone(),
+            attempt: self.attempt.clone(),
+        }
+    }
+}
+
+/// Run `request` whenever `deps` change and keep track of its loading, success or error state.
+///
+/// Responses that arrive after `deps` have changed again (or after the component is gone)
+/// are ignored, so a slow request can never overwrite the result of a newer one.
+#[hook]
+pub fn use_fetch<T, D, F, Fut>(deps: D, request: F) -> UseFetchHandle<T>
+where
+    T: 'static,
+    D: PartialEq + 'static,
+    F: FnOnce(&D) -> Fut + 'static,
+    Fut: Future<Output = Result<T, FetchError>> + 'static,
+{
+    let state = use_state(|| FetchState::Idle);
+    let attempt = use_state(|| 0u32);
+    // Bumped every time a request becomes stale, so its response can be recognised and dropped.
+    let generation = use_mut_ref(|| 0u64);
+
+    {
+        let state = state.clone();
+        use_effect_with((deps, *attempt), move |(deps, _)| {
+            let current = *generation.borrow();
+            state.set(FetchState::Loading);
+
+            let response = request(deps);
+            {
+                let generation = generation.clone();
+                wasm_bindgen_futures::spawn_local(async move {
+                    let result = response.await;
+                    if *generation.borrow() == current {
+                        state.set(match result {
+                            Ok(data) => FetchState::Ok(data),
+                            Err(err) => FetchState::Err(err),
+                        });
+                    }
+                });
+            }
+
+            move || *generation.borrow_mut() += 1
+        });
+    }
+
+    UseFetchHandle { state, attempt }
+}
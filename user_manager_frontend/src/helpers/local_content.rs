@@ -0,0 +1,426 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+
+use super::{
+    content::{
+        search_terms, Article, ArticleLogo, ArticleLogoAsset, Author, CategoryDetails,
+        ContentFuture, ContentSource, PostFilter, PostSlice, SearchResult,
+    },
+    fetch::FetchError,
+    markdown::{plain_text, to_portable_text},
+};
+use crate::components::{ArticleBody, AuthorSummary, BlogPost, BlogSlug, Category};
+
+// `SITE_JSON`, the contents of `content/site.json`, and `POST_FILES`, the slug and Markdown of every
+// `content/posts/<slug>.md`. Written by `build.rs`.
+include!(concat!(env!("OUT_DIR"), "/local_content.rs"));
+
+// A search word found in a title counts this many times as much as one found in the text.
+const TITLE_BOOST: u32 = 3;
+
+/// The authors, categories and posts listed in `site.json`.
+#[derive(Debug, Default, Deserialize)]
+struct Site {
+    #[serde(default)]
+    authors: Vec<LocalAuthor>,
+    #[serde(default)]
+    categories: Vec<LocalCategory>,
+    #[serde(default)]
+    posts: Vec<LocalPost>,
+}
+
+#[derive(Debug, Deserialize)]
+struct LocalAuthor {
+    slug: String,
+    name: String,
+    #[serde(rename = "imageUrl")]
+    image_url: Option<String>,
+    /// Markdown.
+    #[serde(default)]
+    bio: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct LocalCategory {
+    slug: String,
+    title: String,
+    description: Option<String>,
+}
+
+/// A post's details. Its body is the Markdown file named after its slug.
+#[derive(Debug, Deserialize)]
+struct LocalPost {
+    slug: String,
+    title: String,
+    #[serde(rename = "logoUrl")]
+    logo_url: Option<String>,
+    #[serde(rename = "publishedAt")]
+    published_at: Option<DateTime<Utc>>,
+    #[serde(rename = "updatedAt")]
+    updated_at: Option<DateTime<Utc>>,
+    /// The author's slug.
+    author: Option<String>,
+    /// Category slugs.
+    #[serde(default)]
+    categories: Vec<String>,
+    #[serde(default)]
+    tags: Vec<String>,
+}
+
+/// Content read from files bundled into the app at build time, for working offline and without a
+/// Sanity project.
+///
+/// The files live in `content/` (or the directory `CONTENT_DIR` names when building): `site.json`
+/// lists the authors, categories and posts, and `posts/<slug>.md` holds the body of each post.
+/// Search works like Sanity's: every word has to start a word of the title or text.
+pub struct LocalContent {
+    /// Newest post first. A `site.json` that doesn't parse fails every request.
+    site: Result<Site, FetchError>,
+    /// The body of each post, by slug.
+    bodies: HashMap<String, Vec<ArticleBody>>,
+}
+
+impl LocalContent {
+    /// The content bundled from the `content/` directory.
+    pub fn bundled() -> Self {
+        Self::new(SITE_JSON, POST_FILES)
+    }
+
+    /// Content from the text of a `site.json` and the `(slug, markdown)` of each post's body.
+    pub fn new(site_json: &str, posts: &[(&str, &str)]) -> Self {
+        let site = serde_json::from_str::<Site>(site_json)
+            .map(|mut site| {
+                // Undated posts go last. The slug keeps the order stable, like `_id` does for Sanity.
+                site.posts.sort_by(|a, b| {
+                    (b.published_at.is_some(), b.published_at, &a.slug).cmp(&(
+                        a.published_at.is_some(),
+                        a.published_at,
+                        &b.slug,
+                    ))
+                });
+                site
+            })
+            .map_err(|err| FetchError::Decode(format!("site.json: {}", err)));
+        let bodies = posts
+            .iter()
+            .map(|(slug, markdown)| (slug.to_string(), to_portable_text(markdown)))
+            .collect();
+        Self { site, bodies }
+    }
+
+    fn site(&self) -> Result<&Site, FetchError> {
+        self.site.as_ref().map_err(Clone::clone)
+    }
+
+    fn body(&self, slug: &str) -> Vec<ArticleBody> {
+        self.bodies.get(slug).cloned().unwrap_or_default()
+    }
+
+    fn categories(site: &Site, slugs: &[String]) -> Vec<Category> {
+        slugs
+            .iter()
+            .filter_map(|slug| {
+                site.categories
+                    .iter()
+                    .find(|category| &category.slug == slug)
+            })
+            .map(|category| Category {
+                title: category.title.clone(),
+                slug: category.slug.clone(),
+            })
+            .collect()
+    }
+
+    fn card(site: &Site, post: &LocalPost) -> BlogPost {
+        BlogPost {
+            slug: BlogSlug {
+                current: post.slug.clone(),
+            },
+            title: post.title.clone(),
+            logo_url: post.logo_url.clone(),
+            categories: Self::categories(site, &post.categories),
+            tags: post.tags.clone(),
+        }
+    }
+}
+
+fn matches_filter(post: &LocalPost, filter: &PostFilter) -> bool {
+    match filter {
+        PostFilter::All => true,
+        PostFilter::Tag(tag) => post.tags.contains(tag),
+        PostFilter::Category(slug) => post.categories.contains(slug),
+        PostFilter::Author(slug) => post.author.as_ref() == Some(slug),
+    }
+}
+
+/// The lowercased words of `text`.
+fn words(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect()
+}
+
+impl ContentSource for LocalContent {
+    fn list_posts<'a>(
+        &'a self,
+        filter: &'a PostFilter,
+        start: u32,
+        count: u32,
+    ) -> ContentFuture<'a, PostSlice> {
+        Box::pin(async move {
+            let site = self.site()?;
+            let posts = site
+                .posts
+                .iter()
+                .filter(|post| matches_filter(post, filter))
+                .collect::<Vec<_>>();
+            Ok(PostSlice {
+                total: posts.len() as u32,
+                posts: posts
+                    .into_iter()
+                    .skip(start as usize)
+                    .take(count as usize)
+                    .map(|post| Self::card(site, post))
+                    .collect(),
+            })
+        })
+    }
+
+    fn get_post_by_slug<'a>(&'a self, slug: &'a str) -> ContentFuture<'a, Article> {
+        Box::pin(async move {
+            let site = self.site()?;
+            let post = site
+                .posts
+                .iter()
+                .find(|post| post.slug == slug)
+                .ok_or(FetchError::NotFound)?;
+            let author = post.author.as_ref().and_then(|slug| {
+                site.authors
+                    .iter()
+                    .find(|author| &author.slug == slug)
+                    .map(|author| AuthorSummary {
+                        name: author.name.clone(),
+                        slug: Some(author.slug.clone()),
+                        image_url: author.image_url.clone(),
+                    })
+            });
+            Ok(Article {
+                body: self.body(slug),
+                title: Some(post.title.clone()),
+                logo: post.logo_url.clone().map(|url| ArticleLogo {
+                    asset: ArticleLogoAsset { url },
+                }),
+                categories: Self::categories(site, &post.categories),
+                tags: post.tags.clone(),
+                author,
+                published_at: post.published_at,
+                updated_at: post.updated_at,
+            })
+        })
+    }
+
+    fn search<'a>(&'a self, query: &'a str, limit: u32) -> ContentFuture<'a, Vec<SearchResult>> {
+        Box::pin(async move {
+            let site = self.site()?;
+            let terms = search_terms(query);
+            let mut results = Vec::new();
+            for post in &site.posts {
+                let text = plain_text(&self.body(&post.slug));
+                let (title_words, text_words) = (words(&post.title), words(&text));
+                let found = |words: &[String], term: &String| {
+                    words.iter().any(|word| word.starts_with(term.as_str()))
+                };
+                let mut score = 0;
+                for term in &terms {
+                    match (found(&title_words, term), found(&text_words, term)) {
+                        (false, false) => {
+                            score = 0;
+                            break;
+                        }
+                        (in_title, in_text) => {
+                            score += u32::from(in_title) * TITLE_BOOST + u32::from(in_text)
+                        }
+                    }
+                }
+                if score > 0 {
+                    results.push((
+                        score,
+                        SearchResult {
+                            post: Self::card(site, post),
+                            text: Some(text),
+                        },
+                    ));
+                }
+            }
+            // Stable, so equally good matches stay newest first.
+            results.sort_by(|(a, _), (b, _)| b.cmp(a));
+            Ok(results
+                .into_iter()
+                .take(limit as usize)
+                .map(|(_, result)| result)
+                .collect())
+        })
+    }
+
+    fn get_author<'a>(&'a self, slug: &'a str) -> ContentFuture<'a, Author> {
+        Box::pin(async move {
+            let author = self
+                .site()?
+                .authors
+                .iter()
+                .find(|author| author.slug == slug)
+                .ok_or(FetchError::NotFound)?;
+            Ok(Author {
+                name: author.name.clone(),
+                image_url: author.image_url.clone(),
+                bio: to_portable_text(&author.bio),
+            })
+        })
+    }
+
+    fn get_category<'a>(&'a self, slug: &'a str) -> ContentFuture<'a, CategoryDetails> {
+        Box::pin(async move {
+            let category = self
+                .site()?
+                .categories
+                .iter()
+                .find(|category| category.slug == slug)
+                .ok_or(FetchError::NotFound)?;
+            Ok(CategoryDetails {
+                title: category.title.clone(),
+                description: category.description.clone(),
+            })
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::task::{Context, Poll, Waker};
+
+    use super::*;
+
+    const SITE: &str = r#"{
+        "authors": [{"slug": "ferris", "name": "Ferris", "bio": "A *crab*."}],
+        "categories": [{"slug": "rust", "title": "Rust"}, {"slug": "web", "title": "Web"}],
+        "posts": [
+            {"slug": "old", "title": "Old news", "publishedAt": "2023-01-01T00:00:00Z",
+             "categories": ["web"], "tags": ["news"]},
+            {"slug": "draft", "title": "A draft about Rust"},
+            {"slug": "new", "title": "Rust in the browser", "publishedAt": "2024-01-01T00:00:00Z",
+             "author": "ferris", "categories": ["rust", "web", "missing"], "tags": ["wasm"]},
+            {"slug": "also-new", "title": "Yew hooks", "publishedAt": "2024-01-01T00:00:00Z",
+             "author": "ferris", "categories": ["rust"]}
+        ]
+    }"#;
+    const POSTS: &[(&str, &str)] = &[
+        ("old", "Nothing about crabs here."),
+        ("new", "# Hello\n\nRustaceans compile to **WebAssembly**."),
+        ("also-new", "State and effects, the Rust way."),
+    ];
+
+    /// `LocalContent`'s futures never wait, so polling them once is enough.
+    fn run<T>(mut future: ContentFuture<'_, T>) -> Result<T, FetchError> {
+        match future
+            .as_mut()
+            .poll(&mut Context::from_waker(Waker::noop()))
+        {
+            Poll::Ready(result) => result,
+            Poll::Pending => panic!("LocalContent had to wait"),
+        }
+    }
+
+    fn slugs(posts: &[BlogPost]) -> Vec<&str> {
+        posts
+            .iter()
+            .map(|post| post.slug.current.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn newest_first_and_undated_last() {
+        let content = LocalContent::new(SITE, POSTS);
+        let slice = run(content.list_posts(&PostFilter::All, 0, 10)).unwrap();
+        assert_eq!(slice.total, 4);
+        assert_eq!(slugs(&slice.posts), vec!["also-new", "new", "old", "draft"]);
+    }
+
+    #[test]
+    fn slices_and_filters() {
+        let content = LocalContent::new(SITE, POSTS);
+        let slice = run(content.list_posts(&PostFilter::All, 1, 2)).unwrap();
+        assert_eq!((slice.total, slugs(&slice.posts)), (4, vec!["new", "old"]));
+        let slice = run(content.list_posts(&PostFilter::All, u32::MAX, 2)).unwrap();
+        assert_eq!((slice.total, slice.posts.len()), (4, 0));
+
+        let slice = |filter| run(content.list_posts(&filter, 0, 10)).unwrap();
+        assert_eq!(
+            slugs(&slice(PostFilter::Tag("wasm".to_string())).posts),
+            vec!["new"]
+        );
+        assert_eq!(
+            slugs(&slice(PostFilter::Category("web".to_string())).posts),
+            vec!["new", "old"]
+        );
+        assert_eq!(
+            slugs(&slice(PostFilter::Author("ferris".to_string())).posts),
+            vec!["also-new", "new"]
+        );
+        assert_eq!(slice(PostFilter::Tag("none".to_string())).total, 0);
+    }
+
+    #[test]
+    fn posts_authors_and_categories() {
+        let content = LocalContent::new(SITE, POSTS);
+        let article = run(content.get_post_by_slug("new")).unwrap();
+        assert_eq!(article.title.as_deref(), Some("Rust in the browser"));
+        assert_eq!(article.author.unwrap().slug.as_deref(), Some("ferris"));
+        // Unknown categories are left out.
+        assert_eq!(article.categories.len(), 2);
+        assert_eq!(article.body.len(), 2);
+        assert_eq!(run(content.get_post_by_slug("draft")).unwrap().body, vec![]);
+        assert_eq!(
+            run(content.get_post_by_slug("gone")),
+            Err(FetchError::NotFound)
+        );
+
+        assert_eq!(run(content.get_author("ferris")).unwrap().bio.len(), 1);
+        assert_eq!(run(content.get_category("web")).unwrap().title, "Web");
+        assert_eq!(run(content.get_category("go")), Err(FetchError::NotFound));
+    }
+
+    #[test]
+    fn search_needs_every_term_somewhere() {
+        let content = LocalContent::new(SITE, POSTS);
+        let search = |query| {
+            run(content.search(query, 10))
+                .unwrap()
+                .into_iter()
+                .map(|result| result.post.slug.current)
+                .collect::<Vec<_>>()
+        };
+        // Title matches count most; equally good ones stay newest first.
+        assert_eq!(search("rust"), vec!["new", "draft", "also-new"]);
+        // One term in the title and one in the text is enough.
+        assert_eq!(search("browser webassem"), vec!["new"]);
+        assert_eq!(search("RUST crabs"), Vec::<String>::new());
+        assert_eq!(search(""), Vec::<String>::new());
+        assert_eq!(run(content.search("rust", 1)).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn a_broken_site_json_fails_every_request() {
+        let content = LocalContent::new("{", POSTS);
+        assert!(matches!(
+            run(content.list_posts(&PostFilter::All, 0, 10)),
+            Err(FetchError::Decode(_))
+        ));
+        assert!(matches!(
+            run(content.search("rust", 10)),
+            Err(FetchError::Decode(_))
+        ));
+    }
+}
@@ -0,0 +1,251 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, Duration, Utc};
+use gloo_console::{error, log};
+use gloo_storage::{LocalStorage, Storage};
+use serde::{Deserialize, Serialize};
+
+// Used unless the build sets `LOGIN_MAX_FAILURES`, `LOGIN_BASE_DELAY_SECONDS`
+// or `LOGIN_LOCKOUT_MINUTES`.
+const DEFAULT_MAX_FAILURES: u32 = 5;
+const DEFAULT_BASE_DELAY_SECONDS: i64 = 1;
+const DEFAULT_LOCKOUT_MINUTES: i64 = 15;
+
+// LocalStorage key for the failed login attempts.
+const ATTEMPTS_KEY: &str = "login_attempts";
+
+/// How failed logins are slowed down.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LoginPolicy {
+    /// Failures in a row after which the email is locked out.
+    pub max_failures: u32,
+    /// The wait after the first failure. It doubles with every failure after that.
+    pub base_delay: Duration,
+    /// How long a locked out email has to wait. Its failures are forgotten afterwards, as are
+    /// failures that are older than this.
+    pub lockout: Duration,
+}
+
+impl Default for LoginPolicy {
+    /// Uses `LOGIN_MAX_FAILURES`, `LOGIN_BASE_DELAY_SECONDS` and `LOGIN_LOCKOUT_MINUTES` from
+    /// the build environment, falling back to 5 failures, 1 second and 15 minutes.
+    fn default() -> Self {
+        fn parse<T: std::str::FromStr>(value: Option<&str>, default: T) -> T {
+            value
+                .and_then(|value| value.parse().ok())
+                .unwrap_or(default)
+        }
+        Self {
+            max_failures: parse(option_env!("LOGIN_MAX_FAILURES"), DEFAULT_MAX_FAILURES).max(1),
+            base_delay: Duration::seconds(parse(
+                option_env!("LOGIN_BASE_DELAY_SECONDS"),
+                DEFAULT_BASE_DELAY_SECONDS,
+            )),
+            lockout: Duration::minutes(parse(
+                option_env!("LOGIN_LOCKOUT_MINUTES"),
+                DEFAULT_LOCKOUT_MINUTES,
+            )),
+        }
+    }
+}
+
+impl LoginPolicy {
+    /// How long to wait after the `failures`-th failure in a row.
+    pub fn delay_after(&self, failures: u32) -> Duration {
+        match failures {
+            0 => Duration::zero(),
+            failures if failures >= self.max_failures => self.lockout,
+            // Never waits longer than a lockout, however the policy is configured.
+            failures => (self.base_delay * 2i32.saturating_pow(failures - 1)).min(self.lockout),
+        }
+    }
+}
+
+/// The failed logins for one email.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct Failures {
+    count: u32,
+    last_at: DateTime<Utc>,
+}
+
+/// Failed logins per email, kept in LocalStorage so reloading the page doesn't reset them.
+///
+/// This only slows down someone using this app; providers that check passwords on a server
+/// have to limit attempts there too.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct LoginThrottle {
+    failures: HashMap<String, Failures>,
+}
+
+impl LoginThrottle {
+    pub fn load() -> Self {
+        LocalStorage::get(ATTEMPTS_KEY).unwrap_or_default()
+    }
+
+    fn save(&self) {
+        if let Err(err) = LocalStorage::set(ATTEMPTS_KEY, self) {
+            error!("Couldn't save failed login attempts:", err.to_string());
+        }
+    }
+
+    // Emails are compared the way people type them, so "Test@Example.com " counts as "test@example.com".
+    fn key(email: &str) -> String {
+        email.trim().to_lowercase()
+    }
+
+    /// How long `email` has to wait before it may try again, if at all.
+    pub fn wait_time(&self, email: &str, policy: &LoginPolicy) -> Option<Duration> {
+        self.wait_time_at(email, policy, Utc::now())
+    }
+
+    fn wait_time_at(
+        &self,
+        email: &str,
+        policy: &LoginPolicy,
+        now: DateTime<Utc>,
+    ) -> Option<Duration> {
+        let failures = self.failures.get(&Self::key(email))?;
+        let wait = failures.last_at + policy.delay_after(failures.count) - now;
+        (wait > Duration::zero()).then_some(wait)
+    }
+
+    /// Count a failed login for `email`.
+    pub fn record_failure(&mut self, email: &str, policy: &LoginPolicy) {
+        if self.count_failure(email, policy, Utc::now()) >= policy.max_failures {
+            log!("Too many failed logins, locking out the email.");
+        }
+        self.save();
+    }
+
+    /// Count a failed login for `email` at `now`, and answer how many failures in a row it has.
+    /// Failures from before a finished lockout, or as long ago as one lasts, start over.
+    fn count_failure(&mut self, email: &str, policy: &LoginPolicy, now: DateTime<Utc>) -> u32 {
+        let failures = self.failures.entry(Self::key(email)).or_insert(Failures {
+            count: 0,
+            last_at: now,
+        });
+        if failures.count >= policy.max_failures || now - failures.last_at >= policy.lockout {
+            failures.count = 0;
+        }
+        failures.count += 1;
+        failures.last_at = now;
+        failures.count
+    }
+
+    /// Forget the failures of `email` after it logged in.
+    pub fn record_success(&mut self, email: &str) {
+        if self.failures.remove(&Self::key(email)).is_some() {
+            self.save();
+        }
+    }
+}
+
+/// A wait like "3 minutes" or "42 seconds", rounded up.
+pub fn describe_wait(wait: Duration) -> String {
+    let seconds = (wait.num_milliseconds() + 999) / 1000;
+    match seconds {
+        ..=1 => "1 second".to_string(),
+        2..=90 => format!("{} seconds", seconds),
+        _ => format!("{} minutes", (seconds + 59) / 60),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy() -> LoginPolicy {
+        LoginPolicy {
+            max_failures: 5,
+            base_delay: Duration::seconds(1),
+            lockout: Duration::minutes(15),
+        }
+    }
+
+    #[test]
+    fn the_delay_doubles_until_the_lockout() {
+        let delays = (0..=6)
+            .map(|failures| policy().delay_after(failures))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            delays,
+            [0, 1, 2, 4, 8, 900, 900].map(Duration::seconds).to_vec()
+        );
+        let slow = LoginPolicy {
+            base_delay: Duration::minutes(10),
+            ..policy()
+        };
+        assert_eq!(slow.delay_after(3), Duration::minutes(15));
+        assert_eq!(policy().delay_after(u32::MAX - 1), Duration::minutes(15));
+    }
+
+    #[test]
+    fn failures_count_per_email_until_the_lockout() {
+        let (policy, start) = (policy(), Utc::now());
+        let mut throttle = LoginThrottle::default();
+        assert_eq!(
+            throttle.wait_time_at("ada@example.com", &policy, start),
+            None
+        );
+        for count in 1..=5 {
+            assert_eq!(
+                throttle.count_failure(" Ada@Example.com", &policy, start),
+                count
+            );
+        }
+        assert_eq!(
+            throttle.wait_time_at("ada@example.com", &policy, start),
+            Some(Duration::minutes(15))
+        );
+        let later = start + Duration::minutes(10);
+        assert_eq!(
+            throttle.wait_time_at("ada@example.com", &policy, later),
+            Some(Duration::minutes(5))
+        );
+        assert_eq!(
+            throttle.wait_time_at("grace@example.com", &policy, later),
+            None
+        );
+    }
+
+    #[test]
+    fn failures_start_over_after_a_lockout() {
+        let (policy, start) = (policy(), Utc::now());
+        let mut throttle = LoginThrottle::default();
+        for _ in 0..5 {
+            throttle.count_failure("ada@example.com", &policy, start);
+        }
+        let after = start + Duration::minutes(15);
+        assert_eq!(
+            throttle.wait_time_at("ada@example.com", &policy, after),
+            None
+        );
+        assert_eq!(throttle.count_failure("ada@example.com", &policy, after), 1);
+        assert_eq!(
+            throttle.wait_time_at("ada@example.com", &policy, after),
+            Some(Duration::seconds(1))
+        );
+    }
+
+    #[test]
+    fn old_failures_are_forgotten() {
+        let (policy, start) = (policy(), Utc::now());
+        let mut throttle = LoginThrottle::default();
+        for _ in 0..4 {
+            throttle.count_failure("ada@example.com", &policy, start);
+        }
+        let tomorrow = start + Duration::days(1);
+        assert_eq!(
+            throttle.count_failure("ada@example.com", &policy, tomorrow),
+            1
+        );
+    }
+
+    #[test]
+    fn waits_are_described_rounded_up() {
+        assert_eq!(describe_wait(Duration::milliseconds(200)), "1 second");
+        assert_eq!(describe_wait(Duration::milliseconds(41_200)), "42 seconds");
+        assert_eq!(describe_wait(Duration::seconds(91)), "2 minutes");
+        assert_eq!(describe_wait(Duration::minutes(15)), "15 minutes");
+    }
+}
@@ -0,0 +1,335 @@
+use pulldown_cmark::{Event, Options, Parser, Tag, TagEnd};
+use serde_json::Map;
+
+use crate::components::{ArticleBody, ArticleBodyAsset, ArticleBodyChild, MarkDef};
+
+/// Markdown as the Portable Text blocks `PortableText` renders.
+///
+/// Paragraphs, headings, block quotes, (nested) lists, emphasis, strong, strikethrough, inline code,
+/// links and images carry over. Code blocks become paragraphs of code, and anything Portable Text
+/// has no block for (tables, raw HTML, rules) is left out.
+pub fn to_portable_text(markdown: &str) -> Vec<ArticleBody> {
+    let mut converter = Converter::default();
+    for event in Parser::new_ext(markdown, Options::ENABLE_STRIKETHROUGH) {
+        converter.event(event);
+    }
+    converter.finish_block();
+    converter.blocks
+}
+
+/// The text of `blocks` without any formatting, one block per paragraph.
+pub fn plain_text(blocks: &[ArticleBody]) -> String {
+    blocks
+        .iter()
+        .filter_map(|block| block.children.as_ref())
+        .map(|children| {
+            children
+                .iter()
+                .map(|child| child.text.as_str())
+                .collect::<String>()
+        })
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+/// Builds up the blocks while walking through the parser's events.
+#[derive(Default)]
+struct Converter {
+    blocks: Vec<ArticleBody>,
+    /// The text block being filled.
+    current: Option<ArticleBody>,
+    /// Images found in the current block. They follow it as blocks of their own.
+    images: Vec<ArticleBody>,
+    /// The marks of the text, innermost last.
+    marks: Vec<String>,
+    /// "bullet" or "number" for each list we are in, innermost last.
+    lists: Vec<&'static str>,
+    quote_depth: u32,
+    /// The URL and alt text of the image being read.
+    image: Option<(String, String)>,
+    next_key: u32,
+}
+
+impl Converter {
+    fn key(&mut self) -> String {
+        self.next_key += 1;
+        format!("md{}", self.next_key)
+    }
+
+    fn event(&mut self, event: Event) {
+        match event {
+            // Paragraphs inside a list item continue the item's block.
+            Event::Start(Tag::Paragraph) if self.current.is_some() => {}
+            Event::Start(Tag::Paragraph) => {
+                let style = if self.quote_depth > 0 {
+                    "blockquote"
+                } else {
+                    "normal"
+                };
+                self.start_block(style, None);
+            }
+            Event::Start(Tag::Heading { level, .. }) => {
+                self.start_block(&format!("h{}", level as u8), None)
+            }
+            Event::Start(Tag::CodeBlock(_)) => {
+                self.start_block("normal", None);
+                self.marks.push("code".to_string());
+            }
+            Event::Start(Tag::BlockQuote(_)) => self.quote_depth += 1,
+            Event::Start(Tag::List(first)) => {
+                // A list nested in an item ends the item's own text.
+                self.finish_block();
+                self.lists
+                    .push(if first.is_some() { "number" } else { "bullet" });
+            }
+            Event::Start(Tag::Item) => {
+                let list_item = self.lists.last().copied().unwrap_or("bullet");
+                self.start_block("normal", Some(list_item));
+            }
+            Event::Start(Tag::Emphasis) => self.marks.push("em".to_string()),
+            Event::Start(Tag::Strong) => self.marks.push("strong".to_string()),
+            Event::Start(Tag::Strikethrough) => self.marks.push("strike-through".to_string()),
+            Event::Start(Tag::Link { dest_url, .. }) => {
+                let key = self.key();
+                self.block().mark_defs.push(MarkDef {
+                    key: key.clone(),
+                    content_type: "link".to_string(),
+                    href: Some(dest_url.to_string()),
+                    extra: Map::new(),
+                });
+                self.marks.push(key);
+            }
+            Event::Start(Tag::Image { dest_url, .. }) => {
+                self.image = Some((dest_url.to_string(), String::new()))
+            }
+            Event::End(TagEnd::Paragraph) if self.lists.is_empty() => self.finish_block(),
+            Event::End(TagEnd::Heading(_) | TagEnd::Item) => self.finish_block(),
+            Event::End(TagEnd::CodeBlock) => {
+                self.marks.pop();
+                self.finish_block();
+            }
+            Event::End(TagEnd::BlockQuote(_)) => self.quote_depth -= 1,
+            Event::End(TagEnd::List(_)) => {
+                self.lists.pop();
+            }
+            Event::End(
+                TagEnd::Emphasis | TagEnd::Strong | TagEnd::Strikethrough | TagEnd::Link,
+            ) => {
+                self.marks.pop();
+            }
+            Event::End(TagEnd::Image) => {
+                if let Some((url, alt)) = self.image.take() {
+                    let key = self.key();
+                    self.images.push(image_block(key, url, alt));
+                }
+            }
+            Event::Text(text) => match &mut self.image {
+                Some((_, alt)) => alt.push_str(&text),
+                None => self.span(&text, None),
+            },
+            Event::Code(code) => self.span(&code, Some("code")),
+            Event::SoftBreak => self.span(" ", None),
+            Event::HardBreak => self.span("\n", None),
+            _ => {}
+        }
+    }
+
+    fn start_block(&mut self, style: &str, list_item: Option<&str>) {
+        self.finish_block();
+        let key = self.key();
+        self.current = Some(ArticleBody {
+            content_type: "block".to_string(),
+            style: Some(style.to_string()),
+            key,
+            asset: None,
+            alt: None,
+            children: Some(Vec::new()),
+            list_item: list_item.map(str::to_string),
+            level: list_item.map(|_| self.lists.len() as u32),
+            mark_defs: Vec::new(),
+        });
+    }
+
+    /// The text block being filled, starting a paragraph if there is none (e.g. after a nested list).
+    fn block(&mut self) -> &mut ArticleBody {
+        if self.current.is_none() {
+            self.start_block("normal", None);
+        }
+        self.current.as_mut().expect("a block was just started")
+    }
+
+    fn span(&mut self, text: &str, extra_mark: Option<&str>) {
+        let key = self.key();
+        let mut marks = self.marks.clone();
+        marks.extend(extra_mark.map(str::to_string));
+        if let Some(children) = &mut self.block().children {
+            children.push(ArticleBodyChild {
+                content_type: "span".to_string(),
+                key,
+                text: text.to_string(),
+                marks,
+            });
+        }
+    }
+
+    /// Add the current block, unless it is empty, and the images found in it.
+    fn finish_block(&mut self) {
+        if let Some(block) = self.current.take() {
+            if block
+                .children
+                .as_ref()
+                .is_some_and(|children| !children.is_empty())
+            {
+                self.blocks.push(block);
+            }
+        }
+        self.blocks.append(&mut self.images);
+    }
+}
+
+fn image_block(key: String, url: String, alt: String) -> ArticleBody {
+    let extension = url.rsplit('.').next().unwrap_or_default().to_lowercase();
+    let mime_type = match extension.as_str() {
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "svg" => "image/svg+xml",
+        "webp" => "image/webp",
+        _ => "image/png",
+    };
+    ArticleBody {
+        content_type: "image".to_string(),
+        style: None,
+        key,
+        asset: Some(ArticleBodyAsset {
+            url,
+            mime_type: mime_type.to_string(),
+        }),
+        alt: (!alt.is_empty()).then_some(alt),
+        children: None,
+        list_item: None,
+        level: None,
+        mark_defs: Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The style, list item, level and text of each block, to compare at a glance.
+    fn outline(blocks: &[ArticleBody]) -> Vec<(String, Option<String>, Option<u32>, String)> {
+        blocks
+            .iter()
+            .map(|block| {
+                (
+                    block
+                        .style
+                        .clone()
+                        .unwrap_or_else(|| block.content_type.clone()),
+                    block.list_item.clone(),
+                    block.level,
+                    plain_text(std::slice::from_ref(block)),
+                )
+            })
+            .collect()
+    }
+
+    fn block(
+        style: &str,
+        list_item: Option<&str>,
+        level: Option<u32>,
+        text: &str,
+    ) -> (String, Option<String>, Option<u32>, String) {
+        (
+            style.to_string(),
+            list_item.map(str::to_string),
+            level,
+            text.to_string(),
+        )
+    }
+
+    #[test]
+    fn headings_paragraphs_and_quotes() {
+        let blocks = to_portable_text("# Title\n\nSome *text*\non two lines.\n\n> Quoted");
+        assert_eq!(
+            outline(&blocks),
+            vec![
+                block("h1", None, None, "Title"),
+                block("normal", None, None, "Some text on two lines."),
+                block("blockquote", None, None, "Quoted"),
+            ]
+        );
+        let spans = blocks[1].children.as_ref().unwrap();
+        assert_eq!(spans[1].text, "text");
+        assert_eq!(spans[1].marks, vec!["em"]);
+    }
+
+    #[test]
+    fn nested_lists() {
+        let blocks = to_portable_text("- one\n  1. one.a\n  2. one.b\n- two\n\n1. first");
+        assert_eq!(
+            outline(&blocks),
+            vec![
+                block("normal", Some("bullet"), Some(1), "one"),
+                block("normal", Some("number"), Some(2), "one.a"),
+                block("normal", Some("number"), Some(2), "one.b"),
+                block("normal", Some("bullet"), Some(1), "two"),
+                block("normal", Some("number"), Some(1), "first"),
+            ]
+        );
+    }
+
+    #[test]
+    fn links_become_mark_defs() {
+        let blocks = to_portable_text("See [**the docs**](https://yew.rs) and `code`.");
+        let block = &blocks[0];
+        assert_eq!(block.mark_defs.len(), 1);
+        let link = &block.mark_defs[0];
+        assert_eq!(link.content_type, "link");
+        assert_eq!(link.href.as_deref(), Some("https://yew.rs"));
+        let spans = block.children.as_ref().unwrap();
+        assert_eq!(spans[1].text, "the docs");
+        assert_eq!(spans[1].marks, vec![link.key.clone(), "strong".to_string()]);
+        assert_eq!(spans[3].marks, vec!["code"]);
+    }
+
+    #[test]
+    fn images_follow_their_paragraph() {
+        let blocks = to_portable_text("Look: ![A *crab*](ferris.webp) there.\n\n![](logo)");
+        assert_eq!(
+            outline(&blocks),
+            vec![
+                block("normal", None, None, "Look:  there."),
+                block("image", None, None, ""),
+                block("image", None, None, ""),
+            ]
+        );
+        let asset = blocks[1].asset.as_ref().unwrap();
+        assert_eq!(asset.url, "ferris.webp");
+        assert_eq!(asset.mime_type, "image/webp");
+        assert_eq!(blocks[1].alt.as_deref(), Some("A crab"));
+        assert_eq!(blocks[2].asset.as_ref().unwrap().mime_type, "image/png");
+        assert_eq!(blocks[2].alt, None);
+    }
+
+    #[test]
+    fn keys_are_unique() {
+        let blocks = to_portable_text("- a [b](c)\n- d\n\n![e](f.png)");
+        let mut keys = blocks
+            .iter()
+            .flat_map(|block| {
+                std::iter::once(block.key.clone()).chain(
+                    block
+                        .children
+                        .iter()
+                        .flatten()
+                        .map(|child| child.key.clone()),
+                )
+            })
+            .collect::<Vec<_>>();
+        let count = keys.len();
+        keys.sort();
+        keys.dedup();
+        assert_eq!(keys.len(), count);
+    }
+}
@@ -1 +1,20 @@
+pub mod auth;
+pub mod auth_context;
+pub mod content;
+pub mod dates;
+pub mod fetch;
+#[cfg(feature = "local-content")]
+pub mod local_content;
+pub mod login_throttle;
+#[cfg(feature = "local-content")]
+pub mod markdown;
+pub mod oidc;
+pub mod password;
+pub mod roles;
+pub mod sanity;
+pub mod session;
+pub mod totp;
 pub mod user_manager;
+pub mod user_repository;
+pub mod validation;
+pub mod visibility;
@@ -0,0 +1,659 @@
+use std::{fmt, rc::Rc};
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use gloo_console::log;
+use gloo_net::http::Request;
+use gloo_storage::{SessionStorage, Storage};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use super::{
+    auth::{AuthError, AuthFuture},
+    fetch::FetchError,
+    password::random_token,
+    roles::Role,
+    user_manager::{validate_profile, UserProfile},
+    validation::{validate_username, ValidationErrors},
+};
+
+// Used unless the build sets `OIDC_CLIENT_ID`.
+const DEFAULT_CLIENT_ID: &str = "user-manager-frontend";
+// The path of `Route::AuthCallback`, which the identity provider sends the browser back to.
+const CALLBACK_PATH: &str = "/auth/callback";
+const SCOPE: &str = "openid profile email";
+// SessionStorage key for the login waiting for the identity provider. The redirect comes back to the same tab.
+const PENDING_LOGIN_KEY: &str = "oidc_login";
+// How long a login may take at the identity provider.
+const PENDING_LOGIN_MINUTES: i64 = 10;
+
+/// Everything that can go wrong while logging in through an identity provider.
+#[derive(Debug, Clone, PartialEq)]
+pub enum OidcError {
+    /// The identity provider turned the login down, e.g. because the user cancelled it.
+    Denied(String),
+    /// The callback doesn't belong to a login started in this tab, or that login took too long.
+    NoLoginInProgress,
+    /// The `state` that came back isn't the one that was sent.
+    StateMismatch,
+    /// The ID token can't be read, or isn't meant for this app and this login.
+    InvalidIdToken(String),
+    /// The email belongs to an account with a password or a second factor, which single sign-on
+    /// mustn't get around, or to another account at the identity provider.
+    AccountExists,
+    /// The details from the identity provider don't make a valid account, e.g. without a birthday.
+    InvalidProfile(Vec<String>),
+}
+
+impl fmt::Display for OidcError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OidcError::Denied(reason) => {
+                write!(f, "The identity provider refused the login: {}", reason)
+            }
+            OidcError::NoLoginInProgress => {
+                write!(
+                    f,
+                    "This login has expired or was started elsewhere. Please try again."
+                )
+            }
+            OidcError::StateMismatch => {
+                write!(f, "This login doesn't match the one that was started.")
+            }
+            OidcError::InvalidIdToken(reason) => {
+                write!(f, "The identity provider's answer is invalid: {}", reason)
+            }
+            OidcError::AccountExists => write!(
+                f,
+                "An account with this email already exists. Please log in with its password."
+            ),
+            OidcError::InvalidProfile(messages) => write!(
+                f,
+                "Your details at the identity provider can't be used to sign up: {}",
+                messages.join(" ")
+            ),
+        }
+    }
+}
+
+/// The `aud` claim, which is a single client ID or a list of them.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Audience {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl Audience {
+    pub fn contains(&self, client_id: &str) -> bool {
+        match self {
+            Audience::One(audience) => audience == client_id,
+            Audience::Many(audiences) => audiences.iter().any(|audience| audience == client_id),
+        }
+    }
+}
+
+/// An account at an identity provider: the provider's `iss` and the `sub` it knows the account by.
+/// Unlike the email, it never changes, so accounts made through single sign-on are found by it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExternalIdentity {
+    pub issuer: String,
+    pub subject: String,
+}
+
+/// The claims of an ID token that this app looks at.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IdTokenClaims {
+    pub iss: String,
+    pub sub: String,
+    pub aud: Audience,
+    /// Expiry, in seconds since the epoch.
+    pub exp: i64,
+    #[serde(default)]
+    pub nonce: Option<String>,
+    #[serde(default)]
+    pub email: Option<String>,
+    #[serde(default)]
+    pub email_verified: Option<bool>,
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub preferred_username: Option<String>,
+    /// `YYYY-MM-DD`, if the identity provider knows it.
+    #[serde(default)]
+    pub birthdate: Option<String>,
+}
+
+impl IdTokenClaims {
+    /// The account at the identity provider these claims are about.
+    pub fn identity(&self) -> ExternalIdentity {
+        ExternalIdentity {
+            issuer: self.iss.clone(),
+            subject: self.sub.clone(),
+        }
+    }
+
+    /// The email, if the identity provider says it has verified it. One without an
+    /// `email_verified` claim is refused too.
+    pub fn verified_email(&self) -> Result<String, OidcError> {
+        match (&self.email, self.email_verified) {
+            (Some(email), Some(true)) => Ok(email.trim().to_string()),
+            _ => Err(OidcError::InvalidIdToken("no verified email".to_string())),
+        }
+    }
+
+    /// A new reader account for these claims, checked like a sign-up.
+    ///
+    /// The username is `preferred_username`, or the start of the email if that isn't a valid
+    /// username. The birthday comes from `birthdate`, without which nobody is signed up.
+    pub fn profile(&self) -> Result<UserProfile, OidcError> {
+        let email = self.verified_email()?;
+        let username = [self.preferred_username.as_deref(), email.split('@').next()]
+            .into_iter()
+            .flatten()
+            .map(|candidate| {
+                candidate
+                    .chars()
+                    .filter(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.'))
+                    .collect::<String>()
+            })
+            .find(|candidate| validate_username(candidate).is_ok())
+            .unwrap_or_default();
+        let name = self.name.clone().unwrap_or_else(|| username.clone());
+        let birthday = self
+            .birthdate
+            .as_deref()
+            .and_then(|birthdate| NaiveDate::parse_from_str(birthdate, "%Y-%m-%d").ok());
+
+        validate_profile(
+            &username,
+            &email,
+            &name,
+            birthday,
+            Role::default(),
+            ValidationErrors::default(),
+        )
+        .map_err(|errors| OidcError::InvalidProfile(errors.messages().map(String::from).collect()))
+    }
+}
+
+/// What is sent to the authorization endpoint.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AuthorizationRequest {
+    pub client_id: String,
+    pub redirect_uri: String,
+    pub state: String,
+    pub nonce: String,
+    /// The SHA-256 of the code verifier (PKCE, RFC 7636), base64url without padding.
+    pub code_challenge: String,
+}
+
+impl AuthorizationRequest {
+    /// The request as a query string, for the authorization endpoint's URL.
+    pub fn query(&self) -> String {
+        [
+            ("response_type", "code"),
+            ("client_id", &self.client_id),
+            ("redirect_uri", &self.redirect_uri),
+            ("scope", SCOPE),
+            ("state", &self.state),
+            ("nonce", &self.nonce),
+            ("code_challenge", &self.code_challenge),
+            ("code_challenge_method", "S256"),
+        ]
+        .iter()
+        .map(|(key, value)| format!("{}={}", key, urlencoding::encode(value)))
+        .collect::<Vec<_>>()
+        .join("&")
+    }
+}
+
+/// What is sent to the token endpoint to exchange the code from the callback.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TokenRequest {
+    pub client_id: String,
+    pub redirect_uri: String,
+    pub code: String,
+    pub code_verifier: String,
+}
+
+/// What the token endpoint answers with. Only the ID token is used.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TokenResponse {
+    pub id_token: String,
+}
+
+/// An OpenID Connect identity provider that logs users in with the authorization code flow.
+pub trait OidcIssuer {
+    /// The `iss` of the ID tokens it hands out.
+    fn issuer(&self) -> &str;
+
+    /// Where to send the browser to log in.
+    fn authorization_url<'a>(&'a self, request: &'a AuthorizationRequest)
+        -> AuthFuture<'a, String>;
+
+    /// Exchange the code the browser came back with for tokens.
+    fn exchange_code<'a>(&'a self, request: &'a TokenRequest) -> AuthFuture<'a, TokenResponse>;
+}
+
+/// The identity provider the app offers, if any. Builds that set `OIDC_ISSUER` use that one.
+/// Builds without `AUTH_ENDPOINT` either, which keep everything in the browser, get the mock issuer.
+pub fn default_issuer() -> Option<Rc<dyn OidcIssuer>> {
+    match (option_env!("OIDC_ISSUER"), option_env!("AUTH_ENDPOINT")) {
+        (Some(issuer), _) => Some(Rc::new(HttpIssuer::new(issuer))),
+        (None, None) => Some(Rc::new(MockIssuer)),
+        (None, Some(_)) => None,
+    }
+}
+
+fn client_id() -> String {
+    option_env!("OIDC_CLIENT_ID")
+        .unwrap_or(DEFAULT_CLIENT_ID)
+        .to_string()
+}
+
+fn redirect_uri() -> Result<String, AuthError> {
+    let origin = web_sys::window()
+        .expect("Couldn't get the window")
+        .location()
+        .origin()
+        .map_err(|err| FetchError::Network(format!("{:?}", err)))?;
+    Ok(format!("{}{}", origin, CALLBACK_PATH))
+}
+
+// The PKCE code challenge for `code_verifier`.
+fn code_challenge(code_verifier: &str) -> String {
+    URL_SAFE_NO_PAD.encode(Sha256::digest(code_verifier.as_bytes()))
+}
+
+/// A login waiting for the identity provider to send the browser back.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct PendingLogin {
+    state: String,
+    nonce: String,
+    code_verifier: String,
+    remember: bool,
+    /// The path to go to once logged in.
+    next: String,
+    started_at: DateTime<Utc>,
+}
+
+/// A login the identity provider has vouched for.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OidcLogin {
+    pub id_token: String,
+    pub claims: IdTokenClaims,
+    /// Whether the session should outlive the browser tab.
+    pub remember: bool,
+    /// The path to go to now.
+    pub next: String,
+}
+
+/// Send the browser to `issuer` to log in. It comes back to `Route::AuthCallback`,
+/// which goes on to `next` once `finish_login` is done.
+pub async fn begin_login(
+    issuer: &dyn OidcIssuer,
+    remember: bool,
+    next: &str,
+) -> Result<(), AuthError> {
+    let pending = PendingLogin {
+        state: random_token(),
+        nonce: random_token(),
+        code_verifier: random_token(),
+        remember,
+        next: next.to_string(),
+        started_at: Utc::now(),
+    };
+    let url = issuer
+        .authorization_url(&AuthorizationRequest {
+            client_id: client_id(),
+            redirect_uri: redirect_uri()?,
+            state: pending.state.clone(),
+            nonce: pending.nonce.clone(),
+            code_challenge: code_challenge(&pending.code_verifier),
+        })
+        .await?;
+
+    SessionStorage::set(PENDING_LOGIN_KEY, &pending)
+        .map_err(|err| FetchError::Network(err.to_string()))?;
+    log!("Sending the browser to the identity provider.");
+    web_sys::window()
+        .expect("Couldn't get the window")
+        .location()
+        .set_href(&url)
+        .map_err(|err| FetchError::Network(format!("{:?}", err)))?;
+    Ok(())
+}
+
+/// Finish the login started by `begin_login` with the `code` and `state` the browser came back with.
+///
+/// The ID token's signature isn't checked: it comes straight from the token endpoint over TLS,
+/// which OpenID Connect accepts for this flow. Its issuer, audience, expiry and nonce are.
+/// Each login can only be finished once.
+pub async fn finish_login(
+    issuer: &dyn OidcIssuer,
+    code: &str,
+    state: &str,
+) -> Result<OidcLogin, AuthError> {
+    let pending = SessionStorage::get::<PendingLogin>(PENDING_LOGIN_KEY)
+        .ok()
+        .filter(|pending| {
+            Utc::now() - pending.started_at < Duration::minutes(PENDING_LOGIN_MINUTES)
+        })
+        .ok_or(OidcError::NoLoginInProgress)?;
+    SessionStorage::delete(PENDING_LOGIN_KEY);
+    if pending.state != state {
+        return Err(OidcError::StateMismatch.into());
+    }
+
+    let tokens = issuer
+        .exchange_code(&TokenRequest {
+            client_id: client_id(),
+            redirect_uri: redirect_uri()?,
+            code: code.to_string(),
+            code_verifier: pending.code_verifier,
+        })
+        .await?;
+    let claims = decode_claims(&tokens.id_token)?;
+
+    let invalid = |reason: &str| Err(OidcError::InvalidIdToken(reason.to_string()).into());
+    if claims.iss != issuer.issuer() {
+        return invalid("wrong issuer");
+    }
+    if !claims.aud.contains(&client_id()) {
+        return invalid("meant for another client");
+    }
+    if claims.exp <= Utc::now().timestamp() {
+        return invalid("expired");
+    }
+    if claims.nonce.as_deref() != Some(pending.nonce.as_str()) {
+        return invalid("wrong nonce");
+    }
+
+    log!("The identity provider vouched for the login.");
+    Ok(OidcLogin {
+        id_token: tokens.id_token,
+        claims,
+        remember: pending.remember,
+        next: pending.next,
+    })
+}
+
+/// The claims in the payload of the JWT `id_token`.
+fn decode_claims(id_token: &str) -> Result<IdTokenClaims, OidcError> {
+    let invalid = |reason: String| OidcError::InvalidIdToken(reason);
+    let payload = id_token
+        .split('.')
+        .nth(1)
+        .ok_or_else(|| invalid("not a JWT".to_string()))?;
+    let json = URL_SAFE_NO_PAD
+        .decode(payload.trim_end_matches('='))
+        .map_err(|err| invalid(err.to_string()))?;
+    serde_json::from_slice(&json).map_err(|err| invalid(err.to_string()))
+}
+
+/// The parts of the discovery document this app needs.
+#[derive(Deserialize)]
+struct Discovery {
+    authorization_endpoint: String,
+    token_endpoint: String,
+}
+
+/// How the token endpoint turns a request down.
+#[derive(Deserialize)]
+struct TokenError {
+    error: String,
+    #[serde(default)]
+    error_description: Option<String>,
+}
+
+/// An identity provider reached over HTTP. Its endpoints are found through
+/// `{issuer}/.well-known/openid-configuration`, and it must allow this app's origin (CORS)
+/// on the token endpoint. The client is public: it authenticates with PKCE, not a secret.
+pub struct HttpIssuer {
+    issuer: String,
+}
+
+impl HttpIssuer {
+    pub fn new(issuer: &str) -> Self {
+        Self {
+            issuer: issuer.trim_end_matches('/').to_string(),
+        }
+    }
+
+    async fn discover(&self) -> Result<Discovery, AuthError> {
+        let url = format!("{}/.well-known/openid-configuration", self.issuer);
+        let response = Request::get(&url).send().await?;
+        if !response.ok() {
+            return Err(FetchError::Status(response.status()).into());
+        }
+        Ok(response.json().await?)
+    }
+}
+
+impl OidcIssuer for HttpIssuer {
+    fn issuer(&self) -> &str {
+        &self.issuer
+    }
+
+    fn authorization_url<'a>(
+        &'a self,
+        request: &'a AuthorizationRequest,
+    ) -> AuthFuture<'a, String> {
+        Box::pin(async move {
+            let discovery = self.discover().await?;
+            Ok(format!(
+                "{}?{}",
+                discovery.authorization_endpoint,
+                request.query()
+            ))
+        })
+    }
+
+    fn exchange_code<'a>(&'a self, request: &'a TokenRequest) -> AuthFuture<'a, TokenResponse> {
+        Box::pin(async move {
+            let discovery = self.discover().await?;
+            let body = [
+                ("grant_type", "authorization_code"),
+                ("client_id", &request.client_id),
+                ("redirect_uri", &request.redirect_uri),
+                ("code", &request.code),
+                ("code_verifier", &request.code_verifier),
+            ]
+            .iter()
+            .map(|(key, value)| format!("{}={}", key, urlencoding::encode(value)))
+            .collect::<Vec<_>>()
+            .join("&");
+
+            let response = Request::post(&discovery.token_endpoint)
+                .header("Content-Type", "application/x-www-form-urlencoded")
+                .body(body)?
+                .send()
+                .await?;
+            match response.status() {
+                400 | 401 => {
+                    let err: TokenError = response.json().await?;
+                    Err(OidcError::Denied(err.error_description.unwrap_or(err.error)).into())
+                }
+                status if !response.ok() => Err(FetchError::Status(status).into()),
+                _ => Ok(response.json().await?),
+            }
+        })
+    }
+}
+
+// The `iss` of the mock issuer's ID tokens.
+const MOCK_ISSUER: &str = "https://mock-issuer.invalid";
+// SessionStorage key for the codes the mock issuer has handed out.
+const MOCK_GRANTS_KEY: &str = "mock_oidc_grants";
+
+/// A code the mock issuer has handed out, with what it was handed out for.
+#[derive(Clone, Serialize, Deserialize)]
+struct MockGrant {
+    code: String,
+    client_id: String,
+    redirect_uri: String,
+    nonce: String,
+    code_challenge: String,
+}
+
+/// Stands in for an identity provider during development and tests, entirely in the browser.
+/// It logs everyone in as the same test identity without asking, and checks the PKCE verifier
+/// like a real one would. Its ID tokens aren't signed.
+pub struct MockIssuer;
+
+impl MockIssuer {
+    fn grants() -> Vec<MockGrant> {
+        SessionStorage::get(MOCK_GRANTS_KEY).unwrap_or_default()
+    }
+
+    fn save_grants(grants: &[MockGrant]) -> Result<(), AuthError> {
+        SessionStorage::set(MOCK_GRANTS_KEY, grants)
+            .map_err(|err| FetchError::Network(err.to_string()).into())
+    }
+}
+
+impl OidcIssuer for MockIssuer {
+    fn issuer(&self) -> &str {
+        MOCK_ISSUER
+    }
+
+    fn authorization_url<'a>(
+        &'a self,
+        request: &'a AuthorizationRequest,
+    ) -> AuthFuture<'a, String> {
+        Box::pin(async move {
+            let code = random_token();
+            let mut grants = Self::grants();
+            grants.push(MockGrant {
+                code: code.clone(),
+                client_id: request.client_id.clone(),
+                redirect_uri: request.redirect_uri.clone(),
+                nonce: request.nonce.clone(),
+                code_challenge: request.code_challenge.clone(),
+            });
+            Self::save_grants(&grants)?;
+
+            // Approves straight away, so the browser goes right back to the app.
+            Ok(format!(
+                "{}?code={}&state={}",
+                request.redirect_uri,
+                urlencoding::encode(&code),
+                urlencoding::encode(&request.state)
+            ))
+        })
+    }
+
+    fn exchange_code<'a>(&'a self, request: &'a TokenRequest) -> AuthFuture<'a, TokenResponse> {
+        Box::pin(async move {
+            // Codes only work once, whether or not the exchange succeeds.
+            let mut grants = Self::grants();
+            let grant = grants
+                .iter()
+                .position(|grant| grant.code == request.code)
+                .map(|index| grants.remove(index));
+            Self::save_grants(&grants)?;
+
+            let grant = grant
+                .filter(|grant| {
+                    grant.client_id == request.client_id
+                        && grant.redirect_uri == request.redirect_uri
+                        && grant.code_challenge == code_challenge(&request.code_verifier)
+                })
+                .ok_or(OidcError::Denied("invalid_grant".to_string()))?;
+
+            let claims = IdTokenClaims {
+                iss: MOCK_ISSUER.to_string(),
+                sub: "mock-user".to_string(),
+                aud: Audience::One(grant.client_id),
+                exp: (Utc::now() + Duration::minutes(5)).timestamp(),
+                nonce: Some(grant.nonce),
+                email: Some("sso.user@example.com".to_string()),
+                email_verified: Some(true),
+                name: Some("SSO Test Person".to_string()),
+                preferred_username: Some("SsoUser".to_string()),
+                birthdate: Some("1990-01-01".to_string()),
+            };
+            let header = URL_SAFE_NO_PAD.encode(r#"{"alg":"none","typ":"JWT"}"#);
+            let payload =
+                serde_json::to_vec(&claims).map_err(|err| FetchError::Decode(err.to_string()))?;
+            Ok(TokenResponse {
+                id_token: format!("{}.{}.", header, URL_SAFE_NO_PAD.encode(payload)),
+            })
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn claims(email_verified: Option<bool>) -> IdTokenClaims {
+        IdTokenClaims {
+            iss: MOCK_ISSUER.to_string(),
+            sub: "42".to_string(),
+            aud: Audience::One("blog".to_string()),
+            exp: 0,
+            nonce: None,
+            email: Some("ada@example.com".to_string()),
+            email_verified,
+            name: None,
+            preferred_username: None,
+            birthdate: Some("1990-01-01".to_string()),
+        }
+    }
+
+    #[test]
+    fn profile_needs_a_verified_email() {
+        assert_eq!(
+            claims(Some(true)).profile().map(|profile| profile.email),
+            Ok("ada@example.com".to_string())
+        );
+        assert!(claims(Some(false)).profile().is_err());
+        // No claim at all is no promise that the email is theirs.
+        assert!(claims(None).profile().is_err());
+    }
+
+    #[test]
+    fn profile_falls_back_to_the_start_of_the_email() {
+        let profile = claims(Some(true)).profile().unwrap();
+        assert_eq!(profile.username, "ada");
+        assert_eq!(profile.name, "ada");
+        assert_eq!(profile.role, Role::default());
+
+        let short = IdTokenClaims {
+            preferred_username: Some("a!".to_string()),
+            ..claims(Some(true))
+        };
+        assert_eq!(short.profile().unwrap().username, "ada");
+    }
+
+    #[test]
+    fn profile_is_checked_like_a_sign_up() {
+        let no_birthday = IdTokenClaims {
+            birthdate: None,
+            ..claims(Some(true))
+        };
+        assert_eq!(
+            no_birthday.profile(),
+            Err(OidcError::InvalidProfile(vec![
+                "Please enter your birthday.".to_string()
+            ]))
+        );
+
+        let unusable = IdTokenClaims {
+            email: Some("x@example.com".to_string()),
+            name: Some("X".to_string()),
+            preferred_username: Some("".to_string()),
+            birthdate: Some("2999-01-01".to_string()),
+            ..claims(Some(true))
+        };
+        assert_eq!(
+            unusable.profile(),
+            Err(OidcError::InvalidProfile(vec![
+                "Username must be at least 3 characters.".to_string(),
+                "Birthday can't be in the future.".to_string(),
+            ]))
+        );
+    }
+}
@@ -0,0 +1,414 @@
+use argon2::{Algorithm, Argon2, Params, Version};
+use base64::{
+    engine::general_purpose::{STANDARD_NO_PAD, URL_SAFE_NO_PAD},
+    Engine,
+};
+use pbkdf2::pbkdf2_hmac;
+use sha2::{Digest, Sha256};
+use subtle::ConstantTimeEq;
+
+/// PBKDF2 rounds for new PBKDF2 hashes, as OWASP recommends for PBKDF2-HMAC-SHA256.
+pub const DEFAULT_PBKDF2_ITERATIONS: u32 = 600_000;
+/// Argon2id costs for new hashes: OWASP's minimum of 19 MiB of memory and 2 passes over it.
+/// As strong as the PBKDF2 rounds above, but a fraction of the time in the browser's main thread.
+pub const DEFAULT_ARGON2_MEMORY_KIB: u32 = 19_456;
+pub const DEFAULT_ARGON2_PASSES: u32 = 2;
+const SALT_LENGTH: usize = 16;
+const HASH_LENGTH: usize = 32;
+const TOKEN_LENGTH: usize = 32;
+
+/// The outcome of checking a password against a stored hash.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PasswordCheck {
+    /// The password doesn't match.
+    Invalid,
+    /// The password matches and the hash is up to date.
+    Valid,
+    /// The password matches, but the hash uses an old scheme or a lower cost and should be replaced.
+    ValidNeedsRehash,
+}
+
+/// A way of turning passwords into stored hash strings.
+///
+/// Every hasher writes a self-describing string, so hashes made by different hashers
+/// (or with different costs) can live side by side and be told apart later.
+pub trait PasswordHasher {
+    /// Whether `encoded` was produced by this hasher.
+    fn recognizes(&self, encoded: &str) -> bool;
+
+    /// Hash `password` with a fresh salt.
+    fn hash(&self, password: &str) -> String;
+
+    /// Check `password` against a hash this hasher recognizes.
+    fn verify(&self, password: &str, encoded: &str) -> bool;
+
+    /// Whether a matching `encoded` hash should be replaced by a new one, e.g. because its cost is too low.
+    fn needs_rehash(&self, _encoded: &str) -> bool {
+        false
+    }
+}
+
+/// Salted PBKDF2-HMAC-SHA256, stored as `$pbkdf2-sha256$i=<iterations>$<salt>$<hash>`
+/// with salt and hash in unpadded base64.
+pub struct Pbkdf2Sha256 {
+    pub iterations: u32,
+}
+
+impl Default for Pbkdf2Sha256 {
+    fn default() -> Self {
+        Self {
+            iterations: DEFAULT_PBKDF2_ITERATIONS,
+        }
+    }
+}
+
+impl Pbkdf2Sha256 {
+    const PREFIX: &'static str = "$pbkdf2-sha256$";
+
+    fn derive(password: &str, salt: &[u8], iterations: u32) -> [u8; HASH_LENGTH] {
+        let mut hash = [0u8; HASH_LENGTH];
+        pbkdf2_hmac::<Sha256>(password.as_bytes(), salt, iterations, &mut hash);
+        hash
+    }
+
+    /// Split a stored hash into its iteration count, salt and hash.
+    fn parse(encoded: &str) -> Option<(u32, Vec<u8>, Vec<u8>)> {
+        let mut parts = encoded.strip_prefix(Self::PREFIX)?.split('$');
+        let iterations = parts.next()?.strip_prefix("i=")?.parse().ok()?;
+        let salt = STANDARD_NO_PAD.decode(parts.next()?).ok()?;
+        let hash = STANDARD_NO_PAD.decode(parts.next()?).ok()?;
+        match parts.next() {
+            None => Some((iterations, salt, hash)),
+            Some(_) => None,
+        }
+    }
+}
+
+impl PasswordHasher for Pbkdf2Sha256 {
+    fn recognizes(&self, encoded: &str) -> bool {
+        encoded.starts_with(Self::PREFIX)
+    }
+
+    fn hash(&self, password: &str) -> String {
+        let mut salt = [0u8; SALT_LENGTH];
+        getrandom::getrandom(&mut salt).expect("Couldn't generate a password salt");
+        let hash = Self::derive(password, &salt, self.iterations);
+
+        format!(
+            "{}i={}${}${}",
+            Self::PREFIX,
+            self.iterations,
+            STANDARD_NO_PAD.encode(salt),
+            STANDARD_NO_PAD.encode(hash)
+        )
+    }
+
+    fn verify(&self, password: &str, encoded: &str) -> bool {
+        match Self::parse(encoded) {
+            Some((iterations, salt, expected)) => Self::derive(password, &salt, iterations)[..]
+                .ct_eq(&expected)
+                .into(),
+            None => false,
+        }
+    }
+
+    fn needs_rehash(&self, encoded: &str) -> bool {
+        !matches!(Self::parse(encoded), Some((iterations, _, _)) if iterations >= self.iterations)
+    }
+}
+
+/// Salted Argon2id with one lane, stored as `$argon2id$v=19$m=<KiB>,t=<passes>,p=1$<salt>$<hash>`
+/// with salt and hash in unpadded base64, like other Argon2 implementations write it.
+pub struct Argon2id {
+    pub memory_kib: u32,
+    pub passes: u32,
+}
+
+impl Default for Argon2id {
+    fn default() -> Self {
+        Self {
+            memory_kib: DEFAULT_ARGON2_MEMORY_KIB,
+            passes: DEFAULT_ARGON2_PASSES,
+        }
+    }
+}
+
+impl Argon2id {
+    const PREFIX: &'static str = "$argon2id$v=19$";
+
+    fn derive(
+        password: &str,
+        salt: &[u8],
+        memory_kib: u32,
+        passes: u32,
+    ) -> Option<[u8; HASH_LENGTH]> {
+        let params = Params::new(memory_kib, passes, 1, Some(HASH_LENGTH)).ok()?;
+        let mut hash = [0u8; HASH_LENGTH];
+        Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+            .hash_password_into(password.as_bytes(), salt, &mut hash)
+            .ok()?;
+        Some(hash)
+    }
+
+    /// Split a stored hash into its memory cost, passes, salt and hash.
+    fn parse(encoded: &str) -> Option<(u32, u32, Vec<u8>, Vec<u8>)> {
+        let mut parts = encoded.strip_prefix(Self::PREFIX)?.split('$');
+        let mut costs = parts.next()?.split(',');
+        let memory_kib = costs.next()?.strip_prefix("m=")?.parse().ok()?;
+        let passes = costs.next()?.strip_prefix("t=")?.parse().ok()?;
+        if costs.next()? != "p=1" || costs.next().is_some() {
+            return None;
+        }
+        let salt = STANDARD_NO_PAD.decode(parts.next()?).ok()?;
+        let hash = STANDARD_NO_PAD.decode(parts.next()?).ok()?;
+        match parts.next() {
+            None => Some((memory_kib, passes, salt, hash)),
+            Some(_) => None,
+        }
+    }
+}
+
+impl PasswordHasher for Argon2id {
+    fn recognizes(&self, encoded: &str) -> bool {
+        encoded.starts_with(Self::PREFIX)
+    }
+
+    fn hash(&self, password: &str) -> String {
+        let mut salt = [0u8; SALT_LENGTH];
+        getrandom::getrandom(&mut salt).expect("Couldn't generate a password salt");
+        let hash = Self::derive(password, &salt, self.memory_kib, self.passes)
+            .expect("Argon2 costs out of range");
+
+        format!(
+            "{}m={},t={},p=1${}${}",
+            Self::PREFIX,
+            self.memory_kib,
+            self.passes,
+            STANDARD_NO_PAD.encode(salt),
+            STANDARD_NO_PAD.encode(hash)
+        )
+    }
+
+    fn verify(&self, password: &str, encoded: &str) -> bool {
+        let Some((memory_kib, passes, salt, expected)) = Self::parse(encoded) else {
+            return false;
+        };
+        match Self::derive(password, &salt, memory_kib, passes) {
+            Some(hash) => hash[..].ct_eq(&expected).into(),
+            None => false,
+        }
+    }
+
+    fn needs_rehash(&self, encoded: &str) -> bool {
+        !matches!(
+            Self::parse(encoded),
+            Some((memory_kib, passes, _, _)) if memory_kib >= self.memory_kib && passes >= self.passes
+        )
+    }
+}
+
+/// The unsalted SHA-256 hex digests this app used to store. Only kept so that
+/// existing accounts can still log in and get upgraded; always due for a rehash.
+pub struct LegacySha256;
+
+impl PasswordHasher for LegacySha256 {
+    fn recognizes(&self, encoded: &str) -> bool {
+        encoded.len() == 64 && encoded.bytes().all(|b| b.is_ascii_hexdigit())
+    }
+
+    fn hash(&self, password: &str) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(password);
+        format!("{:x}", hasher.finalize())
+    }
+
+    fn verify(&self, password: &str, encoded: &str) -> bool {
+        self.hash(password)
+            .as_bytes()
+            .ct_eq(encoded.to_ascii_lowercase().as_bytes())
+            .into()
+    }
+
+    fn needs_rehash(&self, _encoded: &str) -> bool {
+        true
+    }
+}
+
+/// The hasher used for new passwords.
+pub fn current_hasher() -> Box<dyn PasswordHasher> {
+    Box::new(Argon2id::default())
+}
+
+/// Every hasher we can still verify, newest first.
+fn known_hashers() -> Vec<Box<dyn PasswordHasher>> {
+    vec![
+        current_hasher(),
+        Box::new(Pbkdf2Sha256::default()),
+        Box::new(LegacySha256),
+    ]
+}
+
+/// Hash a new password with the current hasher.
+pub fn hash_password(password: &str) -> String {
+    current_hasher().hash(password)
+}
+
+/// Check `password` against `encoded`, whichever hasher produced it.
+pub fn check_password(password: &str, encoded: &str) -> PasswordCheck {
+    let current = current_hasher();
+    let Some(hasher) = known_hashers().into_iter().find(|h| h.recognizes(encoded)) else {
+        return PasswordCheck::Invalid;
+    };
+
+    if !hasher.verify(password, encoded) {
+        PasswordCheck::Invalid
+    } else if !current.recognizes(encoded) || hasher.needs_rehash(encoded) {
+        PasswordCheck::ValidNeedsRehash
+    } else {
+        PasswordCheck::Valid
+    }
+}
+
+/// Take as long as checking `password` against a current hash would, without a hash to check.
+/// For when there's no account to check, so a quick answer doesn't give away that it doesn't exist.
+pub fn check_no_password(password: &str) {
+    std::hint::black_box(current_hasher().hash(password));
+}
+
+/// A random token that is safe to put in a URL, e.g. for password reset links.
+pub fn random_token() -> String {
+    let mut token = [0u8; TOKEN_LENGTH];
+    getrandom::getrandom(&mut token).expect("Couldn't generate a token");
+    URL_SAFE_NO_PAD.encode(token)
+}
+
+/// What to store instead of a token, so whoever reads the storage can't use it.
+/// Tokens are long and random, so a plain SHA-256 is enough.
+pub fn hash_token(token: &str) -> String {
+    STANDARD_NO_PAD.encode(Sha256::digest(token.as_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The PBKDF2-HMAC-SHA256 test vectors from RFC 7914, section 11, cut to our 32 bytes:
+    // "passwd" with the salt "salt" and "Password" with the salt "NaCl".
+    const ONE_ROUND: &str = "$pbkdf2-sha256$i=1$c2FsdA$VawEblbjCJ/sFpHCJUS2BflBhSFt3gRl5oudV8INrLw";
+    const MANY_ROUNDS: &str =
+        "$pbkdf2-sha256$i=80000$TmFDbA$TdzY9guYviGDDO5e8icB+WQaRBjQTAQUrv8Ih2s0q1Y";
+    // SHA-256 of "password123", as the app used to store it.
+    const LEGACY: &str = "ef92b778bafe771e89245b89ecbc08a44a4e166c06659911881f383d4473e94f";
+
+    #[test]
+    fn pbkdf2_matches_the_known_answers() {
+        let hasher = Pbkdf2Sha256 { iterations: 1 };
+        assert!(hasher.verify("passwd", ONE_ROUND));
+        assert!(hasher.verify("Password", MANY_ROUNDS));
+        assert!(!hasher.verify("password", ONE_ROUND));
+    }
+
+    #[test]
+    fn pbkdf2_hashes_with_a_fresh_salt() {
+        let hasher = Pbkdf2Sha256 { iterations: 2 };
+        let (first, second) = (hasher.hash("password"), hasher.hash("password"));
+        assert_ne!(first, second);
+        assert!(first.starts_with("$pbkdf2-sha256$i=2$"));
+        assert!(hasher.verify("password", &first) && hasher.verify("password", &second));
+        assert!(!hasher.needs_rehash(&first));
+        assert!(!Pbkdf2Sha256 { iterations: 1 }.needs_rehash(&first));
+        assert!(Pbkdf2Sha256 { iterations: 3 }.needs_rehash(&first));
+    }
+
+    #[test]
+    fn argon2id_hashes_with_a_fresh_salt() {
+        let hasher = Argon2id {
+            memory_kib: 16,
+            passes: 2,
+        };
+        let (first, second) = (hasher.hash("password"), hasher.hash("password"));
+        assert_ne!(first, second);
+        assert!(first.starts_with("$argon2id$v=19$m=16,t=2,p=1$"));
+        assert!(hasher.verify("password", &first) && hasher.verify("password", &second));
+        assert!(!hasher.verify("passwort", &first));
+        assert!(!hasher.needs_rehash(&first));
+        assert!(!Argon2id {
+            memory_kib: 8,
+            passes: 1
+        }
+        .needs_rehash(&first));
+        assert!(Argon2id {
+            memory_kib: 32,
+            passes: 2
+        }
+        .needs_rehash(&first));
+        assert!(Argon2id {
+            memory_kib: 16,
+            passes: 3
+        }
+        .needs_rehash(&first));
+    }
+
+    #[test]
+    fn argon2id_rejects_malformed_hashes() {
+        let hasher = Argon2id {
+            memory_kib: 16,
+            passes: 1,
+        };
+        let hash = hasher.hash("password");
+        assert!(!hasher.verify("password", &hash.replace("p=1", "p=2")));
+        assert!(!hasher.verify("password", &hash.replace("m=16", "m=x")));
+        assert!(!hasher.verify("password", &format!("{}$extra", hash)));
+        assert!(!hasher.verify("password", "$argon2id$v=19$m=16,t=1,p=1$c2FsdA"));
+    }
+
+    #[test]
+    fn pbkdf2_rejects_malformed_hashes() {
+        let hasher = Pbkdf2Sha256 { iterations: 1 };
+        assert!(!hasher.verify("password", "$pbkdf2-sha256$i=1$c2FsdA"));
+        assert!(!hasher.verify("password", &format!("{}$extra", ONE_ROUND)));
+        assert!(!hasher.verify("password", "$pbkdf2-sha256$i=x$c2FsdA$Eg"));
+    }
+
+    #[test]
+    fn legacy_hashes_still_log_in_and_get_upgraded() {
+        assert_eq!(LegacySha256.hash("password123"), LEGACY);
+        assert_eq!(
+            check_password("password123", LEGACY),
+            PasswordCheck::ValidNeedsRehash
+        );
+        assert_eq!(
+            check_password("password123", &LEGACY.to_ascii_uppercase()),
+            PasswordCheck::ValidNeedsRehash
+        );
+        assert_eq!(
+            check_password("password124", LEGACY),
+            PasswordCheck::Invalid
+        );
+    }
+
+    #[test]
+    fn current_hashes_are_kept_and_older_schemes_replaced() {
+        let hash = hash_password("password123");
+        assert!(hash.starts_with("$argon2id$"));
+        assert_eq!(check_password("password123", &hash), PasswordCheck::Valid);
+        assert_eq!(check_password("password12", &hash), PasswordCheck::Invalid);
+        assert_eq!(
+            check_password("passwd", ONE_ROUND),
+            PasswordCheck::ValidNeedsRehash
+        );
+        assert_eq!(
+            check_password("password", ONE_ROUND),
+            PasswordCheck::Invalid
+        );
+        assert_eq!(check_password("password", "plain"), PasswordCheck::Invalid);
+    }
+
+    #[test]
+    fn tokens_are_random_and_stored_hashed() {
+        let token = random_token();
+        assert_ne!(token, random_token());
+        assert_eq!(hash_token(&token), hash_token(&token));
+        assert_ne!(hash_token(&token), token);
+    }
+}
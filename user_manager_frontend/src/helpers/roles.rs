@@ -0,0 +1,49 @@
+use std::{fmt, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+
+/// What a user is allowed to do. Every role includes the ones before it, so admins can do
+/// everything editors can, and editors everything readers can.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    /// Can read articles. What new accounts get.
+    #[default]
+    Reader,
+    /// Can also write and edit content.
+    Editor,
+    /// Can also manage users.
+    Admin,
+}
+
+impl Role {
+    /// Every role, lowest first.
+    pub const ALL: [Role; 3] = [Role::Reader, Role::Editor, Role::Admin];
+
+    /// Whether someone with this role may do what `required` is needed for.
+    pub fn includes(self, required: Role) -> bool {
+        self >= required
+    }
+}
+
+impl fmt::Display for Role {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Role::Reader => write!(f, "reader"),
+            Role::Editor => write!(f, "editor"),
+            Role::Admin => write!(f, "admin"),
+        }
+    }
+}
+
+impl FromStr for Role {
+    type Err = String;
+
+    /// Parses the names `Display` writes, e.g. from a `<select>`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Role::ALL
+            .into_iter()
+            .find(|role| role.to_string() == s)
+            .ok_or_else(|| format!("Unknown role: {}", s))
+    }
+}
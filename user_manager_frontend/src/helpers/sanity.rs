@@ -0,0 +1,283 @@
+use gloo_net::http::Request;
+use serde::{de::DeserializeOwned, Deserialize};
+use serde_json::{json, Value};
+
+use super::{
+    content::{
+        search_terms, Article, Author, CategoryDetails, ContentFuture, ContentSource, PostFilter,
+        PostSlice, SearchResult,
+    },
+    fetch::FetchError,
+};
+
+// The project we read content from unless the build overrides it with environment variables.
+const DEFAULT_PROJECT_ID: &str = "1fuw6fjt";
+const DEFAULT_DATASET: &str = "production";
+const DEFAULT_API_VERSION: &str = "2022-03-07";
+
+/// The JSON envelope Sanity wraps every query result in.
+#[derive(Deserialize)]
+struct QueryResponse<T> {
+    result: T,
+}
+
+/// Client for running GROQ queries against one Sanity project and dataset.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SanityClient {
+    project_id: String,
+    dataset: String,
+    api_version: String,
+    use_cdn: bool,
+}
+
+impl Default for SanityClient {
+    /// Uses `SANITY_PROJECT_ID`, `SANITY_DATASET`, `SANITY_API_VERSION` and `SANITY_USE_CDN` from
+    /// the build environment, falling back to the project this blog has always used.
+    fn default() -> Self {
+        Self::new(
+            option_env!("SANITY_PROJECT_ID").unwrap_or(DEFAULT_PROJECT_ID),
+            option_env!("SANITY_DATASET").unwrap_or(DEFAULT_DATASET),
+            option_env!("SANITY_API_VERSION").unwrap_or(DEFAULT_API_VERSION),
+        )
+        .with_cdn(option_env!("SANITY_USE_CDN") == Some("true"))
+    }
+}
+
+impl SanityClient {
+    /// Create a client for the given project, dataset and API version (e.g. "2022-03-07").
+    pub fn new(project_id: &str, dataset: &str, api_version: &str) -> Self {
+        Self {
+            project_id: project_id.to_string(),
+            dataset: dataset.to_string(),
+            api_version: api_version.trim_start_matches('v').to_string(),
+            use_cdn: false,
+        }
+    }
+
+    /// Read from Sanity's CDN instead of the live API. Faster, but may serve slightly stale content.
+    pub fn with_cdn(mut self, use_cdn: bool) -> Self {
+        self.use_cdn = use_cdn;
+        self
+    }
+
+    /// The endpoint queries are sent to.
+    fn query_url(&self) -> String {
+        let host = if self.use_cdn { "apicdn" } else { "api" };
+        format!(
+            "https://{}.{}.sanity.io/v{}/data/query/{}",
+            self.project_id, host, self.api_version, self.dataset
+        )
+    }
+
+    /// Run a GROQ query and decode its `result` into `T`.
+    ///
+    /// Every `(name, value)` in `params` is available in the query as `$name`. Values are sent
+    /// JSON-encoded as separate query-string parameters, so they are never spliced into the GROQ itself.
+    pub async fn query<T: DeserializeOwned>(
+        &self,
+        groq: &str,
+        params: &[(&str, Value)],
+    ) -> Result<T, FetchError> {
+        let params = params
+            .iter()
+            .map(|(name, value)| (format!("${}", name), value.to_string()))
+            .collect::<Vec<_>>();
+
+        let response = Request::get(&self.query_url())
+            .query([("query", groq)])
+            .query(params.iter().map(|(name, value)| (name.as_str(), value)))
+            .send()
+            .await?;
+
+        if !response.ok() {
+            return Err(FetchError::Status(response.status()));
+        }
+
+        Ok(response.json::<QueryResponse<T>>().await?.result)
+    }
+}
+
+// The GROQ fields a `BlogPost` needs, for a projection after a query for posts.
+// Posts without categories or tags get empty lists.
+const POST_CARD_FIELDS: &str = r#"slug,title,"logoUrl":logo.asset->url,
+  "categories": coalesce(categories[]->{title, "slug": slug.current}[defined(slug)], []),
+  "tags": coalesce(tags, [])"#;
+
+// GROQ query for a single article. `$slug` is passed as a query parameter, never spliced into the query.
+const ARTICLE_QUERY: &str = r#"*[_type == "post" && slug.current == $slug][0] {
+  body[] {
+    ...,
+    asset->{...,"_key": _id},
+    markDefs[] {
+      ...,
+      _type == "internalLink" => {"slug": @.reference->slug.current}
+    }
+  },
+  title,
+  "categories": coalesce(categories[]->{title, "slug": slug.current}[defined(slug)], []),
+  "tags": coalesce(tags, []),
+  "author": author->{name, "slug": slug.current, "imageUrl": image.asset->url},
+  publishedAt,
+  _updatedAt,
+  logo {
+    ...,
+    asset->{...,"_key": _id}
+  }
+}"#;
+
+// GROQ query for an author's profile. `$slug` is passed as a query parameter.
+const AUTHOR_QUERY: &str = r#"*[_type == "author" && slug.current == $slug][0] {
+  name,
+  "imageUrl": image.asset->url,
+  "bio": coalesce(bio, [])
+}"#;
+
+// GROQ query for the title and description of a category. `$slug` is passed as a query parameter.
+const CATEGORY_QUERY: &str =
+    r#"*[_type == "category" && slug.current == $slug][0]{title, description}"#;
+
+// The GROQ condition for the posts `filter` lets through, with the parameter it uses.
+fn filter_condition(filter: &PostFilter) -> (&'static str, Option<(&'static str, Value)>) {
+    match filter {
+        PostFilter::All => (r#"_type == "post""#, None),
+        PostFilter::Tag(tag) => (
+            r#"_type == "post" && $tag in tags"#,
+            Some(("tag", json!(tag))),
+        ),
+        PostFilter::Category(slug) => (
+            r#"_type == "post" && $category in categories[]->slug.current"#,
+            Some(("category", json!(slug))),
+        ),
+        PostFilter::Author(slug) => (
+            r#"_type == "post" && author->slug.current == $author"#,
+            Some(("author", json!(slug))),
+        ),
+    }
+}
+
+// GROQ query for one slice of the posts matching `condition`, newest first, along with how many
+// there are. `_id` breaks ties between posts published at the same moment, so slices never overlap
+// or skip a post. `$start` and `$end` are passed as query parameters.
+fn posts_query(condition: &str) -> String {
+    format!(
+        r#"{{
+  "posts": *[{condition}] | order(publishedAt desc, _id asc) [$start...$end] {{{fields}}},
+  "total": count(*[{condition}])
+}}"#,
+        condition = condition,
+        fields = POST_CARD_FIELDS
+    )
+}
+
+// GROQ query for the posts that have each of `term_count` terms in the title or the text, best
+// matches first. A term counts three times as much in the title, as it does for `LocalContent`.
+// The terms are passed as the query parameters `$t0`, `$t1`, …, along with `$limit`.
+fn search_query(term_count: usize) -> String {
+    let terms = (0..term_count)
+        .map(|i| format!("$t{}", i))
+        .collect::<Vec<_>>();
+    let condition = terms
+        .iter()
+        .map(|term| format!("(title match {term} || pt::text(body) match {term})"))
+        .collect::<Vec<_>>()
+        .join(" && ");
+    let score = terms
+        .iter()
+        .map(|term| format!("boost(title match {term}, 3), pt::text(body) match {term}"))
+        .collect::<Vec<_>>()
+        .join(", ");
+    format!(
+        r#"*[_type == "post" && {condition}]
+  | score({score})
+  | order(_score desc, publishedAt desc, _id asc) [0...$limit] {{{fields}, "text": pt::text(body)}}"#,
+        condition = condition,
+        score = score,
+        fields = POST_CARD_FIELDS
+    )
+}
+
+impl SanityClient {
+    /// Run a GROQ query for a single document, which Sanity answers with `null` if it doesn't exist.
+    async fn query_one<T: DeserializeOwned>(
+        &self,
+        groq: &str,
+        params: &[(&str, Value)],
+    ) -> Result<T, FetchError> {
+        self.query::<Option<T>>(groq, params)
+            .await?
+            .ok_or(FetchError::NotFound)
+    }
+}
+
+impl ContentSource for SanityClient {
+    fn list_posts<'a>(
+        &'a self,
+        filter: &'a PostFilter,
+        start: u32,
+        count: u32,
+    ) -> ContentFuture<'a, PostSlice> {
+        Box::pin(async move {
+            let (condition, param) = filter_condition(filter);
+            let mut params = vec![
+                ("start", json!(start)),
+                ("end", json!(start.saturating_add(count))),
+            ];
+            params.extend(param);
+            self.query(&posts_query(condition), &params).await
+        })
+    }
+
+    fn get_post_by_slug<'a>(&'a self, slug: &'a str) -> ContentFuture<'a, Article> {
+        Box::pin(async move {
+            self.query_one(ARTICLE_QUERY, &[("slug", json!(slug))])
+                .await
+        })
+    }
+
+    fn search<'a>(&'a self, query: &'a str, limit: u32) -> ContentFuture<'a, Vec<SearchResult>> {
+        Box::pin(async move {
+            let terms = search_terms(query);
+            if terms.is_empty() {
+                return Ok(Vec::new());
+            }
+            let names = (0..terms.len())
+                .map(|i| format!("t{}", i))
+                .collect::<Vec<_>>();
+            // `match` compares whole words, so each term also matches as the start of a longer word.
+            let mut params = names
+                .iter()
+                .zip(&terms)
+                .map(|(name, term)| (name.as_str(), json!(format!("{}*", term))))
+                .collect::<Vec<_>>();
+            params.push(("limit", json!(limit)));
+            self.query(&search_query(terms.len()), &params).await
+        })
+    }
+
+    fn get_author<'a>(&'a self, slug: &'a str) -> ContentFuture<'a, Author> {
+        Box::pin(async move { self.query_one(AUTHOR_QUERY, &[("slug", json!(slug))]).await })
+    }
+
+    fn get_category<'a>(&'a self, slug: &'a str) -> ContentFuture<'a, CategoryDetails> {
+        Box::pin(async move {
+            self.query_one(CATEGORY_QUERY, &[("slug", json!(slug))])
+                .await
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn search_needs_each_term_in_the_title_or_the_text() {
+        let query = search_query(2);
+        assert!(query.contains(
+            r#"_type == "post" && (title match $t0 || pt::text(body) match $t0) && (title match $t1 || pt::text(body) match $t1)]"#
+        ));
+        assert!(query.contains(
+            "score(boost(title match $t0, 3), pt::text(body) match $t0, boost(title match $t1, 3), pt::text(body) match $t1)"
+        ));
+    }
+}
@@ -0,0 +1,215 @@
+use chrono::{DateTime, Duration, Utc};
+use gloo_console::{error, log};
+use gloo_events::EventListener;
+use gloo_storage::{LocalStorage, SessionStorage, Storage};
+use gloo_timers::callback::Interval;
+use serde::{Deserialize, Serialize};
+use yew::{hook, use_effect_with, Callback};
+
+use super::{
+    auth::{default_provider, AuthSession},
+    roles::Role,
+};
+
+// Used unless the build sets `SESSION_LIFETIME_HOURS` or `SESSION_IDLE_MINUTES`.
+const DEFAULT_LIFETIME_HOURS: i64 = 12;
+const DEFAULT_IDLE_MINUTES: i64 = 30;
+
+// Storage key for the current session.
+const SESSION_KEY: &str = "session";
+// Keys written by older versions of the app. `login_state` held the whole `UserState`,
+// password hash included.
+const LEGACY_KEYS: [&str; 2] = ["login", "login_state"];
+
+// Page events that count as the user being active.
+const ACTIVITY_EVENTS: [&str; 4] = ["mousedown", "keydown", "scroll", "touchstart"];
+// Activity is written to storage at most this often.
+const TOUCH_INTERVAL_SECS: i64 = 30;
+// How often `use_idle_timeout` checks whether the session is still alive.
+const IDLE_CHECK_INTERVAL_MS: u32 = 15_000;
+
+/// How long sessions last.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SessionPolicy {
+    /// Time from login until the session expires, unless the provider says otherwise.
+    pub lifetime: Duration,
+    /// Time without any activity after which the user is logged out.
+    pub idle_timeout: Duration,
+}
+
+impl Default for SessionPolicy {
+    /// Uses `SESSION_LIFETIME_HOURS` and `SESSION_IDLE_MINUTES` from the build environment,
+    /// falling back to 12 hours and 30 minutes.
+    fn default() -> Self {
+        let parse = |value: Option<&str>, default| {
+            value
+                .and_then(|value| value.parse().ok())
+                .unwrap_or(default)
+        };
+        Self {
+            lifetime: Duration::hours(parse(
+                option_env!("SESSION_LIFETIME_HOURS"),
+                DEFAULT_LIFETIME_HOURS,
+            )),
+            idle_timeout: Duration::minutes(parse(
+                option_env!("SESSION_IDLE_MINUTES"),
+                DEFAULT_IDLE_MINUTES,
+            )),
+        }
+    }
+}
+
+/// The part of a login that is kept in browser storage.
+///
+/// Only what is needed to greet the user and to ask the provider for the rest:
+/// no password hash, email or birthday. The role only decides which pages are shown;
+/// the provider has to check it again for anything that matters. The full `UserManager` lives in memory only.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Session {
+    pub token: String,
+    pub display_name: String,
+    /// Sessions stored before roles existed are readers.
+    #[serde(default)]
+    pub role: Role,
+    pub issued_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+    pub last_active_at: DateTime<Utc>,
+    /// Kept in LocalStorage so it survives closing the browser. Otherwise it lives in
+    /// SessionStorage and ends with the tab.
+    pub remember: bool,
+}
+
+impl Session {
+    /// The session to store for a fresh login.
+    pub fn from_auth(session: &AuthSession, remember: bool) -> Self {
+        let now = Utc::now();
+        Session {
+            token: session.token.clone(),
+            display_name: session.user.name.clone(),
+            role: session.user.role,
+            issued_at: now,
+            expires_at: session
+                .expires_at
+                .unwrap_or(now + SessionPolicy::default().lifetime),
+            last_active_at: now,
+            remember,
+        }
+    }
+
+    /// This session after the provider refreshed it. The expiry only moves if the provider says so,
+    /// so refreshing can't keep a session alive forever.
+    pub fn renew(self, session: &AuthSession) -> Self {
+        Session {
+            token: session.token.clone(),
+            display_name: session.user.name.clone(),
+            role: session.user.role,
+            expires_at: session.expires_at.unwrap_or(self.expires_at),
+            last_active_at: Utc::now(),
+            ..self
+        }
+    }
+
+    /// Whether the session has run out or has been idle for too long.
+    pub fn is_expired(&self, policy: &SessionPolicy) -> bool {
+        let now = Utc::now();
+        self.expires_at <= now || self.last_active_at + policy.idle_timeout <= now
+    }
+
+    /// The stored session, if there is one that is still alive. Expired sessions are removed.
+    pub fn load() -> Option<Self> {
+        let session = SessionStorage::get::<Session>(SESSION_KEY)
+            .or_else(|_| LocalStorage::get::<Session>(SESSION_KEY))
+            .ok()?;
+        if session.is_expired(&SessionPolicy::default()) {
+            log!("Stored session has expired.");
+            Self::clear();
+            return None;
+        }
+        Some(session)
+    }
+
+    /// Store the session in LocalStorage or SessionStorage, depending on `remember`.
+    pub fn save(&self) {
+        let result = if self.remember {
+            SessionStorage::delete(SESSION_KEY);
+            LocalStorage::set(SESSION_KEY, self)
+        } else {
+            LocalStorage::delete(SESSION_KEY);
+            SessionStorage::set(SESSION_KEY, self)
+        };
+        if let Err(err) = result {
+            error!("Couldn't save the session:", err.to_string());
+        }
+    }
+
+    pub fn clear() {
+        LocalStorage::delete(SESSION_KEY);
+        SessionStorage::delete(SESSION_KEY);
+    }
+
+    /// Record that the user is active, pushing back the idle timeout.
+    pub fn touch() {
+        if let Some(mut session) = Self::load() {
+            let now = Utc::now();
+            if now - session.last_active_at >= Duration::seconds(TOUCH_INTERVAL_SECS) {
+                session.last_active_at = now;
+                session.save();
+            }
+        }
+    }
+}
+
+/// Remove login state written by older versions of the app. Anyone who was logged in
+/// that way has to log in again.
+pub fn purge_legacy_state() {
+    for key in LEGACY_KEYS {
+        if LocalStorage::raw().get_item(key).ok().flatten().is_some() {
+            log!("Removing legacy login state:", key);
+            LocalStorage::delete(key);
+        }
+    }
+}
+
+/// Keep the stored session alive while the user is active on the page, and call `on_expired`
+/// once it has expired or been idle for too long. The provider is told the session has ended.
+#[hook]
+pub fn use_idle_timeout(on_expired: Callback<()>) {
+    use_effect_with((), move |_| {
+        let document = web_sys::window()
+            .and_then(|window| window.document())
+            .expect("Couldn't get the document");
+        let listeners: Vec<EventListener> = ACTIVITY_EVENTS
+            .iter()
+            .map(|event| EventListener::new(&document, *event, |_| Session::touch()))
+            .collect();
+
+        // The token is gone from storage once the session has expired, so remember the latest one.
+        let mut token = Session::load().map(|session| session.token);
+        let mut expired = false;
+        let interval = Interval::new(IDLE_CHECK_INTERVAL_MS, move || {
+            if expired {
+                return;
+            }
+            match Session::load() {
+                Some(session) => token = Some(session.token),
+                None => {
+                    expired = true;
+                    if let Some(token) = token.take() {
+                        wasm_bindgen_futures::spawn_local(async move {
+                            if let Err(err) = default_provider().logout(&token).await {
+                                error!("Logout failed at the auth provider:", err.to_string());
+                            }
+                        });
+                    }
+                    log!("Session ended, logging out.");
+                    on_expired.emit(());
+                }
+            }
+        });
+
+        move || {
+            drop(listeners);
+            drop(interval);
+        }
+    });
+}
@@ -0,0 +1,343 @@
+use chrono::{DateTime, Duration, Utc};
+use data_encoding::BASE32_NOPAD;
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha1::Sha1;
+use subtle::ConstantTimeEq;
+
+use super::password::{hash_token, random_token};
+
+/// Digits in a code.
+pub const CODE_DIGITS: u32 = 6;
+/// Seconds each code is valid for.
+pub const STEP_SECONDS: i64 = 30;
+/// Codes from this many steps before or after now are accepted too, for clocks that are a bit off.
+const ALLOWED_SKEW_STEPS: i64 = 1;
+/// Bytes of randomness in a secret. 160 bits, as RFC 4226 recommends.
+const SECRET_LENGTH: usize = 20;
+/// How many recovery codes a user gets.
+const RECOVERY_CODE_COUNT: usize = 10;
+/// Minutes a login has to be finished with a code once the password was right.
+const LOGIN_CHALLENGE_MINUTES: i64 = 5;
+/// Wrong codes a login can take before it has to start over with the password.
+const LOGIN_CHALLENGE_ATTEMPTS: u32 = 5;
+/// The name authenticator apps show next to the account.
+pub const ISSUER: &str = "Rust Blog";
+
+/// What a user needs to add their account to an authenticator app.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TotpSetup {
+    /// The secret in base32, for typing in by hand.
+    pub secret: String,
+    /// An `otpauth://` URI with the secret, which authenticator apps can open.
+    pub otpauth_uri: String,
+}
+
+impl TotpSetup {
+    /// A fresh random secret for `account`.
+    pub fn generate(account: &str) -> Self {
+        let mut secret = [0u8; SECRET_LENGTH];
+        getrandom::getrandom(&mut secret).expect("Couldn't generate a TOTP secret");
+        let secret = BASE32_NOPAD.encode(&secret);
+        Self {
+            otpauth_uri: otpauth_uri(&secret, account),
+            secret,
+        }
+    }
+}
+
+/// The `otpauth://` URI for `secret`, labelled with `ISSUER` and `account`.
+pub fn otpauth_uri(secret: &str, account: &str) -> String {
+    format!(
+        "otpauth://totp/{}:{}?secret={}&issuer={}&algorithm=SHA1&digits={}&period={}",
+        urlencoding::encode(ISSUER),
+        urlencoding::encode(account),
+        secret,
+        urlencoding::encode(ISSUER),
+        CODE_DIGITS,
+        STEP_SECONDS
+    )
+}
+
+/// The time step `time` falls in.
+fn step_at(time: DateTime<Utc>) -> i64 {
+    time.timestamp().div_euclid(STEP_SECONDS)
+}
+
+/// The code for `step` (RFC 4226 with a time-based counter), or `None` if `secret` isn't valid base32.
+fn code_for_step(secret: &str, step: i64) -> Option<String> {
+    let key = BASE32_NOPAD
+        .decode(secret.trim_end_matches('=').as_bytes())
+        .ok()?;
+    let mut mac = Hmac::<Sha1>::new_from_slice(&key).ok()?;
+    mac.update(&(step as u64).to_be_bytes());
+    let hash = mac.finalize().into_bytes();
+
+    // Dynamic truncation: the last nibble picks the four bytes to use.
+    let offset = (hash[hash.len() - 1] & 0x0f) as usize;
+    let value = u32::from_be_bytes([
+        hash[offset] & 0x7f,
+        hash[offset + 1],
+        hash[offset + 2],
+        hash[offset + 3],
+    ]);
+    Some(format!(
+        "{:0width$}",
+        value % 10u32.pow(CODE_DIGITS),
+        width = CODE_DIGITS as usize
+    ))
+}
+
+/// The time step `code` belongs to, if it is valid for `secret` around `time`.
+pub fn verify_code(secret: &str, code: &str, time: DateTime<Utc>) -> Option<i64> {
+    let code = code.trim().replace(' ', "");
+    let now = step_at(time);
+    (now - ALLOWED_SKEW_STEPS..=now + ALLOWED_SKEW_STEPS).find(|step| {
+        code_for_step(secret, *step)
+            .is_some_and(|expected| bool::from(expected.as_bytes().ct_eq(code.as_bytes())))
+    })
+}
+
+// Recovery codes are compared without dashes, spaces or case.
+fn normalize_recovery_code(code: &str) -> String {
+    code.chars()
+        .filter(char::is_ascii_alphanumeric)
+        .collect::<String>()
+        .to_lowercase()
+}
+
+/// Fresh recovery codes like `abcde-fghij`, each of which can stand in for a code once.
+pub fn generate_recovery_codes() -> Vec<String> {
+    (0..RECOVERY_CODE_COUNT)
+        .map(|_| {
+            let mut bytes = [0u8; 7];
+            getrandom::getrandom(&mut bytes).expect("Couldn't generate a recovery code");
+            let code = BASE32_NOPAD.encode(&bytes).to_lowercase();
+            format!("{}-{}", &code[..5], &code[5..10])
+        })
+        .collect()
+}
+
+/// The two-factor state of an account checked in the browser, as the local provider keeps it.
+/// Recovery codes are only stored hashed.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TwoFactor {
+    /// The secret codes are checked against, once setup is confirmed.
+    secret: Option<String>,
+    /// A secret handed out for setup that hasn't been confirmed with a code yet.
+    pending_secret: Option<String>,
+    recovery_code_hashes: Vec<String>,
+    /// The step of the last code that was accepted, so a code can't be used twice.
+    last_step: Option<i64>,
+    /// The login waiting for a code after the right password.
+    #[serde(default)]
+    login_challenge: Option<LoginChallenge>,
+}
+
+/// A login that got the password right, as `TwoFactor::begin_login` hands it out.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct LoginChallenge {
+    /// Only the hash is stored, so whoever reads the storage can't finish the login.
+    hash: String,
+    expires_at: DateTime<Utc>,
+    attempts_left: u32,
+}
+
+impl TwoFactor {
+    pub fn is_enabled(&self) -> bool {
+        self.secret.is_some()
+    }
+
+    /// Start setting up a new secret for `account`. The current one keeps working until it is confirmed.
+    pub fn begin_setup(&mut self, account: &str) -> TotpSetup {
+        let setup = TotpSetup::generate(account);
+        self.pending_secret = Some(setup.secret.clone());
+        setup
+    }
+
+    /// Switch to the pending secret if `code` matches it, and hand out new recovery codes.
+    pub fn confirm_setup(&mut self, code: &str) -> Option<Vec<String>> {
+        let pending = self.pending_secret.as_deref()?;
+        let step = verify_code(pending, code, Utc::now())?;
+
+        let recovery_codes = generate_recovery_codes();
+        self.secret = self.pending_secret.take();
+        self.recovery_code_hashes = recovery_codes
+            .iter()
+            .map(|code| hash_token(&normalize_recovery_code(code)))
+            .collect();
+        self.last_step = Some(step);
+        Some(recovery_codes)
+    }
+
+    /// Whether `code` is a current code or an unused recovery code. Both only work once.
+    pub fn verify(&mut self, code: &str) -> bool {
+        let Some(secret) = &self.secret else {
+            return false;
+        };
+        if let Some(step) = verify_code(secret, code, Utc::now()) {
+            if self.last_step.is_some_and(|last| step <= last) {
+                return false;
+            }
+            self.last_step = Some(step);
+            return true;
+        }
+
+        let hash = hash_token(&normalize_recovery_code(code));
+        let count = self.recovery_code_hashes.len();
+        self.recovery_code_hashes.retain(|stored| *stored != hash);
+        self.recovery_code_hashes.len() < count
+    }
+
+    /// Start a login whose password was right. The challenge has to come back with a code from
+    /// `finish_login`, within a few minutes, and replaces any earlier one.
+    pub fn begin_login(&mut self, now: DateTime<Utc>) -> String {
+        let challenge = random_token();
+        self.login_challenge = Some(LoginChallenge {
+            hash: hash_token(&challenge),
+            expires_at: now + Duration::minutes(LOGIN_CHALLENGE_MINUTES),
+            attempts_left: LOGIN_CHALLENGE_ATTEMPTS,
+        });
+        challenge
+    }
+
+    /// Whether `challenge` belongs to a login of this account that can still be finished.
+    pub fn has_login_challenge(&self, challenge: &str, now: DateTime<Utc>) -> bool {
+        self.login_challenge.as_ref().is_some_and(|pending| {
+            pending.expires_at > now
+                && bool::from(
+                    pending
+                        .hash
+                        .as_bytes()
+                        .ct_eq(hash_token(challenge).as_bytes()),
+                )
+        })
+    }
+
+    /// Finish the login `challenge` started if `code` is right (see `verify`). The challenge works
+    /// once, and too many wrong codes use it up.
+    pub fn finish_login(&mut self, challenge: &str, code: &str, now: DateTime<Utc>) -> bool {
+        if !self.has_login_challenge(challenge, now) {
+            return false;
+        }
+        if self.verify(code) {
+            self.login_challenge = None;
+            return true;
+        }
+        if let Some(pending) = &mut self.login_challenge {
+            pending.attempts_left -= 1;
+            if pending.attempts_left == 0 {
+                self.login_challenge = None;
+            }
+        }
+        false
+    }
+
+    /// Turn two-factor authentication off again.
+    pub fn disable(&mut self) {
+        *self = Self::default();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    // The SHA-1 seed from RFC 6238 Appendix B, "12345678901234567890", in base32.
+    const RFC_SECRET: &str = "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ";
+
+    fn at(seconds: i64) -> DateTime<Utc> {
+        Utc.timestamp_opt(seconds, 0).unwrap()
+    }
+
+    // Two-factor authentication that is on with `RFC_SECRET` and `recovery_code`.
+    fn enabled(recovery_code: &str) -> TwoFactor {
+        TwoFactor {
+            secret: Some(RFC_SECRET.to_string()),
+            recovery_code_hashes: vec![hash_token(&normalize_recovery_code(recovery_code))],
+            ..TwoFactor::default()
+        }
+    }
+
+    fn current_code() -> String {
+        code_for_step(RFC_SECRET, step_at(Utc::now())).unwrap()
+    }
+
+    #[test]
+    fn codes_match_rfc_6238() {
+        // The appendix lists 8-digit codes; ours are their last six digits.
+        for (time, code) in [
+            (59, "287082"),
+            (1_111_111_109, "081804"),
+            (1_111_111_111, "050471"),
+            (1_234_567_890, "005924"),
+            (2_000_000_000, "279037"),
+            (20_000_000_000, "353130"),
+        ] {
+            assert_eq!(
+                code_for_step(RFC_SECRET, step_at(at(time))).as_deref(),
+                Some(code)
+            );
+        }
+    }
+
+    #[test]
+    fn verify_code_allows_one_step_of_skew() {
+        let time = at(1_111_111_111);
+        assert_eq!(
+            verify_code(RFC_SECRET, "050 471", time),
+            Some(step_at(time))
+        );
+        assert!(verify_code(RFC_SECRET, "050471", at(1_111_111_111 + STEP_SECONDS)).is_some());
+        assert!(verify_code(RFC_SECRET, "050471", at(1_111_111_111 + 3 * STEP_SECONDS)).is_none());
+        assert!(verify_code("not base32!", "050471", time).is_none());
+    }
+
+    #[test]
+    fn a_code_works_only_once() {
+        let mut two_factor = enabled("abcde-fghij");
+        let code = current_code();
+        assert!(two_factor.verify(&code));
+        assert!(!two_factor.verify(&code));
+    }
+
+    #[test]
+    fn a_recovery_code_works_only_once() {
+        let mut two_factor = enabled("abcde-fghij");
+        // Dashes, spaces and case don't matter.
+        assert!(two_factor.verify("ABCDE FGHIJ"));
+        assert!(!two_factor.verify("abcde-fghij"));
+    }
+
+    #[test]
+    fn a_login_challenge_works_once() {
+        let mut two_factor = enabled("abcde-fghij");
+        let now = Utc::now();
+        let challenge = two_factor.begin_login(now);
+        assert!(!two_factor.has_login_challenge("someone-else", now));
+        assert!(two_factor.finish_login(&challenge, &current_code(), now));
+        assert!(!two_factor.finish_login(&challenge, "abcde-fghij", now));
+    }
+
+    #[test]
+    fn a_login_challenge_expires() {
+        let mut two_factor = enabled("abcde-fghij");
+        let now = Utc::now();
+        let challenge = two_factor.begin_login(now);
+        let later = now + Duration::minutes(LOGIN_CHALLENGE_MINUTES);
+        assert!(!two_factor.finish_login(&challenge, "abcde-fghij", later));
+    }
+
+    #[test]
+    fn wrong_codes_use_up_a_login_challenge() {
+        let mut two_factor = enabled("abcde-fghij");
+        let now = Utc::now();
+        let challenge = two_factor.begin_login(now);
+        for _ in 0..LOGIN_CHALLENGE_ATTEMPTS {
+            assert!(!two_factor.finish_login(&challenge, "wrong", now));
+        }
+        assert!(!two_factor.has_login_challenge(&challenge, now));
+    }
+}
@@ -1,35 +1,140 @@
 use chrono::NaiveDate;
 
-use gloo_console::log; // For logging
-use gloo_storage::{LocalStorage, Storage};
+use gloo_console::{error, log}; // For logging
 use serde::{Deserialize, Serialize};
-use sha2::{Digest, Sha256};
 use std::marker::PhantomData;
 
-/// Enum representing the state of a user.
-#[derive(Clone, Serialize, Deserialize)]
+use super::{
+    auth::{AuthError, AuthProvider, AuthSession, LoginOutcome},
+    login_throttle::{LoginPolicy, LoginThrottle},
+    oidc::{ExternalIdentity, OidcLogin},
+    password::{
+        check_no_password, check_password, hash_password, hash_token, random_token, PasswordCheck,
+    },
+    roles::Role,
+    session::Session,
+    totp::{TotpSetup, TwoFactor},
+    validation::{
+        validate_birthday, validate_email, validate_name, validate_password, validate_username,
+        UserField, ValidationErrors,
+    },
+};
+
+/// How many sessions a local account can have at once. Older ones end when a new one starts.
+pub const MAX_SESSIONS: usize = 10;
+
+/// Enum representing the state of a user. Only ever kept in memory; see `Session` for what is stored.
+#[derive(Clone, PartialEq)]
 pub enum UserState {
     Unauthorized(UserManager<Unauthorized>),
+    /// The password was right, but the account also needs a two-factor code.
+    PendingSecondFactor(UserManager<PendingSecondFactor>),
     Authorized(UserManager<Authorized>),
 }
 
 /// Struct representing an authorized user.
-#[derive(Clone)]
+#[derive(Clone, PartialEq)]
 pub struct Authorized;
 
 /// Struct representing an unauthorized user.
-#[derive(Clone)]
+#[derive(Clone, PartialEq)]
 pub struct Unauthorized;
 
+/// Struct representing a user who got the password right and still has to enter a two-factor code.
+/// Their token is only the provider's challenge, which is good for nothing else.
+#[derive(Clone, PartialEq)]
+pub struct PendingSecondFactor;
+
+/// The public details of an account, as handed out by an `AuthProvider`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UserProfile {
+    pub username: String,
+    pub email: String,
+    pub name: String,
+    pub birthday: NaiveDate,
+    /// Decided by the provider. Accounts from before roles existed are readers.
+    #[serde(default)]
+    pub role: Role,
+}
+
+/// Why creating or editing an account failed.
+#[derive(Debug, Clone, PartialEq)]
+pub enum RegistrationError {
+    /// Some of the fields were invalid. Nothing was sent to the provider.
+    Invalid(ValidationErrors),
+    /// The provider refused the account or couldn't be reached.
+    Auth(AuthError),
+}
+
+/// Check the fields every account needs, on top of the `errors` already found,
+/// and build the profile from them.
+/// `birthday` is `None` when the user didn't enter a valid date.
+pub fn validate_profile(
+    username: &str,
+    email: &str,
+    name: &str,
+    birthday: Option<NaiveDate>,
+    role: Role,
+    mut errors: ValidationErrors,
+) -> Result<UserProfile, ValidationErrors> {
+    errors.check(UserField::Username, validate_username(username));
+    errors.check(UserField::Email, validate_email(email));
+    errors.check(UserField::Name, validate_name(name));
+    errors.check(
+        UserField::Birthday,
+        birthday
+            .ok_or_else(|| "Please enter your birthday.".to_string())
+            .and_then(validate_birthday),
+    );
+    match birthday {
+        Some(birthday) if errors.is_empty() => Ok(UserProfile {
+            username: username.trim().to_string(),
+            email: email.trim().to_string(),
+            name: name.trim().to_string(),
+            birthday,
+            role,
+        }),
+        _ => Err(errors),
+    }
+}
+
+// Turn the result of validating a single field into the error the forms understand.
+fn check_field<T>(field: UserField, result: Result<T, String>) -> Result<T, RegistrationError> {
+    result.map_err(|message| {
+        let mut errors = ValidationErrors::default();
+        errors.check(field, Err(message));
+        RegistrationError::Invalid(errors)
+    })
+}
+
 /// Generic struct for managing user data, parameterized by state.
-#[derive(Clone, Serialize, Deserialize)]
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
 pub struct UserManager<State = Unauthorized> {
     state: PhantomData<State>,
     username: String,
     email: String,
-    password_hash: String,
+    /// Only known for accounts whose password is checked in the browser.
+    password_hash: Option<String>,
     name: String,
     birthday: NaiveDate,
+    #[serde(default)]
+    role: Role,
+    /// Only tracked for the local provider's accounts.
+    #[serde(default)]
+    disabled: bool,
+    /// Only tracked for the local provider's accounts.
+    #[serde(default)]
+    two_factor: TwoFactor,
+    /// Hashes of the tokens the local provider handed out for the account, oldest first.
+    /// The tokens themselves are never stored.
+    #[serde(default)]
+    sessions: Vec<String>,
+    /// The identity provider account it was made from, for accounts made through single sign-on.
+    #[serde(default)]
+    identity: Option<ExternalIdentity>,
+    /// The token the auth provider gave us on login. Never written to storage.
+    #[serde(skip)]
+    token: Option<String>,
 }
 
 impl UserManager<Authorized> {
@@ -38,10 +143,158 @@ impl UserManager<Authorized> {
         &self.name
     }
 
+    /// Get the user's username. It can't be changed once the account exists.
+    pub fn get_username(&self) -> &str {
+        &self.username
+    }
+
+    /// Get the user's birthday.
+    pub fn get_birthday(&self) -> NaiveDate {
+        self.birthday
+    }
+
+    /// Validate the user's new name, email and birthday together and save them in one go.
+    /// `birthday` is `None` when the user didn't enter a valid date.
+    pub async fn update_profile(
+        &mut self,
+        provider: &dyn AuthProvider,
+        name: &str,
+        email: &str,
+        birthday: Option<NaiveDate>,
+    ) -> Result<(), RegistrationError> {
+        let profile = validate_profile(
+            &self.username,
+            email,
+            name,
+            birthday,
+            self.role,
+            ValidationErrors::default(),
+        )
+        .map_err(RegistrationError::Invalid)?;
+        if profile == self.profile() {
+            return Ok(());
+        }
+        self.save_profile(provider, &profile).await
+    }
+
+    /// Validate `name` and make it the user's name.
+    #[allow(dead_code)] // The profile form saves every field at once with `update_profile`.
+    pub async fn update_name(
+        &mut self,
+        provider: &dyn AuthProvider,
+        name: &str,
+    ) -> Result<(), RegistrationError> {
+        let email = self.email.clone();
+        self.update_profile(provider, name, &email, Some(self.birthday))
+            .await
+    }
+
+    /// Validate `email` and make it the address the user logs in with.
+    #[allow(dead_code)] // The profile form saves every field at once with `update_profile`.
+    pub async fn update_email(
+        &mut self,
+        provider: &dyn AuthProvider,
+        email: &str,
+    ) -> Result<(), RegistrationError> {
+        let name = self.name.clone();
+        self.update_profile(provider, &name, email, Some(self.birthday))
+            .await
+    }
+
+    /// Validate `birthday` and make it the user's birthday.
+    /// `birthday` is `None` when the user didn't enter a valid date.
+    #[allow(dead_code)] // The profile form saves every field at once with `update_profile`.
+    pub async fn update_birthday(
+        &mut self,
+        provider: &dyn AuthProvider,
+        birthday: Option<NaiveDate>,
+    ) -> Result<(), RegistrationError> {
+        let (name, email) = (self.name.clone(), self.email.clone());
+        self.update_profile(provider, &name, &email, birthday).await
+    }
+
+    /// Replace the user's password with `new_password`, if `current_password` is right.
+    /// The provider ends the account's other sessions, and this one carries on with a new token.
+    pub async fn change_password(
+        &mut self,
+        provider: &dyn AuthProvider,
+        current_password: &str,
+        new_password: &str,
+    ) -> Result<(), RegistrationError> {
+        check_field(UserField::Password, validate_password(new_password))?;
+        let token = self
+            .token
+            .as_deref()
+            .ok_or(RegistrationError::Auth(AuthError::NotLoggedIn))?;
+        match provider
+            .change_password(token, current_password, new_password)
+            .await
+        {
+            Ok(auth_session) => {
+                log!("Changed the password.");
+                self.take_session(auth_session);
+                Ok(())
+            }
+            Err(AuthError::InvalidCredentials) => check_field(
+                UserField::CurrentPassword,
+                Err("Your current password is wrong.".to_string()),
+            ),
+            Err(err) => Err(RegistrationError::Auth(err)),
+        }
+    }
+
+    /// Have the provider save `profile` and take over what it answers with.
+    async fn save_profile(
+        &mut self,
+        provider: &dyn AuthProvider,
+        profile: &UserProfile,
+    ) -> Result<(), RegistrationError> {
+        let token = self
+            .token
+            .as_deref()
+            .ok_or(RegistrationError::Auth(AuthError::NotLoggedIn))?;
+        let auth_session = provider
+            .update_profile(token, profile)
+            .await
+            .map_err(RegistrationError::Auth)?;
+        log!("Updated the profile.");
+        self.take_session(auth_session);
+        Ok(())
+    }
+
+    /// Carry on with the session the provider answered a change with, including a new token
+    /// if it hands one out. The stored session is updated to match.
+    fn take_session(&mut self, auth_session: AuthSession) {
+        if let Some(session) = Session::load() {
+            session.renew(&auth_session).save();
+        }
+        *self = Self::from_session(auth_session);
+    }
+
+    /// What the user is allowed to do.
+    pub fn role(&self) -> Role {
+        self.role
+    }
+
+    /// Check that the user has at least the `required` role, e.g. before showing an admin page.
+    pub fn require_role(&self, required: Role) -> Result<&Self, AuthError> {
+        if self.role.includes(required) {
+            Ok(self)
+        } else {
+            Err(AuthError::Forbidden(required))
+        }
+    }
+
     /// Logout the user, transitioning to Unauthorized state.
-    pub fn logout(self) -> UserState {
-        LocalStorage::set::<bool>("login", false).expect("Couldn't save login toggle!");
-        LocalStorage::delete("login_state");
+    /// The local login state is cleared even if the provider couldn't be reached.
+    pub async fn logout(self, provider: &dyn AuthProvider) -> UserState {
+        if let Some(token) = &self.token {
+            if let Err(err) = provider.logout(token).await {
+                error!("Logout failed at the auth provider:", err.to_string());
+            }
+        }
+
+        Session::clear();
 
         UserState::Unauthorized(UserManager {
             state: PhantomData,
@@ -50,62 +303,394 @@ impl UserManager<Authorized> {
             password_hash: self.password_hash,
             name: self.name,
             birthday: self.birthday,
+            role: self.role,
+            disabled: self.disabled,
+            two_factor: self.two_factor,
+            sessions: self.sessions,
+            identity: self.identity,
+            token: None,
         })
     }
+
+    /// Pick up the stored session after a reload: ask the provider for a fresh token
+    /// and the account details, which are only kept in memory.
+    pub async fn resume(provider: &dyn AuthProvider) -> Result<UserState, AuthError> {
+        let session = Session::load().ok_or(AuthError::NotLoggedIn)?;
+        let auth_session = provider.refresh(&session.token).await?;
+        session.renew(&auth_session).save();
+
+        Ok(UserState::Authorized(Self::from_session(auth_session)))
+    }
+
+    /// Build an authorized user from what the provider returned.
+    fn from_session(session: AuthSession) -> Self {
+        Self::from_profile(session.user, Some(session.token))
+    }
+
+    /// Whether the user has two-factor authentication on.
+    pub async fn two_factor_enabled(&self, provider: &dyn AuthProvider) -> Result<bool, AuthError> {
+        provider.two_factor_enabled(self.token()?).await
+    }
+
+    /// Make a new TOTP secret to add to an authenticator app. It only takes effect once a code
+    /// for it is passed to `confirm_two_factor`.
+    pub async fn begin_two_factor_setup(
+        &self,
+        provider: &dyn AuthProvider,
+    ) -> Result<TotpSetup, AuthError> {
+        provider.begin_two_factor_setup(self.token()?).await
+    }
+
+    /// Turn on two-factor authentication if `code` matches the new secret.
+    /// Answers with the recovery codes, which can't be shown again.
+    pub async fn confirm_two_factor(
+        &self,
+        provider: &dyn AuthProvider,
+        code: &str,
+    ) -> Result<Vec<String>, AuthError> {
+        let recovery_codes = provider.confirm_two_factor(self.token()?, code).await?;
+        log!("Turned on two-factor authentication.");
+        Ok(recovery_codes)
+    }
+
+    /// Turn two-factor authentication off, if `code` is a current code or a recovery code.
+    pub async fn disable_two_factor(
+        &self,
+        provider: &dyn AuthProvider,
+        code: &str,
+    ) -> Result<(), AuthError> {
+        provider.disable_two_factor(self.token()?, code).await?;
+        log!("Turned off two-factor authentication.");
+        Ok(())
+    }
+
+    fn token(&self) -> Result<&str, AuthError> {
+        self.token.as_deref().ok_or(AuthError::NotLoggedIn)
+    }
+}
+
+impl UserManager<PendingSecondFactor> {
+    /// Finish logging in with a code from the user's authenticator app or a recovery code.
+    /// Wrong codes count as failed logins. With `remember`, the session outlives the browser tab.
+    pub async fn verify_second_factor(
+        &self,
+        provider: &dyn AuthProvider,
+        code: &str,
+        remember: bool,
+    ) -> Result<UserState, AuthError> {
+        let policy = LoginPolicy::default();
+        let mut throttle = LoginThrottle::load();
+        if let Some(wait) = throttle.wait_time(&self.email, &policy) {
+            return Err(AuthError::TooManyAttempts(wait));
+        }
+
+        let challenge = self.token.as_deref().ok_or(AuthError::NotLoggedIn)?;
+        match provider.verify_second_factor(challenge, code.trim()).await {
+            Err(err) => {
+                log!("Second factor failed:", err.to_string());
+                if err == AuthError::InvalidCode {
+                    throttle.record_failure(&self.email, &policy);
+                }
+                Err(err)
+            }
+            Ok(session) => {
+                log!("Login succeeded.");
+                throttle.record_success(&self.email);
+                Session::from_auth(&session, remember).save();
+
+                Ok(UserState::Authorized(UserManager::from_session(session)))
+            }
+        }
+    }
 }
 
 impl UserManager<Unauthorized> {
-    /// Login the user with email and password.
-    pub fn login(self, email: &str, password: &str) -> Result<UserState, String> {
-        if email != self.email || UserManager::hash_password(password) != self.password_hash {
-            log!("Login failed: Invalid email or password.");
-            Err("Login failed: Invalid email or password.".to_string())
-        } else {
-            log!("Login succeeded.");
-
-            let new_state = UserState::Authorized(UserManager {
-                state: PhantomData,
-                username: self.username,
-                email: self.email,
-                password_hash: self.password_hash,
-                name: self.name,
-                birthday: self.birthday,
-            });
-
-            LocalStorage::set::<bool>("login", true).expect("Couldn't save login toggle!");
-            LocalStorage::set::<UserState>("login_state", new_state.clone())
-                .expect("Couldn't save login state!");
-
-            Ok(new_state)
+    /// Validate the details of a new account and create it through `provider`.
+    /// `birthday` is `None` when the user didn't enter a valid date.
+    pub async fn register(
+        provider: &dyn AuthProvider,
+        username: &str,
+        email: &str,
+        password: &str,
+        name: &str,
+        birthday: Option<NaiveDate>,
+    ) -> Result<(), RegistrationError> {
+        let mut errors = ValidationErrors::default();
+        errors.check(UserField::Password, validate_password(password));
+        // New accounts are readers; only the provider can hand out more.
+        let profile = validate_profile(username, email, name, birthday, Role::default(), errors)
+            .map_err(RegistrationError::Invalid)?;
+
+        provider
+            .register(&profile, password)
+            .await
+            .map_err(RegistrationError::Auth)?;
+
+        log!("Registered a new account.");
+        Ok(())
+    }
+
+    /// Ask `provider` to send a password reset link to `email`.
+    pub async fn request_password_reset(
+        provider: &dyn AuthProvider,
+        email: &str,
+    ) -> Result<(), RegistrationError> {
+        check_field(UserField::Email, validate_email(email))?;
+        provider
+            .request_password_reset(email.trim())
+            .await
+            .map_err(RegistrationError::Auth)?;
+
+        log!("Requested a password reset.");
+        Ok(())
+    }
+
+    /// Set a new `password` with the token from a password reset link.
+    pub async fn reset_password(
+        provider: &dyn AuthProvider,
+        reset_token: &str,
+        password: &str,
+    ) -> Result<(), RegistrationError> {
+        check_field(UserField::Password, validate_password(password))?;
+        provider
+            .reset_password(reset_token, password)
+            .await
+            .map_err(RegistrationError::Auth)?;
+
+        log!("Reset the password.");
+        Ok(())
+    }
+
+    /// Login with email and password, letting `provider` check the credentials.
+    /// With `remember`, the session outlives the browser tab.
+    /// After a wrong password the email has to wait before trying again, longer with every failure,
+    /// and is locked out for a while once `LoginPolicy::max_failures` is reached.
+    pub async fn login(
+        provider: &dyn AuthProvider,
+        email: &str,
+        password: &str,
+        remember: bool,
+    ) -> Result<UserState, AuthError> {
+        let policy = LoginPolicy::default();
+        let mut throttle = LoginThrottle::load();
+        if let Some(wait) = throttle.wait_time(email, &policy) {
+            log!("Login refused, the email has to wait.");
+            return Err(AuthError::TooManyAttempts(wait));
+        }
+
+        match provider.login(email, password).await {
+            Err(err) => {
+                log!("Login failed:", err.to_string());
+                if err == AuthError::InvalidCredentials {
+                    throttle.record_failure(email, &policy);
+                }
+                Err(err)
+            }
+            // Failures are only forgotten once the second factor is right too, so logging in again
+            // doesn't give more tries at the code.
+            Ok(LoginOutcome::SecondFactor { challenge, user }) => {
+                log!("Password accepted, asking for a second factor.");
+                Ok(UserState::PendingSecondFactor(UserManager::from_profile(
+                    user,
+                    Some(challenge),
+                )))
+            }
+            Ok(LoginOutcome::Session(session)) => {
+                log!("Login succeeded.");
+                throttle.record_success(email);
+
+                // Only the token, name and expiry are stored; the rest stays in memory.
+                Session::from_auth(&session, remember).save();
+
+                Ok(UserState::Authorized(UserManager::from_session(session)))
+            }
         }
     }
+
+    /// Log in as someone an identity provider vouched for, letting `provider` find or make their
+    /// account. Two-factor authentication is left to the identity provider.
+    pub async fn login_with_oidc(
+        provider: &dyn AuthProvider,
+        login: &OidcLogin,
+    ) -> Result<UserState, AuthError> {
+        let session = provider
+            .login_with_id_token(&login.id_token, &login.claims)
+            .await
+            .inspect_err(|err| log!("Single sign-on failed:", err.to_string()))?;
+
+        log!("Logged in through the identity provider.");
+        Session::from_auth(&session, login.remember).save();
+        Ok(UserState::Authorized(UserManager::from_session(session)))
+    }
 }
 
 impl UserManager {
-    /// Create a new UserManager instance, initially in Unauthorized state.
+    /// Create a new UserManager instance, initially in Unauthorized state, as a reader.
     pub fn new(
         username: String,
         email: String,
         password: String,
         name: String,
         birthday: NaiveDate,
-    ) -> UserState {
-        let password_hash = Self::hash_password(&password);
+    ) -> Self {
+        let password_hash = hash_password(&password);
         log!("Created new UserManager in Unauthorized state.");
-        UserState::Unauthorized(UserManager {
+        UserManager {
             state: PhantomData,
             username,
             email,
-            password_hash,
+            password_hash: Some(password_hash),
             name,
             birthday,
-        })
+            role: Role::default(),
+            disabled: false,
+            two_factor: TwoFactor::default(),
+            sessions: Vec::new(),
+            identity: None,
+            token: None,
+        }
+    }
+
+    /// An account for `profile` without a password, for people who log in through an identity provider.
+    pub fn without_password(profile: UserProfile) -> Self {
+        Self::from_profile(profile, None)
     }
 
-    /// Hash a password using SHA256.
-    fn hash_password(password: &str) -> String {
-        let mut hasher = Sha256::new();
-        hasher.update(password);
-        format!("{:x}", hasher.finalize())
+    /// The same account with `role` instead, e.g. for accounts an admin creates.
+    pub fn with_role(self, role: Role) -> Self {
+        UserManager { role, ..self }
+    }
+
+    /// Whether the account can log in with a password, i.e. wasn't made through single sign-on.
+    pub fn has_password(&self) -> bool {
+        self.password_hash.is_some()
+    }
+
+    /// Check `password` against the stored hash. Always invalid if we don't know the hash,
+    /// though that takes just as long.
+    pub fn check_password(&self, password: &str) -> PasswordCheck {
+        match &self.password_hash {
+            Some(password_hash) => check_password(password, password_hash),
+            None => {
+                check_no_password(password);
+                PasswordCheck::Invalid
+            }
+        }
+    }
+
+    /// Replace the stored hash with a fresh one for `password`.
+    pub fn set_password(&mut self, password: &str) {
+        self.password_hash = Some(hash_password(password));
+    }
+
+    /// Start a session and hand out its token. Only the newest `MAX_SESSIONS` sessions are kept.
+    pub fn start_session(&mut self) -> String {
+        let token = random_token();
+        self.sessions.push(hash_token(&token));
+        let ended = self.sessions.len().saturating_sub(MAX_SESSIONS);
+        self.sessions.drain(..ended);
+        token
+    }
+
+    /// Whether `token` belongs to a session of the account that hasn't ended.
+    pub fn has_session(&self, token: &str) -> bool {
+        self.sessions.contains(&hash_token(token))
+    }
+
+    /// End the session `token` belongs to.
+    pub fn end_session(&mut self, token: &str) {
+        let hash = hash_token(token);
+        self.sessions.retain(|session| *session != hash);
+    }
+
+    /// End every session, so no token handed out for the account works any more.
+    pub fn end_sessions(&mut self) {
+        self.sessions.clear();
+    }
+
+    /// The identity provider account the account was made from, if any.
+    pub fn identity(&self) -> Option<&ExternalIdentity> {
+        self.identity.as_ref()
+    }
+
+    /// Link the account to an identity provider account, which can then log in to it.
+    pub fn set_identity(&mut self, identity: ExternalIdentity) {
+        self.identity = Some(identity);
+    }
+
+    /// Replace the account details, role included. The password stays the same.
+    pub fn set_profile(&mut self, profile: UserProfile) {
+        let UserProfile {
+            username,
+            email,
+            name,
+            birthday,
+            role,
+        } = profile;
+        self.username = username;
+        self.email = email;
+        self.name = name;
+        self.birthday = birthday;
+        self.role = role;
+    }
+
+    pub fn is_disabled(&self) -> bool {
+        self.disabled
+    }
+
+    pub fn set_disabled(&mut self, disabled: bool) {
+        self.disabled = disabled;
+    }
+
+    pub fn two_factor(&self) -> &TwoFactor {
+        &self.two_factor
+    }
+
+    pub fn two_factor_mut(&mut self) -> &mut TwoFactor {
+        &mut self.two_factor
+    }
+}
+
+impl<State> UserManager<State> {
+    /// Get the user's email.
+    pub fn get_email(&self) -> &str {
+        &self.email
+    }
+
+    /// A user in this state with the details from `profile`, e.g. as handed out by a provider.
+    fn from_profile(profile: UserProfile, token: Option<String>) -> Self {
+        let UserProfile {
+            username,
+            email,
+            name,
+            birthday,
+            role,
+        } = profile;
+
+        UserManager {
+            state: PhantomData,
+            username,
+            email,
+            password_hash: None,
+            name,
+            birthday,
+            role,
+            disabled: false,
+            two_factor: TwoFactor::default(),
+            sessions: Vec::new(),
+            identity: None,
+            token,
+        }
+    }
+
+    /// The account details that are safe to hand out.
+    pub fn profile(&self) -> UserProfile {
+        UserProfile {
+            username: self.username.clone(),
+            email: self.email.clone(),
+            name: self.name.clone(),
+            birthday: self.birthday,
+            role: self.role,
+        }
     }
 }
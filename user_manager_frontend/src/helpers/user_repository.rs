@@ -0,0 +1,143 @@
+use std::rc::Rc;
+
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+
+use super::{
+    auth::{AuthFuture, HttpAuthProvider, LocalAuthProvider},
+    roles::Role,
+    user_manager::{validate_profile, RegistrationError, UserProfile},
+    validation::{validate_password, UserField, ValidationErrors},
+};
+
+/// An account as an admin sees it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ManagedUser {
+    #[serde(flatten)]
+    pub profile: UserProfile,
+    /// Disabled accounts can't log in, and their sessions stop being accepted.
+    #[serde(default)]
+    pub disabled: bool,
+}
+
+/// One page of users, in the order the repository keeps them.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UserPage {
+    pub users: Vec<ManagedUser>,
+    /// How many users there are across all pages.
+    pub total: usize,
+}
+
+impl UserPage {
+    /// How many pages of `per_page` users there are. Always at least one.
+    pub fn page_count(&self, per_page: usize) -> usize {
+        self.total.div_ceil(per_page).max(1)
+    }
+}
+
+/// Where accounts are managed. Users are looked up by their username.
+///
+/// Every call is made on behalf of the logged-in user whose `token` is passed, and fails with
+/// `AuthError::Forbidden` unless they are an admin.
+/// Admins can't demote, disable or delete their own account or the last enabled admin; that fails
+/// with `AuthError::AdminLockout`.
+pub trait UserRepository {
+    /// Page `page` (counting from 0) of `per_page` users.
+    fn list_users<'a>(
+        &'a self,
+        token: &'a str,
+        page: usize,
+        per_page: usize,
+    ) -> AuthFuture<'a, UserPage>;
+
+    fn get_user<'a>(&'a self, token: &'a str, username: &'a str) -> AuthFuture<'a, ManagedUser>;
+
+    /// Create an account that can log in with `profile.email` and `password`.
+    fn create_user<'a>(
+        &'a self,
+        token: &'a str,
+        profile: &'a UserProfile,
+        password: &'a str,
+    ) -> AuthFuture<'a, ManagedUser>;
+
+    /// Replace the details of the account currently called `username`.
+    fn update_user<'a>(
+        &'a self,
+        token: &'a str,
+        username: &'a str,
+        profile: &'a UserProfile,
+    ) -> AuthFuture<'a, ManagedUser>;
+
+    fn set_disabled<'a>(
+        &'a self,
+        token: &'a str,
+        username: &'a str,
+        disabled: bool,
+    ) -> AuthFuture<'a, ()>;
+
+    fn delete_user<'a>(&'a self, token: &'a str, username: &'a str) -> AuthFuture<'a, ()>;
+}
+
+/// The repository the app uses, next to `auth::default_provider`: builds that set `AUTH_ENDPOINT`
+/// manage that server's users, everything else the local provider's accounts.
+pub fn default_repository() -> Rc<dyn UserRepository> {
+    match option_env!("AUTH_ENDPOINT") {
+        Some(endpoint) => Rc::new(HttpAuthProvider::new(endpoint)),
+        None => Rc::new(LocalAuthProvider::default()),
+    }
+}
+
+/// What an admin entered in the user form. `password` is only used when creating a user.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UserForm {
+    pub username: String,
+    pub email: String,
+    pub password: String,
+    pub name: String,
+    /// `None` when no valid date was entered.
+    pub birthday: Option<NaiveDate>,
+    pub role: Role,
+}
+
+impl UserForm {
+    fn profile(&self, errors: ValidationErrors) -> Result<UserProfile, RegistrationError> {
+        validate_profile(
+            &self.username,
+            &self.email,
+            &self.name,
+            self.birthday,
+            self.role,
+            errors,
+        )
+        .map_err(RegistrationError::Invalid)
+    }
+}
+
+/// Validate `form` and create the account it describes.
+pub async fn create_user(
+    repository: &dyn UserRepository,
+    token: &str,
+    form: &UserForm,
+) -> Result<ManagedUser, RegistrationError> {
+    let mut errors = ValidationErrors::default();
+    errors.check(UserField::Password, validate_password(&form.password));
+    let profile = form.profile(errors)?;
+    repository
+        .create_user(token, &profile, &form.password)
+        .await
+        .map_err(RegistrationError::Auth)
+}
+
+/// Validate `form` and save it as the new details of the account called `username`.
+pub async fn update_user(
+    repository: &dyn UserRepository,
+    token: &str,
+    username: &str,
+    form: &UserForm,
+) -> Result<ManagedUser, RegistrationError> {
+    let profile = form.profile(ValidationErrors::default())?;
+    repository
+        .update_user(token, username, &profile)
+        .await
+        .map_err(RegistrationError::Auth)
+}
@@ -0,0 +1,175 @@
+use chrono::{Datelike, Local, NaiveDate};
+
+/// Users must be at least this old to sign up.
+pub const MIN_AGE_YEARS: i32 = 13;
+/// Passwords shorter than this are rejected.
+pub const MIN_PASSWORD_LENGTH: usize = 8;
+
+/// The user fields that can be validated, used to show errors next to the right input.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UserField {
+    Username,
+    Email,
+    Password,
+    /// The password the user has now, asked for before changing it.
+    CurrentPassword,
+    Name,
+    Birthday,
+}
+
+/// Error messages for the fields that failed validation.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ValidationErrors(Vec<(UserField, String)>);
+
+impl ValidationErrors {
+    /// Record `result` for `field` if it is an error.
+    pub fn check(&mut self, field: UserField, result: Result<(), String>) {
+        if let Err(message) = result {
+            self.0.push((field, message));
+        }
+    }
+
+    /// The error message for `field`, if it has one.
+    pub fn get(&self, field: UserField) -> Option<&str> {
+        self.0
+            .iter()
+            .find(|(f, _)| *f == field)
+            .map(|(_, message)| message.as_str())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Every error message, in the order they were found.
+    pub fn messages(&self) -> impl Iterator<Item = &str> {
+        self.0.iter().map(|(_, message)| message.as_str())
+    }
+}
+
+pub fn validate_username(username: &str) -> Result<(), String> {
+    let username = username.trim();
+    if username.len() < 3 {
+        return Err("Username must be at least 3 characters.".to_string());
+    }
+    if !username
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-' || c == '.')
+    {
+        return Err("Username may only contain letters, digits, '.', '_' and '-'.".to_string());
+    }
+    Ok(())
+}
+
+pub fn validate_email(email: &str) -> Result<(), String> {
+    let invalid = || Err("Please enter a valid email address.".to_string());
+
+    let Some((local, domain)) = email.trim().split_once('@') else {
+        return invalid();
+    };
+    if local.is_empty()
+        || domain.contains('@')
+        || email.trim().contains(char::is_whitespace)
+        || !domain.contains('.')
+        || domain.starts_with('.')
+        || domain.ends_with('.')
+    {
+        return invalid();
+    }
+    Ok(())
+}
+
+pub fn validate_password(password: &str) -> Result<(), String> {
+    if password.chars().count() < MIN_PASSWORD_LENGTH {
+        return Err(format!(
+            "Password must be at least {} characters.",
+            MIN_PASSWORD_LENGTH
+        ));
+    }
+    if !password.chars().any(char::is_alphabetic) || !password.chars().any(|c| c.is_ascii_digit()) {
+        return Err("Password must contain both letters and digits.".to_string());
+    }
+    Ok(())
+}
+
+pub fn validate_name(name: &str) -> Result<(), String> {
+    if name.trim().is_empty() {
+        return Err("Please enter your name.".to_string());
+    }
+    Ok(())
+}
+
+/// Birthdays can't be in the future, and the user must be at least `MIN_AGE_YEARS` old.
+pub fn validate_birthday(birthday: NaiveDate) -> Result<(), String> {
+    let today = Local::now().date_naive();
+    if birthday > today {
+        return Err("Birthday can't be in the future.".to_string());
+    }
+
+    let mut age = today.year() - birthday.year();
+    if (today.month(), today.day()) < (birthday.month(), birthday.day()) {
+        age -= 1;
+    }
+    if age < MIN_AGE_YEARS {
+        return Err(format!("You must be at least {} years old.", MIN_AGE_YEARS));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Months;
+
+    use super::*;
+
+    #[test]
+    fn usernames() {
+        assert!(validate_username(" ada_lovelace-1.0 ").is_ok());
+        assert!(validate_username("ab").is_err());
+        assert!(validate_username("ada lovelace").is_err());
+        assert!(validate_username("åse").is_err());
+    }
+
+    #[test]
+    fn emails() {
+        assert!(validate_email(" ada@example.com ").is_ok());
+        for email in [
+            "ada",
+            "@example.com",
+            "ada@example",
+            "ada@.com",
+            "ada@example.",
+            "a@b@c.com",
+            "a da@example.com",
+        ] {
+            assert!(validate_email(email).is_err(), "{}", email);
+        }
+    }
+
+    #[test]
+    fn passwords() {
+        assert!(validate_password("password1").is_ok());
+        assert!(validate_password("pass1").is_err());
+        assert!(validate_password("password").is_err());
+        assert!(validate_password("12345678").is_err());
+    }
+
+    #[test]
+    fn birthdays() {
+        let today = Local::now().date_naive();
+        let years_ago = |years: u32| today.checked_sub_months(Months::new(12 * years)).unwrap();
+        assert!(validate_birthday(years_ago(MIN_AGE_YEARS as u32)).is_ok());
+        assert!(validate_birthday(years_ago(MIN_AGE_YEARS as u32).succ_opt().unwrap()).is_err());
+        assert!(validate_birthday(today.succ_opt().unwrap()).is_err());
+    }
+
+    #[test]
+    fn errors_are_kept_per_field() {
+        let mut errors = ValidationErrors::default();
+        errors.check(UserField::Name, validate_name(" "));
+        errors.check(UserField::Email, validate_email("ada@example.com"));
+        assert!(!errors.is_empty());
+        assert_eq!(errors.get(UserField::Name), Some("Please enter your name."));
+        assert_eq!(errors.get(UserField::Email), None);
+    }
+}
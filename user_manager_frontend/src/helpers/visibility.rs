@@ -0,0 +1,49 @@
+use web_sys::{
+    js_sys::Array,
+    wasm_bindgen::{closure::Closure, JsCast},
+    Element, IntersectionObserver, IntersectionObserverEntry, IntersectionObserverInit,
+};
+use yew::{hook, use_effect_with, use_state, NodeRef};
+
+/// Whether the element behind `node` is on screen, as an IntersectionObserver sees it.
+///
+/// `margin` grows the viewport like a CSS margin (e.g. `"400px"`), so elements count as visible
+/// a little before they scroll into view. The element has to be rendered whenever the component is;
+/// one that only shows up later isn't watched.
+#[hook]
+pub fn use_visible(node: NodeRef, margin: &'static str) -> bool {
+    let visible = use_state(|| false);
+
+    {
+        let visible = visible.clone();
+        use_effect_with(node, move |node| {
+            let callback = Closure::<dyn FnMut(Array)>::new(move |entries: Array| {
+                // The last entry is the most recent change.
+                if let Some(entry) = entries.iter().last() {
+                    visible.set(
+                        entry
+                            .unchecked_into::<IntersectionObserverEntry>()
+                            .is_intersecting(),
+                    );
+                }
+            });
+            let options = IntersectionObserverInit::new();
+            options.set_root_margin(margin);
+            let observer =
+                IntersectionObserver::new_with_options(callback.as_ref().unchecked_ref(), &options)
+                    .ok();
+            if let (Some(observer), Some(element)) = (&observer, node.cast::<Element>()) {
+                observer.observe(&element);
+            }
+
+            move || {
+                if let Some(observer) = observer {
+                    observer.disconnect();
+                }
+                drop(callback);
+            }
+        });
+    }
+
+    *visible
+}
@@ -10,6 +10,9 @@ mod pages;
 
 // The main function is the entry point of our application.
 fn main() {
+    // Older versions stored the whole user, password hash included, in LocalStorage. Get rid of it.
+    helpers::session::purge_legacy_state();
+
     // `yew::Renderer::<App>::new()` creates a new renderer for our root component `App`.
     // `.render()` starts the Yew application, mounting it to the browser DOM.
     yew::Renderer::<App>::new().render();
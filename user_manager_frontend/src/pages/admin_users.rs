@@ -0,0 +1,478 @@
+use chrono::NaiveDate;
+use gloo_console::{error, log};
+use web_sys::HtmlSelectElement;
+use yew::{
+    classes, function_component, html, use_effect_with, use_state, Callback, Event, Html,
+    MouseEvent, Properties, TargetCast,
+};
+use yew_router::prelude::*;
+
+use super::form::{bind, field_error, INPUT_STYLE};
+use crate::{
+    app::Route,
+    components::ErrorPanel,
+    helpers::{
+        auth::AuthError,
+        auth_context::use_auth,
+        roles::Role,
+        user_manager::RegistrationError,
+        user_repository::{
+            create_user, default_repository, update_user, ManagedUser, UserForm, UserPage,
+        },
+        validation::{UserField, ValidationErrors},
+    },
+};
+
+// How many users are shown per page.
+const USERS_PER_PAGE: usize = 10;
+
+const BUTTON_STYLE: &str = "px-3 py-1 text-sm text-white rounded-md shadow-sm transition-colors";
+
+// What an admin can do to a user straight from the table.
+#[derive(Clone, Copy, PartialEq)]
+enum UserAction {
+    Disable,
+    Enable,
+    Delete,
+}
+
+// Ask the user to confirm something that can't be undone.
+fn confirm(message: &str) -> bool {
+    web_sys::window()
+        .and_then(|window| window.confirm_with_message(message).ok())
+        .unwrap_or(false)
+}
+
+// A paginated table of every account, with ways to create, edit, disable and delete them.
+// Only admins get here; `App` shows everyone else a forbidden view.
+#[function_component]
+pub fn AdminUsersPage() -> Html {
+    let auth = use_auth();
+    let page = use_state(|| 0usize);
+    // Bumped after every change, so the current page is loaded again.
+    let version = use_state(|| 0u32);
+    let users = use_state(|| None::<Result<UserPage, AuthError>>);
+    let action_error = use_state(|| None::<AuthError>);
+
+    {
+        let users = users.clone();
+        let page = page.clone();
+        let token = auth.token();
+        use_effect_with((*page, *version), move |(current, _)| {
+            let current = *current;
+            wasm_bindgen_futures::spawn_local(async move {
+                let Some(token) = token else {
+                    users.set(Some(Err(AuthError::NotLoggedIn)));
+                    return;
+                };
+                let result = default_repository()
+                    .list_users(&token, current, USERS_PER_PAGE)
+                    .await;
+                match &result {
+                    // The last user on the last page was deleted, so show the page before it.
+                    Ok(loaded) if loaded.users.is_empty() && current > 0 => {
+                        page.set(current - 1);
+                        return;
+                    }
+                    Err(err) => error!("Couldn't load users", err.to_string()),
+                    Ok(_) => {}
+                }
+                users.set(Some(result));
+            });
+            || ()
+        });
+    }
+
+    // Run `action` on the user called `username`, then load the page again.
+    let on_action = {
+        let token = auth.token();
+        let version = version.clone();
+        let action_error = action_error.clone();
+        move |username: String, action: UserAction| {
+            let token = token.clone();
+            let version = version.clone();
+            let action_error = action_error.clone();
+            Callback::from(move |_: MouseEvent| {
+                if action == UserAction::Delete
+                    && !confirm(&format!("Delete {}? This can't be undone.", username))
+                {
+                    return;
+                }
+                let Some(token) = token.clone() else {
+                    return;
+                };
+                let username = username.clone();
+                let version = version.clone();
+                let action_error = action_error.clone();
+                wasm_bindgen_futures::spawn_local(async move {
+                    let repository = default_repository();
+                    let result = match action {
+                        UserAction::Disable => {
+                            repository.set_disabled(&token, &username, true).await
+                        }
+                        UserAction::Enable => {
+                            repository.set_disabled(&token, &username, false).await
+                        }
+                        UserAction::Delete => repository.delete_user(&token, &username).await,
+                    };
+                    match result {
+                        Ok(()) => {
+                            log!("Updated user", username);
+                            action_error.set(None);
+                        }
+                        Err(err) => {
+                            error!("Couldn't update user", err.to_string());
+                            action_error.set(Some(err));
+                        }
+                    }
+                    version.set(*version + 1);
+                });
+            })
+        }
+    };
+
+    let on_retry = {
+        let version = version.clone();
+        Callback::from(move |_| version.set(*version + 1))
+    };
+    let go_to = |target: usize| {
+        let page = page.clone();
+        Callback::from(move |_: MouseEvent| page.set(target))
+    };
+
+    let row = |user: &ManagedUser| {
+        let profile = &user.profile;
+        let toggle = if user.disabled {
+            html! {
+                <button
+                    onclick={on_action(profile.username.clone(), UserAction::Enable)}
+                    class={classes!(BUTTON_STYLE, "bg-green-600", "hover:bg-green-700")}
+                >
+                    { "Enable" }
+                </button>
+            }
+        } else {
+            html! {
+                <button
+                    onclick={on_action(profile.username.clone(), UserAction::Disable)}
+                    class={classes!(BUTTON_STYLE, "bg-yellow-600", "hover:bg-yellow-700")}
+                >
+                    { "Disable" }
+                </button>
+            }
+        };
+        html! {
+            <tr key={profile.username.clone()} class="border-t border-gray-200">
+                <td class="p-2 font-medium">{ &profile.username }</td>
+                <td class="p-2">{ &profile.name }</td>
+                <td class="p-2">{ &profile.email }</td>
+                <td class="p-2">{ profile.birthday.format("%Y-%m-%d").to_string() }</td>
+                <td class="p-2">{ profile.role.to_string() }</td>
+                <td class="p-2">{ if user.disabled { "Disabled" } else { "Active" } }</td>
+                <td class="p-2 flex gap-2">
+                    <Link<Route>
+                        to={Route::AdminEditUser { username: profile.username.clone() }}
+                        classes={classes!(BUTTON_STYLE, "bg-blue-500", "hover:bg-blue-600")}
+                    >
+                        { "Edit" }
+                    </Link<Route>>
+                    { toggle }
+                    <button
+                        onclick={on_action(profile.username.clone(), UserAction::Delete)}
+                        class={classes!(BUTTON_STYLE, "bg-[#d9534f]", "hover:bg-[#c9302c]")}
+                    >
+                        { "Delete" }
+                    </button>
+                </td>
+            </tr>
+        }
+    };
+
+    html! {
+        <div class="max-w-[1200px] mx-auto p-4 box-border">
+            <div class="flex flex-wrap justify-between items-center mb-5">
+                <h1 class="text-[clamp(1.5rem,5vw,2.5rem)] text-gray-900 font-bold m-0">{ "Users" }</h1>
+                <div class="flex gap-2">
+                    <Link<Route> to={Route::Home} classes="px-4 py-2 text-blue-600 hover:underline">
+                        { "Back to the front page" }
+                    </Link<Route>>
+                    <Link<Route>
+                        to={Route::AdminNewUser}
+                        classes="px-4 py-2 bg-[#5cb85c] text-white font-semibold rounded-lg hover:shadow-md"
+                    >
+                        { "New user" }
+                    </Link<Route>>
+                </div>
+            </div>
+            {
+                match &*action_error {
+                    Some(err) => html! { <p class="text-red-700 mb-4">{ err.to_string() }</p> },
+                    None => html! {},
+                }
+            }
+            {
+                match &*users {
+                    None => html! { <p class="text-gray-600">{ "Loading users..." }</p> },
+                    Some(Err(AuthError::Request(err))) => html! {
+                        <ErrorPanel error={err.clone()} on_retry={on_retry} />
+                    },
+                    Some(Err(err)) => html! { <p class="text-red-700">{ err.to_string() }</p> },
+                    Some(Ok(loaded)) => {
+                        let page_count = loaded.page_count(USERS_PER_PAGE);
+                        html! {
+                            <>
+                                <table class="w-full bg-white shadow-md rounded-lg text-left">
+                                    <thead>
+                                        <tr class="text-gray-600">
+                                            <th class="p-2">{ "Username" }</th>
+                                            <th class="p-2">{ "Name" }</th>
+                                            <th class="p-2">{ "Email" }</th>
+                                            <th class="p-2">{ "Birthday" }</th>
+                                            <th class="p-2">{ "Role" }</th>
+                                            <th class="p-2">{ "Status" }</th>
+                                            <th class="p-2"></th>
+                                        </tr>
+                                    </thead>
+                                    <tbody>
+                                        { for loaded.users.iter().map(row) }
+                                    </tbody>
+                                </table>
+                                <div class="flex justify-center items-center gap-4 mt-4">
+                                    <button
+                                        onclick={go_to(page.saturating_sub(1))}
+                                        disabled={*page == 0}
+                                        class="px-3 py-1 rounded-md border disabled:opacity-50"
+                                    >
+                                        { "Previous" }
+                                    </button>
+                                    <span class="text-gray-600">
+                                        { format!("Page {} of {} ({} users)", *page + 1, page_count, loaded.total) }
+                                    </span>
+                                    <button
+                                        onclick={go_to(*page + 1)}
+                                        disabled={*page + 1 >= page_count}
+                                        class="px-3 py-1 rounded-md border disabled:opacity-50"
+                                    >
+                                        { "Next" }
+                                    </button>
+                                </div>
+                            </>
+                        }
+                    }
+                }
+            }
+        </div>
+    }
+}
+
+#[derive(PartialEq, Properties)]
+pub struct UserFormPageProps {
+    // The user to edit. Without one, the form creates a new user.
+    #[prop_or_default]
+    pub username: Option<String>,
+}
+
+// A form to create a user, or to edit the details and role of an existing one.
+#[function_component]
+pub fn UserFormPage(props: &UserFormPageProps) -> Html {
+    let navigator = use_navigator().expect("Couldn't get the navigator");
+    let auth = use_auth();
+    let editing = props.username.clone();
+
+    // One piece of state per input field.
+    let username = use_state(|| "".to_string());
+    let email = use_state(|| "".to_string());
+    let password = use_state(|| "".to_string());
+    let name = use_state(|| "".to_string());
+    let birthday = use_state(|| "".to_string());
+    let role = use_state(Role::default);
+
+    // Errors for individual fields, and one for problems that aren't about a single field.
+    let field_errors = use_state(ValidationErrors::default);
+    let error_message = use_state(|| "".to_string());
+
+    // Fill in the form with the user being edited.
+    {
+        let username = username.clone();
+        let email = email.clone();
+        let name = name.clone();
+        let birthday = birthday.clone();
+        let role = role.clone();
+        let error_message = error_message.clone();
+        let token = auth.token();
+        use_effect_with(editing.clone(), move |editing| {
+            if let (Some(editing), Some(token)) = (editing.clone(), token) {
+                wasm_bindgen_futures::spawn_local(async move {
+                    match default_repository().get_user(&token, &editing).await {
+                        Ok(user) => {
+                            let profile = user.profile;
+                            username.set(profile.username);
+                            email.set(profile.email);
+                            name.set(profile.name);
+                            birthday.set(profile.birthday.format("%Y-%m-%d").to_string());
+                            role.set(profile.role);
+                        }
+                        Err(err) => {
+                            error!("Couldn't load user", err.to_string());
+                            error_message.set(err.to_string());
+                        }
+                    }
+                });
+            }
+            || ()
+        });
+    }
+
+    let on_role = {
+        let role = role.clone();
+        Callback::from(move |e: Event| {
+            match e
+                .target_unchecked_into::<HtmlSelectElement>()
+                .value()
+                .parse()
+            {
+                Ok(selected) => role.set(selected),
+                Err(err) => error!(err),
+            }
+        })
+    };
+
+    let on_save = {
+        let editing = editing.clone();
+        let username = username.clone();
+        let email = email.clone();
+        let password = password.clone();
+        let name = name.clone();
+        let birthday = birthday.clone();
+        let role = role.clone();
+        let field_errors = field_errors.clone();
+        let error_message = error_message.clone();
+
+        Callback::from(move |_| {
+            let form = UserForm {
+                username: (*username).clone(),
+                email: (*email).clone(),
+                password: (*password).clone(),
+                name: (*name).clone(),
+                // `<input type="date">` gives us an ISO date, or an empty string if nothing valid was picked.
+                birthday: NaiveDate::parse_from_str(&birthday, "%Y-%m-%d").ok(),
+                role: *role,
+            };
+            let Some(token) = auth.token() else {
+                return;
+            };
+            let editing = editing.clone();
+            let field_errors = field_errors.clone();
+            let error_message = error_message.clone();
+            let navigator = navigator.clone();
+
+            wasm_bindgen_futures::spawn_local(async move {
+                let repository = default_repository();
+                let result = match &editing {
+                    Some(editing) => update_user(&*repository, &token, editing, &form).await,
+                    None => create_user(&*repository, &token, &form).await,
+                };
+
+                match result {
+                    Ok(_) => navigator.push(&Route::AdminUsers),
+                    Err(RegistrationError::Invalid(errors)) => {
+                        field_errors.set(errors);
+                        error_message.set("".to_string());
+                    }
+                    Err(RegistrationError::Auth(err)) => {
+                        field_errors.set(ValidationErrors::default());
+                        error_message.set(err.to_string());
+                        error!("Couldn't save user", err.to_string());
+                    }
+                }
+            });
+        })
+    };
+
+    html! {
+        <div class="max-w-xl mx-auto my-8 p-6 bg-white shadow-md rounded-lg">
+            <h1 class="text-3xl text-gray-800 font-bold mb-4">
+                { if editing.is_some() { "Edit user" } else { "New user" } }
+            </h1>
+            <input
+                type="text"
+                placeholder="Username"
+                value={(*username).clone()}
+                oninput={bind(&username)}
+                style={INPUT_STYLE}
+            />
+            { field_error(&field_errors, UserField::Username) }
+            <input
+                type="email"
+                placeholder="Email"
+                value={(*email).clone()}
+                oninput={bind(&email)}
+                style={INPUT_STYLE}
+            />
+            { field_error(&field_errors, UserField::Email) }
+            {
+                // Passwords are only set when the account is created.
+                if editing.is_none() {
+                    html! {
+                        <>
+                            <input
+                                type="password"
+                                placeholder="Password"
+                                value={(*password).clone()}
+                                oninput={bind(&password)}
+                                style={INPUT_STYLE}
+                            />
+                            { field_error(&field_errors, UserField::Password) }
+                        </>
+                    }
+                } else {
+                    html! {}
+                }
+            }
+            <input
+                type="text"
+                placeholder="Full name"
+                value={(*name).clone()}
+                oninput={bind(&name)}
+                style={INPUT_STYLE}
+            />
+            { field_error(&field_errors, UserField::Name) }
+            <label class="block text-sm text-gray-600">{ "Birthday" }</label>
+            <input
+                type="date"
+                value={(*birthday).clone()}
+                oninput={bind(&birthday)}
+                style={INPUT_STYLE}
+            />
+            { field_error(&field_errors, UserField::Birthday) }
+            <label class="block text-sm text-gray-600">{ "Role" }</label>
+            <select onchange={on_role} style={INPUT_STYLE}>
+                {
+                    for Role::ALL.into_iter().map(|option| html! {
+                        <option value={option.to_string()} selected={option == *role}>
+                            { option.to_string() }
+                        </option>
+                    })
+                }
+            </select>
+            <div class="flex gap-4 items-center mt-4">
+                <button
+                    onclick={on_save}
+                    class="px-4 py-2 bg-[#5cb85c] text-white font-semibold rounded-lg hover:shadow-md"
+                >
+                    { "Save" }
+                </button>
+                <Link<Route> to={Route::AdminUsers} classes="text-blue-600 hover:underline">
+                    { "Cancel" }
+                </Link<Route>>
+            </div>
+            {
+                if !(*error_message).is_empty() {
+                    html! { <p class="text-red-700 mt-4">{ &*error_message }</p> }
+                } else {
+                    html! {}
+                }
+            }
+        </div>
+    }
+}
@@ -0,0 +1,86 @@
+use gloo_console::{error, log};
+use serde::Deserialize;
+use yew::{function_component, html, use_effect_with, use_state, Html};
+use yew_router::prelude::*;
+
+use super::login::return_route;
+use crate::{
+    app::{LoginQuery, Route},
+    helpers::{auth::AuthError, auth_context::use_auth, oidc::OidcError},
+};
+
+// The query string the identity provider sends the browser back with.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+struct CallbackQuery {
+    code: Option<String>,
+    state: Option<String>,
+    // Set instead of `code` when the login was turned down.
+    error: Option<String>,
+    error_description: Option<String>,
+}
+
+// Where the identity provider sends the browser back to. Exchanges the code for a session once,
+// then goes on to the page the login was started from.
+#[function_component]
+pub fn AuthCallbackPage() -> Html {
+    let navigator = use_navigator().expect("Couldn't get the navigator");
+    let location = use_location().expect("Couldn't get the location");
+    let auth = use_auth();
+    let error_message = use_state(|| "".to_string());
+
+    {
+        let error_message = error_message.clone();
+        use_effect_with((), move |_| {
+            let query = location.query::<CallbackQuery>().unwrap_or_default();
+            wasm_bindgen_futures::spawn_local(async move {
+                let result = match query {
+                    CallbackQuery {
+                        error: Some(err),
+                        error_description,
+                        ..
+                    } => Err(OidcError::Denied(error_description.unwrap_or(err)).into()),
+                    CallbackQuery {
+                        code: Some(code),
+                        state: Some(state),
+                        ..
+                    } => auth.finish_oidc_login(&code, &state).await,
+                    _ => Err(AuthError::from(OidcError::NoLoginInProgress)),
+                };
+                match result {
+                    Ok(next) => {
+                        log!("Logged in through single sign-on.");
+                        // Replaced, so going back doesn't try to use the same code again.
+                        return_route(&LoginQuery { next: Some(next) }).replace(&navigator);
+                    }
+                    Err(err) => {
+                        error!("Single sign-on error", err.to_string());
+                        error_message.set(err.to_string());
+                    }
+                }
+            });
+            || ()
+        });
+    }
+
+    html! {
+        <div style="font-family: Arial, sans-serif; padding: 20px; background-color: #f8f9fa;">
+            <div style="display: flex; justify-content: center; align-items: center; height: 100vh; background-color: #f5f5f5;">
+                <div style="text-align: center; background: white; padding: 40px; border-radius: 8px; box-shadow: 0 4px 6px rgba(0, 0, 0, 0.1); max-width: 360px;">
+                    {
+                        if (*error_message).is_empty() {
+                            html! { <p>{ "Finishing your login..." }</p> }
+                        } else {
+                            html! {
+                                <>
+                                    <h1 style="margin-bottom: 20px;">{ "Login failed" }</h1>
+                                    <p style="color: red; margin-bottom: 20px;">{ &*error_message }</p>
+                                    <Link<Route> to={Route::Login}>{ "Back to logging in" }</Link<Route>>
+                                </>
+                            }
+                        }
+                    }
+                </div>
+            </div>
+        </div>
+    }
+}
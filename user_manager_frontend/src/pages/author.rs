@@ -0,0 +1,78 @@
+use gloo_console::error;
+use yew::{function_component, html, Callback, Html, Properties};
+use yew_router::prelude::*;
+
+use super::home::InfinitePosts;
+use crate::{
+    app::Route,
+    components::{ErrorPanel, NotFound, PortableText},
+    helpers::{
+        content::{default_source, PostFilter},
+        fetch::{use_fetch, FetchError, FetchState},
+    },
+};
+
+#[derive(PartialEq, Properties)]
+pub struct AuthorPageProps {
+    pub slug: String,
+}
+
+// An author's picture, name and bio, followed by their posts, newest first.
+#[function_component]
+pub fn AuthorPage(props: &AuthorPageProps) -> Html {
+    let AuthorPageProps { slug } = props;
+
+    let author = use_fetch(slug.clone(), |slug| {
+        let slug = slug.clone();
+        async move {
+            let author = default_source().get_author(&slug).await;
+            if let Err(err) = &author {
+                error!("Couldn't load the author", err.to_string());
+            }
+            author
+        }
+    });
+    let on_retry = {
+        let author = author.clone();
+        Callback::from(move |_| author.retry())
+    };
+
+    match &*author {
+        FetchState::Idle | FetchState::Loading => {
+            html! { <p class="text-center text-gray-600">{ "Loading..." }</p> }
+        }
+        FetchState::Err(FetchError::NotFound) => html! {
+            <NotFound message={format!("We couldn't find an author called \"{}\".", slug)} />
+        },
+        FetchState::Err(err) => html! { <ErrorPanel error={err.clone()} on_retry={on_retry} /> },
+        FetchState::Ok(author) => html! {
+            <div class="max-w-[1200px] mx-auto p-4 box-border">
+                <div class="flex flex-col sm:flex-row items-center gap-6 mb-8 p-6 bg-white shadow-md rounded-lg">
+                    {
+                        match &author.image_url {
+                            Some(url) => html! {
+                                <img src={url.clone()} alt={author.name.clone()} class="w-24 h-24 rounded-full object-cover" />
+                            },
+                            None => html! {},
+                        }
+                    }
+                    <div>
+                        <h1 class="text-3xl text-gray-800 font-bold mb-2">{ &author.name }</h1>
+                        <div class="prose text-gray-700 max-w-none">
+                            <PortableText blocks={author.bio.clone()} />
+                        </div>
+                    </div>
+                </div>
+                <div class="flex flex-wrap justify-between items-center mb-5">
+                    <h2 class="text-[clamp(1.2rem,4vw,1.8rem)] text-gray-800 font-semibold m-0">
+                        { format!("Posts by {}", author.name) }
+                    </h2>
+                    <Link<Route> to={Route::Home} classes="text-blue-600 hover:underline">
+                        { "Back to the front page" }
+                    </Link<Route>>
+                </div>
+                <InfinitePosts key={slug.clone()} filter={PostFilter::Author(slug.clone())} />
+            </div>
+        },
+    }
+}
@@ -0,0 +1,25 @@
+use web_sys::HtmlInputElement;
+use yew::{html, Callback, Html, InputEvent, TargetCast, UseStateHandle};
+
+use crate::helpers::validation::{UserField, ValidationErrors};
+
+pub const INPUT_STYLE: &str =
+    "padding: 8px; width: 100%; margin-bottom: 4px; border: 1px solid #ccc; border-radius: 4px;";
+
+// Keeps an input's current value in `state`.
+pub fn bind(state: &UseStateHandle<String>) -> Callback<InputEvent> {
+    let state = state.clone();
+    Callback::from(move |e: InputEvent| {
+        state.set(e.target_unchecked_into::<HtmlInputElement>().value())
+    })
+}
+
+// The error message shown under an input, if that field is invalid.
+pub fn field_error(errors: &ValidationErrors, field: UserField) -> Html {
+    match errors.get(field) {
+        Some(message) => html! {
+            <p style="color: red; font-size: 14px; margin: 0 0 10px; text-align: left;">{ message }</p>
+        },
+        None => html! { <div style="margin-bottom: 6px;"></div> },
+    }
+}
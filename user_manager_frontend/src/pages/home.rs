@@ -1,47 +1,43 @@
-use gloo_console::log;
-// This import allows you to make HTTP requests (e.g., GET, POST) from Rust in a web environment.
-use gloo_net::http::Request;
-// Here we import features to interact with the browser's local storage (LocalStorage).
-use gloo_storage::{LocalStorage, Storage};
-// `serde` is a framework for serializing and deserializing data. `Deserialize` helps decode JSON into Rust types.
-use serde::Deserialize;
+use std::rc::Rc;
+
+use gloo_console::{error, log};
 
 // Below are parts of the Yew framework:
 // - `function_component` for creating a functional component
 // - `html` for writing HTML in Rust using a JSX-like syntax
-// - `use_effect_with` for running side effects
 // - `use_state` for state management
 // - `Callback` and `Html` are utility types
-use yew::{function_component, html, use_effect_with, use_state, Callback, Html};
+use yew::{
+    function_component, html, use_effect_with, use_node_ref, use_reducer, use_state, Callback,
+    Html, MouseEvent, Properties, Reducible, UseReducerHandle,
+};
 // This import gives us the ability to navigate between pages/routes in a Yew application.
 use yew_router::prelude::*;
 
-use crate::{app::Route, helpers::user_manager::UserState};
-
-
-// We define a struct that matches the shape of the JSON response for fetching blog posts (outer container).
-#[derive(Debug, Clone, PartialEq, Deserialize)]
-struct BlogPostsRequest {
-    result: Vec<BlogPost>,
-}
-
-// This struct represents individual blog posts.
-#[derive(Debug, Clone, PartialEq, Deserialize)]
-struct BlogPost {
-    slug: BlogSlug,
-    title: String,
-    #[serde(rename = "logoUrl")]
-    logo_url: Option<String>,
-}
+use crate::{
+    app::{HomeQuery, Route},
+    components::{BlogPost, ErrorPanel, PostGrid},
+    helpers::{
+        auth_context::use_auth,
+        content::{default_source, PostFilter, PostSlice},
+        fetch::{use_fetch, FetchError, FetchState},
+        roles::Role,
+        visibility::use_visible,
+    },
+};
 
-#[derive(Debug, Clone, PartialEq, Deserialize)]
-struct BlogSlug {
-    current: String,
-}
+// Posts per page, and per batch while scrolling.
+const POSTS_PER_PAGE: u32 = 9;
 
-#[derive(Debug, Clone, PartialEq, Deserialize)]
-struct ApiResponse {
-    result: Vec<BlogPost>, // Blog posts are stored in "result"
+// Load `POSTS_PER_PAGE` of the posts `filter` lets through, skipping the `start` newest.
+async fn fetch_posts(filter: &PostFilter, start: u32) -> Result<PostSlice, FetchError> {
+    let slice = default_source()
+        .list_posts(filter, start, POSTS_PER_PAGE)
+        .await;
+    if let Err(err) = &slice {
+        error!("Couldn't load blog posts", err.to_string());
+    }
+    slice
 }
 
 // A Yew function component called `HomePage`. It shows a homepage view.
@@ -50,78 +46,34 @@ pub fn HomePage() -> Html {
     // Navigator lets us programmatically go to different routes.
     let navigator = use_navigator().expect("Couldn't get the navigator");
 
-    // Check if the user is logged in by reading a boolean from local storage.
-    // If the value isn't `true`, we log a message and redirect to the login page.
-    if LocalStorage::get::<bool>("login").unwrap_or_else(|_| false) == false {
-        log!("Not logged in");
-        navigator.replace(&Route::Login);
-    }
-// Try to get the user's state (e.g., Authorized, Unauthorized) from local storage.
-    // If we fail, we set "login" to false and redirect to the login page.
-    let local_storage_user_state = match LocalStorage::get::<UserState>("login_state") {
-        Ok(user_state) => user_state,
-        Err(_) => {
-            LocalStorage::set::<bool>("login", false).expect("Couldn't save login toggle!");
-
-            navigator.replace(&Route::Login);
-            return html! { <p>{ "Internal error with user handeling!" }</p> };
-        }
-    };
-     // `user_state` holds the current user's state. We initialize it from what we got above.
-    let user_state = use_state(move || local_storage_user_state);
-// We check if the user is actually authorized. If not, we send them to Login.
-    let user = match &*user_state {
-        UserState::Authorized(user) => user.clone(),
-        UserState::Unauthorized(_) => {
-            navigator.replace(&Route::Login);
-            return html! { <p>{ "Internal error with user handeling!" }</p> };
-        }
-    };
+    // Who is logged in, shared by the whole app. `RequireAuth` only shows this page to logged-in users.
+    let auth = use_auth();
+    // Greet the user with the freshest name we have.
+    let display_name = auth.display_name().unwrap_or_default();
   // A place to store any error messages that might occur.
     let error_message = use_state(|| "".to_string());
-    // This will store the list of blog posts once we fetch them.
-    let blog_posts = use_state(|| Vec::<BlogPost>::new());
-
-// This block uses a Yew "effect" hook to perform an asynchronous fetch of blog posts.
-    {
-        let blog_posts = blog_posts.clone();
- //`use_effect_with` runs a side effect when its dependencies change. Here, the dependencies are `()`, which never changes,
-        // so it runs once when the component first loads.
-        use_effect_with((), move |_| {
-            let blog_posts = blog_posts.clone();
-
-            wasm_bindgen_futures::spawn_local(async move {
-                   // Make an HTTP GET request to fetch the first 3 blog posts from our API.
-                let fetched_posts = Request::get("https://1fuw6fjt.api.sanity.io/v2022-03-07/data/query/production?query=*[_type == \"post\"][0...3]{slug,title,\"logoUrl\":logo.asset->url}")
-                    .send()
-                    .await
-                    .unwrap()
-                     // Convert the JSON response into our `BlogPostsRequest` struct.
-                    .json::<BlogPostsRequest>()
-                    .await
-                    .unwrap();
- // Update the `blog_posts` state with the fetched blog posts.
-                blog_posts.set(fetched_posts.result);
-            });
-            || ()
-        });
-    }
+    // Which page of posts to show, or whether to keep loading more while scrolling.
+    let location = use_location().expect("Couldn't get the location");
+    let query = location.query::<HomeQuery>().unwrap_or_default();
 // Callback that runs when the user clicks the "Log out" button.
     let on_logout = {
-        let user_state = user_state.clone();
+        let auth = auth.clone();
         let error_message = error_message.clone();
         let navigator = navigator.clone();
 
         Callback::from(move |_| {
-            // We only log out if the user is currently authorized.
-            if let UserState::Authorized(manager) = &*user_state {
-                // `logout()` will return a new `UserState` (Unauthorized).
-                user_state.set(manager.clone().logout());
+            let auth = auth.clone();
+            let error_message = error_message.clone();
+            let navigator = navigator.clone();
+
+            // Telling the auth provider may involve a request, so this runs asynchronously.
+            wasm_bindgen_futures::spawn_local(async move {
+                auth.logout().await;
                 error_message.set("".to_string());
                 log!("User logged out.");
 // Redirect the user to the login page after logging out.
                 navigator.replace(&Route::Login);
-            }
+            });
         })
     };
 
@@ -139,8 +91,35 @@ pub fn HomePage() -> Html {
 
             <div class="flex flex-wrap justify-between items-center mb-5">
                 <h1 class="text-[clamp(1.5rem,5vw,2.5rem)] text-gray-900 font-bold m-0">
-                    { format!("Welcome, {}!", user.get_name()) }
+                    { format!("Welcome, {}!", display_name) }
                 </h1>
+                <Link<Route>
+                    to={Route::Search}
+                    classes="px-6 py-3 text-blue-600 font-semibold hover:underline"
+                >
+                    { "Search" }
+                </Link<Route>>
+                <Link<Route>
+                    to={Route::Profile}
+                    classes="px-6 py-3 text-blue-600 font-semibold hover:underline"
+                >
+                    { "Profile" }
+                </Link<Route>>
+                {
+                    // Only admins can manage users.
+                    if auth.has_role(Role::Admin) {
+                        html! {
+                            <Link<Route>
+                                to={Route::AdminUsers}
+                                classes="px-6 py-3 bg-blue-500 text-white font-semibold rounded-lg hover:bg-blue-600 hover:shadow-md"
+                            >
+                                { "Manage users" }
+                            </Link<Route>>
+                        }
+                    } else {
+                        html! {}
+                    }
+                }
                 <button
                     onclick={on_logout}
                     class="px-6 py-3 bg-[#d9534f] text-white font-semibold rounded-lg cursor-pointer
@@ -153,77 +132,216 @@ pub fn HomePage() -> Html {
 
 
             <div>
-                <h2 class="text-[clamp(1.2rem,4vw,1.8rem)] text-gray-800 font-semibold mb-5 mt-0">
-                    { "Latest Posts" }
-                </h2>
-
-
-                <ul class="
-                    grid grid-cols-[repeat(auto-fill,_minmax(300px,_1fr))]
-                    gap-6
-                    list-none
-                    p-0
-                    m-0
-                ">
-                    {
-                        for blog_posts.iter().map(|post| {
-                            html! {
-                                <li
-                                    class="
-                                        bg-white
-                                        p-6
-                                        rounded-xl
-                                        shadow-lg
-                                        transition-transform
-                                        duration-300
-                                        ease-in-out
-                                        cursor-pointer
-                                        hover:shadow-xl
-                                     
-                                    "
-                                >
-                                    {
-                                        if let Some(url) = &post.logo_url {
-                                            html! {
-                                                <img
-                                                    src={url.clone()}
-                                                    alt="Blog image"
-                                                    class="w-full h-auto rounded-xl mb-4"
-                                                />
-                                            }
-                                        } else {
-                                            html! {}
-                                        }
-                                    }
-                                    <h3 class="text-lg text-gray-900 font-bold mb-3 mt-0">
-                                        { &post.title }
-                                    </h3>
-                                    <Link<Route>
-                                    to={Route::Article { slug: post.slug.current.clone() }}
-                                    classes="
-                                        inline-block
-                                        px-4
-                                        py-2
-                                        bg-blue-500
-                                        text-white
-                                        text-sm
-                                        font-medium
-                                        rounded-md
-                                        shadow-sm
-                                        hover:bg-blue-600
-                                        transition-colors
-                                    "
-                                >
-                                    { "Read More" }
-                                </Link<Route>>
-
-
-                                </li>
-                            }
-                        })
+                <div class="flex flex-wrap justify-between items-center mb-5">
+                    <h2 class="text-[clamp(1.2rem,4vw,1.8rem)] text-gray-800 font-semibold m-0">
+                        { "Latest Posts" }
+                    </h2>
+                    // Switches between pages and endless scrolling.
+                    <Link<Route, HomeQuery>
+                        to={Route::Home}
+                        query={Some(HomeQuery { page: None, infinite: !query.infinite })}
+                        classes="text-blue-600 hover:underline"
+                    >
+                        { if query.infinite { "Show pages" } else { "Keep scrolling instead" } }
+                    </Link<Route, HomeQuery>>
+                </div>
+                {
+                    if query.infinite {
+                        html! { <InfinitePosts filter={PostFilter::All} /> }
+                    } else {
+                        html! { <PagedPosts page={query.page.unwrap_or(1).max(1)} /> }
                     }
-                </ul>
+                }
             </div>
         </div>
     }
 }
+
+#[derive(PartialEq, Properties)]
+struct PagedPostsProps {
+    // Starts at 1.
+    page: u32,
+}
+
+// Link to another page of posts.
+fn page_link(page: u32, label: &str) -> Html {
+    html! {
+        <Link<Route, HomeQuery>
+            to={Route::Home}
+            query={Some(HomeQuery { page: Some(page), infinite: false })}
+            classes="px-4 py-2 bg-blue-500 text-white text-sm font-medium rounded-md shadow-sm hover:bg-blue-600 transition-colors"
+        >
+            { label }
+        </Link<Route, HomeQuery>>
+    }
+}
+
+// One page of posts, with links to the newer and older pages.
+#[function_component]
+fn PagedPosts(props: &PagedPostsProps) -> Html {
+    let page = props.page;
+    let slice = use_fetch(page, |page| {
+        // Pages past the end, however far, just come back empty.
+        let start = (page - 1).saturating_mul(POSTS_PER_PAGE);
+        async move { fetch_posts(&PostFilter::All, start).await }
+    });
+    let on_retry = {
+        let slice = slice.clone();
+        Callback::from(move |_| slice.retry())
+    };
+
+    match &*slice {
+        FetchState::Idle | FetchState::Loading => {
+            html! { <p class="text-gray-600">{ "Loading posts..." }</p> }
+        }
+        FetchState::Err(err) => html! { <ErrorPanel error={err.clone()} on_retry={on_retry} /> },
+        FetchState::Ok(slice) => {
+            let page_count = slice.total.div_ceil(POSTS_PER_PAGE).max(1);
+            if slice.posts.is_empty() && page > 1 {
+                return html! {
+                    <div class="text-center text-gray-600">
+                        <p class="mb-4">{ format!("There are only {} pages of posts.", page_count) }</p>
+                        { page_link(1, "Go to the newest posts") }
+                    </div>
+                };
+            }
+            html! {
+                <>
+                    <PostGrid posts={slice.posts.clone()} />
+                    <nav class="flex justify-center items-center gap-4 mt-8">
+                        { if page > 1 { page_link(page - 1, "Newer posts") } else { html! {} } }
+                        <span class="text-gray-600">{ format!("Page {} of {}", page, page_count) }</span>
+                        { if page < page_count { page_link(page + 1, "Older posts") } else { html! {} } }
+                    </nav>
+                </>
+            }
+        }
+    }
+}
+
+// The posts loaded so far while scrolling.
+#[derive(Debug, Clone, Default, PartialEq)]
+struct Feed {
+    posts: Vec<BlogPost>,
+    // How many posts there are, once the first slice has told us.
+    total: Option<u32>,
+    loading: bool,
+    error: Option<FetchError>,
+}
+
+enum FeedAction {
+    Loading,
+    Loaded(PostSlice),
+    Failed(FetchError),
+}
+
+impl Reducible for Feed {
+    type Action = FeedAction;
+
+    fn reduce(self: Rc<Self>, action: Self::Action) -> Rc<Self> {
+        let mut feed = Rc::unwrap_or_clone(self);
+        match action {
+            FeedAction::Loading => {
+                feed.loading = true;
+                feed.error = None;
+            }
+            FeedAction::Loaded(slice) => {
+                feed.posts.extend(slice.posts);
+                feed.total = Some(slice.total);
+                feed.loading = false;
+            }
+            FeedAction::Failed(err) => {
+                feed.error = Some(err);
+                feed.loading = false;
+            }
+        }
+        Rc::new(feed)
+    }
+}
+
+impl Feed {
+    fn has_more(&self) -> bool {
+        match self.total {
+            Some(total) => (self.posts.len() as u32) < total,
+            None => true,
+        }
+    }
+}
+
+// Fetch the next slice of posts, unless one is already on its way or there are no more.
+fn load_more(feed: UseReducerHandle<Feed>, filter: PostFilter) {
+    if feed.loading || !feed.has_more() {
+        return;
+    }
+    let start = feed.posts.len() as u32;
+    feed.dispatch(FeedAction::Loading);
+    wasm_bindgen_futures::spawn_local(async move {
+        match fetch_posts(&filter, start).await {
+            Ok(slice) => feed.dispatch(FeedAction::Loaded(slice)),
+            Err(err) => feed.dispatch(FeedAction::Failed(err)),
+        }
+    });
+}
+
+#[derive(PartialEq, Properties)]
+pub(super) struct InfinitePostsProps {
+    pub filter: PostFilter,
+}
+
+// The posts in one list that loads older ones as the end of it scrolls into view.
+// Give it a `key` that changes with the filter, so a new filter starts a new list.
+#[function_component]
+pub(super) fn InfinitePosts(props: &InfinitePostsProps) -> Html {
+    let feed = use_reducer(Feed::default);
+    // An empty element after the posts. Once it is (nearly) on screen, more posts are loaded.
+    let sentinel = use_node_ref();
+    let near_end = use_visible(sentinel.clone(), "400px");
+
+    // Keeps loading while the end is in view, so a screen that isn't full yet fills up.
+    // After an error it waits for "Try again" instead.
+    {
+        let feed = feed.clone();
+        let filter = props.filter.clone();
+        use_effect_with(
+            (near_end, feed.posts.len(), feed.loading, feed.error.is_some()),
+            move |(near_end, _, _, failed)| {
+                if *near_end && !*failed {
+                    load_more(feed, filter);
+                }
+                || ()
+            },
+        );
+    }
+
+    // Also loads more on a click, for browsers without IntersectionObserver.
+    let on_more = {
+        let feed = feed.clone();
+        let filter = props.filter.clone();
+        Callback::from(move |_: MouseEvent| load_more(feed.clone(), filter.clone()))
+    };
+
+    html! {
+        <>
+            <PostGrid posts={feed.posts.clone()} />
+            <div ref={sentinel}></div>
+            {
+                match (&feed.error, feed.loading, feed.has_more()) {
+                    (Some(err), _, _) => html! { <ErrorPanel error={err.clone()} on_retry={on_more} /> },
+                    (None, true, _) => html! { <p class="text-gray-600 text-center mt-8">{ "Loading posts..." }</p> },
+                    (None, false, true) => html! {
+                        <div class="text-center mt-8">
+                            <button
+                                onclick={on_more}
+                                class="px-4 py-2 bg-blue-500 text-white text-sm font-medium rounded-md shadow-sm hover:bg-blue-600 transition-colors"
+                            >
+                                { "Load more posts" }
+                            </button>
+                        </div>
+                    },
+                    (None, false, false) if feed.posts.is_empty() => html! { <p class="text-gray-600">{ "There are no posts here yet." }</p> },
+                    (None, false, false) => html! { <p class="text-gray-600 text-center mt-8">{ "You've reached the oldest post." }</p> },
+                }
+            }
+        </>
+    }
+}
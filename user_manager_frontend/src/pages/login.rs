@@ -1,90 +1,419 @@
-// This line imports the `NaiveDate` type from the `chrono` library.
-// `NaiveDate` is used to work with dates without any time or timezone information.
-use chrono::NaiveDate;
-// These imports provide logging (`log` and `error`) and local storage functionality (`LocalStorage`).
+// These imports provide logging (`log` and `error`).
 use gloo_console::{error, log};
-use gloo_storage::{LocalStorage, Storage};
 
 // This import allows us to interact with the <input> elements in the browser.
 use web_sys::HtmlInputElement;
 
 // These imports are core parts of the Yew framework. They let us create components, manage state, and handle events.
-use yew::{function_component, html, use_state, Callback, Html, InputEvent, TargetCast};
+use yew::{
+    function_component, html, use_effect_with, use_force_update, use_state, Callback, Event, Html,
+    InputEvent, MouseEvent, TargetCast, UseStateHandle,
+};
+
+// Dates and the timer behind the countdown shown after too many failed logins.
+use chrono::{DateTime, Duration, Utc};
+use gloo_timers::callback::Interval;
 
-// This import allows us to navigate between different pages or routes in a Yew application.
-use yew_router::hooks::use_navigator;
+// This import allows us to navigate between different pages or routes in a Yew application,
+// and to link to other pages.
+use yew_router::prelude::*;
 
 // Here we import the `Route` enum (or struct) that defines different pages in our app,
-// and also the `UserManager` and `UserState` which handle user-related logic such as logging in.
+// and the shared auth context, which logs in through the auth provider.
 use crate::{
-    app::Route,
-    helpers::user_manager::{UserManager, UserState},
+    app::{LoginQuery, Route},
+    helpers::{
+        auth::{default_provider, AuthError},
+        auth_context::{use_auth, LoginProgress},
+        login_throttle::{describe_wait, LoginPolicy, LoginThrottle},
+        oidc::{self, default_issuer},
+        user_manager::{RegistrationError, UserManager},
+    },
 };
 
+// A page to go to after logging in, with the query it had (e.g. a search or a page number).
+#[derive(Clone, PartialEq)]
+pub(super) struct ReturnTo {
+    route: Route,
+    query: Vec<(String, String)>,
+}
+
+impl ReturnTo {
+    // The path and query, as `?next=` takes them.
+    fn to_path(&self) -> String {
+        let query = self
+            .query
+            .iter()
+            .map(|(name, value)| {
+                format!(
+                    "{}={}",
+                    urlencoding::encode(name),
+                    urlencoding::encode(value)
+                )
+            })
+            .collect::<Vec<_>>();
+        if query.is_empty() {
+            self.route.to_path()
+        } else {
+            format!("{}?{}", self.route.to_path(), query.join("&"))
+        }
+    }
+
+    pub(super) fn push(&self, navigator: &Navigator) {
+        if let Err(err) = navigator.push_with_query(&self.route, &self.query) {
+            error!("Couldn't go back to the page", err.to_string());
+            navigator.push(&self.route);
+        }
+    }
+
+    pub(super) fn replace(&self, navigator: &Navigator) {
+        if let Err(err) = navigator.replace_with_query(&self.route, &self.query) {
+            error!("Couldn't go back to the page", err.to_string());
+            navigator.replace(&self.route);
+        }
+    }
+}
+
+// The `name=value` pairs of a query string, decoded.
+fn query_pairs(query: &str) -> Vec<(String, String)> {
+    let decode = |part: &str| {
+        urlencoding::decode(&part.replace('+', " "))
+            .map(|part| part.into_owned())
+            .unwrap_or_default()
+    };
+    query
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (name, value) = pair.split_once('=').unwrap_or((pair, ""));
+            (decode(name), decode(value))
+        })
+        .collect()
+}
+
+// Where to go after logging in: the page named by `?next=` if it is one of ours, otherwise Home.
+// Only paths that match a route are accepted, so `next` can't send anyone to another site.
+// The query of `next` is kept, so a search or a page of posts comes back as it was.
+pub(super) fn return_route(query: &LoginQuery) -> ReturnTo {
+    let home = ReturnTo {
+        route: Route::Home,
+        query: Vec::new(),
+    };
+    let Some(next) = query.next.as_deref().filter(|next| next.starts_with('/')) else {
+        return home;
+    };
+    let (path, query) = next.split_once('?').unwrap_or((next, ""));
+    match Route::recognize(path) {
+        Some(Route::Login | Route::ResetPassword { .. } | Route::AuthCallback | Route::NotFound)
+        | None => home,
+        Some(route) => ReturnTo {
+            route,
+            query: query_pairs(query),
+        },
+    }
+}
+
+// Show why logging in failed. Waits after too many failures are shown as a countdown instead.
+fn show_login_error(
+    err: AuthError,
+    email: &str,
+    error_message: &UseStateHandle<String>,
+    retry_at: &UseStateHandle<Option<DateTime<Utc>>>,
+) {
+    error!("Login error", err.to_string());
+    let wait = match err {
+        AuthError::TooManyAttempts(wait) => {
+            error_message.set("".to_string());
+            Some(wait)
+        }
+        err => {
+            error_message.set(err.to_string());
+            LoginThrottle::load().wait_time(email, &LoginPolicy::default())
+        }
+    };
+    retry_at.set(wait.map(|wait| Utc::now() + wait));
+}
+
 #[function_component]
 pub fn LoginPage() -> Html {
     // `use_navigator` gives us a way to navigate between routes (pages) in our web application.
     let navigator = use_navigator().expect("Couldn't get the navigator");
+    // Who is logged in, shared by the whole app.
+    let auth = use_auth();
+    // The page to return to once the user has logged in.
+    let location = use_location().expect("Couldn't get the location");
+    let next = return_route(&location.query::<LoginQuery>().unwrap_or_default());
 
-    // This checks if the user is already logged in by reading a boolean from the browser's Local Storage.
-    // If it's `true`, we log a message and redirect the user to the Home page without showing the login form.
-    if LocalStorage::get::<bool>("login").unwrap_or_else(|_| false) == true {
-        log!("Already logged in");
-        navigator.replace(&Route::Home);
+    // This checks if the user is already logged in, i.e. has a session that hasn't expired.
+    // If so, we log a message and send the user on without showing the login form.
+    // It runs again when the user logs in in another tab while this one shows the form.
+    {
+        let navigator = navigator.clone();
+        let next = next.clone();
+        use_effect_with(auth.is_logged_in(), move |logged_in| {
+            if *logged_in {
+                log!("Already logged in");
+                next.replace(&navigator);
+            }
+            || ()
+        });
     }
 
-    // Create a piece of state (`user_state`) that holds an initial "unauthorized" user
-    // with some default test data (username, email, password, name, and birth date).
-    let user_state = use_state(|| {
-        UserManager::new(
-            "TestUser".to_string(),
-            "test@example.com".to_string(),
-            "password123".to_string(),
-            "Test Person".to_string(),
-            NaiveDate::from_ymd_opt(1990, 1, 1).expect("Couldn't parse naive date"),
-        )
-    });
-
     // Two pieces of state to store whatever the user types in the email and password fields.
-    let email = use_state(|| "".to_string());
+    // Someone who just logged out in this tab gets their email filled in.
+    let email = use_state(|| auth.last_email().unwrap_or_default());
     let password = use_state(|| "".to_string());
+    // Whether the session should survive closing the browser. Off by default, so shared machines forget it.
+    let remember = use_state(|| false);
     let error_message = use_state(|| "".to_string());
+    // When the email may try to log in again after failed attempts, and the time left until then.
+    let retry_at = use_state(|| None::<DateTime<Utc>>);
+    let wait = retry_at
+        .map(|retry_at| retry_at - Utc::now())
+        .filter(|wait| *wait > Duration::zero());
+    // Redraws the page every second while there is a wait, so the countdown moves. Once the wait
+    // is over the effect runs again and drops the interval.
+    {
+        let force_update = use_force_update();
+        use_effect_with((*retry_at, wait.is_some()), move |(_, waiting)| {
+            let interval =
+                waiting.then(|| Interval::new(1_000, move || force_update.force_update()));
+            move || drop(interval)
+        });
+    }
+    // Whether the password was right and the form now asks for a two-factor code.
+    let second_factor = use_state(|| false);
+    let code = use_state(|| "".to_string());
+    // Whether the form asks for an email to send a password reset link to, instead of logging in.
+    let forgot = use_state(|| false);
+    // Shown once a reset link has been asked for.
+    let reset_message = use_state(|| "".to_string());
+
+    // Switches between logging in and asking for a reset link.
+    let on_forgot = {
+        let forgot = forgot.clone();
+        let error_message = error_message.clone();
+        let reset_message = reset_message.clone();
+        Callback::from(move |_| {
+            error_message.set("".to_string());
+            reset_message.set("".to_string());
+            forgot.set(!*forgot);
+        })
+    };
+
+    // This callback is triggered when the user asks for a password reset link.
+    let on_reset = {
+        let email = email.clone();
+        let error_message = error_message.clone();
+        let reset_message = reset_message.clone();
+
+        Callback::from(move |_| {
+            let email = (*email).clone();
+            let error_message = error_message.clone();
+            let reset_message = reset_message.clone();
+
+            wasm_bindgen_futures::spawn_local(async move {
+                match UserManager::request_password_reset(&*default_provider(), &email).await {
+                    // The same answer whether or not the account exists.
+                    Ok(()) => {
+                        error_message.set("".to_string());
+                        reset_message.set(
+                            "If there is an account with that email, we have sent it a link to reset the password."
+                                .to_string(),
+                        );
+                    }
+                    Err(RegistrationError::Invalid(_)) => {
+                        error_message.set("Please enter a valid email address.".to_string());
+                    }
+                    Err(RegistrationError::Auth(err)) => {
+                        error_message.set(err.to_string());
+                        error!("Password reset error", err.to_string());
+                    }
+                }
+            });
+        })
+    };
 
     // This callback is triggered when the user clicks the "Log in" button.
+    // This callback is triggered when the user enters their two-factor code.
+    let on_verify = {
+        let email = email.clone();
+        let code = code.clone();
+        let remember = remember.clone();
+        let error_message = error_message.clone();
+        let retry_at = retry_at.clone();
+        let navigator = navigator.clone();
+        let next = next.clone();
+        let auth = auth.clone();
+        let second_factor = second_factor.clone();
+
+        Callback::from(move |_| {
+            let email = (*email).clone();
+            let code = code.clone();
+            let remember = *remember;
+            let error_message = error_message.clone();
+            let retry_at = retry_at.clone();
+            let navigator = navigator.clone();
+            let next = next.clone();
+            let auth = auth.clone();
+            let second_factor = second_factor.clone();
+
+            wasm_bindgen_futures::spawn_local(async move {
+                match auth.verify_second_factor(&code, remember).await {
+                    Ok(()) => {
+                        error_message.set("".to_string());
+                        log!("User logged in successfully!");
+                        next.push(&navigator);
+                    }
+                    // The login expired or took too many wrong codes, so it starts over.
+                    Err(AuthError::InvalidCredentials) => {
+                        auth.cancel_second_factor();
+                        code.set("".to_string());
+                        second_factor.set(false);
+                        error_message.set(
+                            "Your login has expired. Please enter your password again.".to_string(),
+                        );
+                    }
+                    Err(err) => {
+                        code.set("".to_string());
+                        show_login_error(err, &email, &error_message, &retry_at);
+                    }
+                }
+            });
+        })
+    };
+
+    // Goes back to the password form from the code step.
+    let on_cancel_code = {
+        let second_factor = second_factor.clone();
+        let code = code.clone();
+        let error_message = error_message.clone();
+        let auth = auth.clone();
+        Callback::from(move |e: MouseEvent| {
+            e.prevent_default();
+            auth.cancel_second_factor();
+            code.set("".to_string());
+            error_message.set("".to_string());
+            second_factor.set(false);
+        })
+    };
+
+    // Sends the browser to the identity provider, if the app offers one. The `remember` choice
+    // and the page to return to are kept until it comes back.
+    let issuer = default_issuer();
+    let on_sso = {
+        let issuer = issuer.clone();
+        let remember = remember.clone();
+        let error_message = error_message.clone();
+        let next = next.to_path();
+        Callback::from(move |_| {
+            let Some(issuer) = issuer.clone() else { return };
+            let remember = *remember;
+            let error_message = error_message.clone();
+            let next = next.clone();
+            wasm_bindgen_futures::spawn_local(async move {
+                if let Err(err) = oidc::begin_login(&*issuer, remember, &next).await {
+                    error!("Couldn't start single sign-on", err.to_string());
+                    error_message.set(err.to_string());
+                }
+            });
+        })
+    };
+
     let on_login = {
-        let user_state = user_state.clone();
         let email = email.clone();
         let password = password.clone();
+        let remember = remember.clone();
         let error_message = error_message.clone();
+        let retry_at = retry_at.clone();
+        let second_factor = second_factor.clone();
 
         Callback::from(move |_| {
-            // We only attempt to log in if the current user state is `Unauthorized`.
-            if let UserState::Unauthorized(manager) = &*user_state {
-                // Call the `login` method on our `UserManager`, passing in the email and password.
-                match manager.clone().login(&email, &password) {
-                    // If login is successful, we set a new user state and clear the error message.
-                    Ok(new_state) => {
-                        user_state.set(new_state);
+            let email = (*email).clone();
+            let password = (*password).clone();
+            let remember = *remember;
+            let error_message = error_message.clone();
+            let retry_at = retry_at.clone();
+            let second_factor = second_factor.clone();
+            let navigator = navigator.clone();
+            let next = next.clone();
+            let auth = auth.clone();
+
+            // The auth provider may have to ask a server, so logging in happens asynchronously.
+            wasm_bindgen_futures::spawn_local(async move {
+                // Log in through the auth context, letting the auth provider check the email and password.
+                match auth.login(&email, &password, remember).await {
+                    // The password was right, but the account also needs a code.
+                    Ok(LoginProgress::NeedsSecondFactor) => {
+                        error_message.set("".to_string());
+                        second_factor.set(true);
+                    }
+                    // If login is successful, we clear the error message.
+                    Ok(LoginProgress::LoggedIn) => {
                         error_message.set("".to_string());
                         log!("User logged in successfully!");
-                        // After successful login, navigate to the Home page.
-                        navigator.push(&Route::Home);
+                        // After successful login, go back to the page that sent us here (or Home).
+                        next.push(&navigator);
                     }
                      // If there's an error, we display it and log it to the console.
-                    Err(err) => {
-                        error_message.set(err.clone());
-                        error!("Login error", err);
-                    }
+                    Err(err) => show_login_error(err, &email, &error_message, &retry_at),
                 }
-            }
+            });
         })
     };
+
+    // Accounts with two-factor authentication get a second step asking for their code.
+    if *second_factor {
+        return html! {
+            <div style="font-family: Arial, sans-serif; padding: 20px; background-color: #f8f9fa;">
+                <div style="display: flex; justify-content: center; align-items: center; height: 100vh; background-color: #f5f5f5;">
+                    <div style="text-align: center; background: white; padding: 40px; border-radius: 8px; box-shadow: 0 4px 6px rgba(0, 0, 0, 0.1);">
+                        <h1 style="margin-bottom: 20px;">{ "Two-factor authentication" }</h1>
+                        <p style="margin-bottom: 10px; font-size: 14px; max-width: 320px;">
+                            { "Enter the code from your authenticator app, or one of your recovery codes." }
+                        </p>
+                        <input
+                            type="text"
+                            inputmode="numeric"
+                            autocomplete="one-time-code"
+                            placeholder="Code"
+                            value={(*code).clone()}
+                            oninput={Callback::from(move |e: InputEvent| code.set(e.target_unchecked_into::<HtmlInputElement>().value()))}
+                            style="padding: 8px; width: 100%; margin-bottom: 10px; border: 1px solid #ccc; border-radius: 4px;"
+                        />
+                        <button onclick={on_verify} disabled={wait.is_some()} style="padding: 10px 20px; font-size: 16px; background-color: #5cb85c; color: white; border: none; border-radius: 4px; cursor: pointer;">{ "Verify" }</button>
+                        {
+                            match wait {
+                                Some(wait) => html! {
+                                    <p style="color: #b45309; margin-top: 10px; max-width: 320px;">
+                                        { format!("Too many failed attempts. You can try again in {}.", describe_wait(wait)) }
+                                    </p>
+                                },
+                                None => html! {},
+                            }
+                        }
+                        {
+                            if !(*error_message).is_empty() {
+                                html! { <p style="color: red; margin-top: 10px;">{ &*error_message }</p> }
+                            } else {
+                                html! {}
+                            }
+                        }
+                        <p style="margin-top: 20px; font-size: 14px;">
+                            <a href="#" onclick={on_cancel_code}>{ "Back to logging in" }</a>
+                        </p>
+                    </div>
+                </div>
+            </div>
+        };
+    }
+
 // The HTML (using Yew's JSX-like syntax) that we render for the login page.
     html! {
         <div style="font-family: Arial, sans-serif; padding: 20px; background-color: #f8f9fa;">
             <div style="display: flex; justify-content: center; align-items: center; height: 100vh; background-color: #f5f5f5;">
                 <div style="text-align: center; background: white; padding: 40px; border-radius: 8px; box-shadow: 0 4px 6px rgba(0, 0, 0, 0.1);">
-                    <h1 style="margin-bottom: 20px;">{ "Log in" }</h1>
+                    <h1 style="margin-bottom: 20px;">{ if *forgot { "Reset your password" } else { "Log in" } }</h1>
                     <input
                         type="email"
                         placeholder="Email"
@@ -92,14 +421,63 @@ pub fn LoginPage() -> Html {
                         oninput={Callback::from(move |e: InputEvent| email.set(e.target_unchecked_into::<HtmlInputElement>().value()))}
                         style="padding: 8px; width: 100%; margin-bottom: 10px; border: 1px solid #ccc; border-radius: 4px;"
                     />
-                    <input
-                        type="password"
-                        placeholder="Password"
-                        value={(*password).clone()}
-                        oninput={Callback::from(move |e: InputEvent| password.set(e.target_unchecked_into::<HtmlInputElement>().value()))}
-                        style="padding: 8px; width: 100%; margin-bottom: 10px; border: 1px solid #ccc; border-radius: 4px;"
-                    />
-                    <button onclick={on_login} style="padding: 10px 20px; font-size: 16px; background-color: #5cb85c; color: white; border: none; border-radius: 4px; cursor: pointer;">{ "Log in" }</button>
+                    {
+                        if *forgot {
+                            html! {
+                                <>
+                                    <button onclick={on_reset} style="padding: 10px 20px; font-size: 16px; background-color: #5cb85c; color: white; border: none; border-radius: 4px; cursor: pointer;">{ "Send reset link" }</button>
+                                    {
+                                        if !(*reset_message).is_empty() {
+                                            html! { <p style="color: green; margin-top: 10px; max-width: 320px;">{ &*reset_message }</p> }
+                                        } else {
+                                            html! {}
+                                        }
+                                    }
+                                </>
+                            }
+                        } else {
+                            html! {
+                                <>
+                                    <input
+                                        type="password"
+                                        placeholder="Password"
+                                        value={(*password).clone()}
+                                        oninput={Callback::from(move |e: InputEvent| password.set(e.target_unchecked_into::<HtmlInputElement>().value()))}
+                                        style="padding: 8px; width: 100%; margin-bottom: 10px; border: 1px solid #ccc; border-radius: 4px;"
+                                    />
+                                    <label style="display: block; margin-bottom: 10px; font-size: 14px; text-align: left;">
+                                        <input
+                                            type="checkbox"
+                                            checked={*remember}
+                                            onchange={Callback::from(move |e: Event| remember.set(e.target_unchecked_into::<HtmlInputElement>().checked()))}
+                                            style="margin-right: 6px;"
+                                        />
+                                        { "Remember me" }
+                                    </label>
+                                    <button onclick={on_login} disabled={wait.is_some()} style="padding: 10px 20px; font-size: 16px; background-color: #5cb85c; color: white; border: none; border-radius: 4px; cursor: pointer;">{ "Log in" }</button>
+                                    {
+                                        if issuer.is_some() {
+                                            html! {
+                                                <button onclick={on_sso} style="display: block; margin: 10px auto 0; padding: 10px 20px; font-size: 16px; background-color: white; color: #333; border: 1px solid #ccc; border-radius: 4px; cursor: pointer;">{ "Log in with single sign-on" }</button>
+                                            }
+                                        } else {
+                                            html! {}
+                                        }
+                                    }
+                                    {
+                                        match wait {
+                                            Some(wait) => html! {
+                                                <p style="color: #b45309; margin-top: 10px; max-width: 320px;">
+                                                    { format!("Too many failed login attempts. You can try again in {}.", describe_wait(wait)) }
+                                                </p>
+                                            },
+                                            None => html! {},
+                                        }
+                                    }
+                                </>
+                            }
+                        }
+                    }
                     {
                         if !(*error_message).is_empty() {
                             html! { <p style="color: red; margin-top: 10px;">{ &*error_message }</p> }
@@ -107,8 +485,47 @@ pub fn LoginPage() -> Html {
                             html! {}
                         }
                     }
+                    <p style="margin-top: 20px; font-size: 14px;">
+                        <a href="#" onclick={on_forgot.reform(|e: MouseEvent| e.prevent_default())}>
+                            { if *forgot { "Back to logging in" } else { "Forgot your password?" } }
+                        </a>
+                    </p>
+                    <p style="margin-top: 10px; font-size: 14px;">
+                        { "New here? " }
+                        <Link<Route> to={Route::Register}>{ "Create an account" }</Link<Route>>
+                    </p>
                 </div>
             </div>
         </div>
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn next(path: &str) -> ReturnTo {
+        return_route(&LoginQuery {
+            next: Some(path.to_string()),
+        })
+    }
+
+    #[test]
+    fn keeps_the_query_of_the_page() {
+        let search = next("/search?q=rust+ownership");
+        assert!(search.route == Route::Search);
+        assert_eq!(
+            search.query,
+            vec![("q".to_string(), "rust ownership".to_string())]
+        );
+        assert_eq!(search.to_path(), "/search?q=rust%20ownership");
+        assert_eq!(next("/?page=3").to_path(), "/?page=3");
+    }
+
+    #[test]
+    fn only_returns_to_our_own_pages() {
+        for path in ["https://example.com/", "//example.com/", "/login?next=/", "/no/such/page"] {
+            assert_eq!(next(path).to_path(), "/");
+        }
+    }
+}
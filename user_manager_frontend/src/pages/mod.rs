@@ -16,3 +16,46 @@ pub use login::LoginPage;
 mod article;
 // Re-export the `ArticlePage` from the `article` module.
 pub use article::ArticlePage;
+
+// Declare a module named `register` in the file "register.rs".
+mod register;
+// Re-export the `RegisterPage` used to sign up for a new account.
+pub use register::RegisterPage;
+
+// Declare a module named `form` in the file "form.rs", with the input helpers shared by the forms.
+mod form;
+
+// Declare a module named `admin_users` in the file "admin_users.rs".
+mod admin_users;
+// Re-export the `AdminUsersPage` listing every account, and the `UserFormPage` to create or edit one.
+pub use admin_users::{AdminUsersPage, UserFormPage};
+
+// Declare a module named `profile` in the file "profile.rs".
+mod profile;
+// Re-export the `ProfilePage` where users see and correct their own details.
+pub use profile::ProfilePage;
+
+// Declare a module named `reset_password` in the file "reset_password.rs".
+mod reset_password;
+// Re-export the `ResetPasswordPage` that password reset links lead to.
+pub use reset_password::ResetPasswordPage;
+
+// Declare a module named `auth_callback` in the file "auth_callback.rs".
+mod auth_callback;
+// Re-export the `AuthCallbackPage` the identity provider sends the browser back to after single sign-on.
+pub use auth_callback::AuthCallbackPage;
+
+// Declare a module named `search` in the file "search.rs".
+mod search;
+// Re-export the `SearchPage` that searches the articles' titles and text.
+pub use search::SearchPage;
+
+// Declare a module named `topic` in the file "topic.rs".
+mod topic;
+// Re-export the `TopicPage` listing the posts with a tag or in a category, and the `Topic` it lists.
+pub use topic::{Topic, TopicPage};
+
+// Declare a module named `author` in the file "author.rs".
+mod author;
+// Re-export the `AuthorPage` with an author's bio and posts.
+pub use author::AuthorPage;
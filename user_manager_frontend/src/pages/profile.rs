@@ -0,0 +1,498 @@
+use chrono::NaiveDate;
+use gloo_console::{error, log};
+use yew::{function_component, html, use_effect_with, use_state, Callback, Html};
+use yew_router::prelude::*;
+
+use super::form::{bind, field_error, INPUT_STYLE};
+use crate::{
+    app::Route,
+    helpers::{
+        auth::default_provider,
+        auth_context::use_auth,
+        totp::TotpSetup,
+        user_manager::RegistrationError,
+        validation::{UserField, ValidationErrors},
+    },
+};
+
+// One labelled line of the profile.
+fn detail(label: &str, value: String) -> Html {
+    html! {
+        <div class="flex justify-between border-b border-gray-200 py-2">
+            <span class="text-gray-600">{ label }</span>
+            <span class="text-gray-900 font-medium">{ value }</span>
+        </div>
+    }
+}
+
+// Shows the logged-in user's details, with a form to correct their name, email and birthday
+// and one to change their password.
+#[function_component]
+pub fn ProfilePage() -> Html {
+    let auth = use_auth();
+    let user = auth.user();
+
+    // Whether the form is shown instead of the details.
+    let editing = use_state(|| false);
+    let name = use_state(|| "".to_string());
+    let email = use_state(|| "".to_string());
+    let birthday = use_state(|| "".to_string());
+
+    // Errors for individual fields, and one for problems that aren't about a single field.
+    let field_errors = use_state(ValidationErrors::default);
+    let error_message = use_state(|| "".to_string());
+
+    // Start the form from the current details whenever it is opened.
+    {
+        let name = name.clone();
+        let email = email.clone();
+        let birthday = birthday.clone();
+        let user = user.clone();
+        use_effect_with(*editing, move |editing| {
+            if let (true, Some(user)) = (*editing, user) {
+                name.set(user.get_name().to_string());
+                email.set(user.get_email().to_string());
+                birthday.set(user.get_birthday().format("%Y-%m-%d").to_string());
+            }
+            || ()
+        });
+    }
+
+    let on_edit = {
+        let editing = editing.clone();
+        let field_errors = field_errors.clone();
+        let error_message = error_message.clone();
+        Callback::from(move |_| {
+            field_errors.set(ValidationErrors::default());
+            error_message.set("".to_string());
+            editing.set(!*editing);
+        })
+    };
+
+    let on_save = {
+        let editing = editing.clone();
+        let name = name.clone();
+        let email = email.clone();
+        let birthday = birthday.clone();
+        let field_errors = field_errors.clone();
+        let error_message = error_message.clone();
+
+        Callback::from(move |_| {
+            let name = (*name).clone();
+            let email = (*email).clone();
+            // `<input type="date">` gives us an ISO date, or an empty string if nothing valid was picked.
+            let birthday = NaiveDate::parse_from_str(&birthday, "%Y-%m-%d").ok();
+            let editing = editing.clone();
+            let field_errors = field_errors.clone();
+            let error_message = error_message.clone();
+            let auth = auth.clone();
+
+            wasm_bindgen_futures::spawn_local(async move {
+                match auth.update_profile(&name, &email, birthday).await {
+                    Ok(()) => {
+                        log!("Profile saved.");
+                        field_errors.set(ValidationErrors::default());
+                        error_message.set("".to_string());
+                        editing.set(false);
+                    }
+                    Err(RegistrationError::Invalid(errors)) => {
+                        field_errors.set(errors);
+                        error_message.set("".to_string());
+                    }
+                    Err(RegistrationError::Auth(err)) => {
+                        field_errors.set(ValidationErrors::default());
+                        error_message.set(err.to_string());
+                        error!("Couldn't save the profile", err.to_string());
+                    }
+                }
+            });
+        })
+    };
+
+    let Some(user) = user else {
+        // `RequireAuth` only lets logged-in users in; the account follows once the provider has confirmed it.
+        return html! { <p class="text-center text-gray-600 my-8">{ "Loading profile..." }</p> };
+    };
+
+    html! {
+        <>
+            <div class="max-w-xl mx-auto my-8 p-6 bg-white shadow-md rounded-lg">
+                <h1 class="text-3xl text-gray-800 font-bold mb-4">{ "Your profile" }</h1>
+                {
+                    if *editing {
+                        html! {
+                            <>
+                                <label class="block text-sm text-gray-600">{ "Full name" }</label>
+                                <input
+                                    type="text"
+                                    value={(*name).clone()}
+                                    oninput={bind(&name)}
+                                    style={INPUT_STYLE}
+                                />
+                                { field_error(&field_errors, UserField::Name) }
+                                <label class="block text-sm text-gray-600">{ "Email" }</label>
+                                <input
+                                    type="email"
+                                    value={(*email).clone()}
+                                    oninput={bind(&email)}
+                                    style={INPUT_STYLE}
+                                />
+                                { field_error(&field_errors, UserField::Email) }
+                                <label class="block text-sm text-gray-600">{ "Birthday" }</label>
+                                <input
+                                    type="date"
+                                    value={(*birthday).clone()}
+                                    oninput={bind(&birthday)}
+                                    style={INPUT_STYLE}
+                                />
+                                { field_error(&field_errors, UserField::Birthday) }
+                                <div class="flex gap-4 items-center mt-4">
+                                    <button
+                                        onclick={on_save}
+                                        class="px-4 py-2 bg-[#5cb85c] text-white font-semibold rounded-lg hover:shadow-md"
+                                    >
+                                        { "Save" }
+                                    </button>
+                                    <button onclick={on_edit} class="text-blue-600 hover:underline">
+                                        { "Cancel" }
+                                    </button>
+                                </div>
+                            </>
+                        }
+                    } else {
+                        html! {
+                            <>
+                                { detail("Username", user.get_username().to_string()) }
+                                { detail("Name", user.get_name().to_string()) }
+                                { detail("Email", user.get_email().to_string()) }
+                                { detail("Birthday", user.get_birthday().format("%Y-%m-%d").to_string()) }
+                                { detail("Role", user.role().to_string()) }
+                                <div class="flex gap-4 items-center mt-4">
+                                    <button
+                                        onclick={on_edit}
+                                        class="px-4 py-2 bg-blue-500 text-white font-semibold rounded-lg hover:bg-blue-600"
+                                    >
+                                        { "Edit profile" }
+                                    </button>
+                                    <Link<Route> to={Route::Home} classes="text-blue-600 hover:underline">
+                                        { "Back to the front page" }
+                                    </Link<Route>>
+                                </div>
+                            </>
+                        }
+                    }
+                }
+                {
+                    if !(*error_message).is_empty() {
+                        html! { <p class="text-red-700 mt-4">{ &*error_message }</p> }
+                    } else {
+                        html! {}
+                    }
+                }
+            </div>
+            <ChangePasswordForm />
+            <TwoFactorSettings />
+        </>
+    }
+}
+
+// Lets the logged-in user pick a new password after confirming the current one.
+#[function_component]
+fn ChangePasswordForm() -> Html {
+    let auth = use_auth();
+    let current_password = use_state(|| "".to_string());
+    let new_password = use_state(|| "".to_string());
+    let field_errors = use_state(ValidationErrors::default);
+    // Problems that aren't about a single field, or a note that it worked.
+    let message = use_state(|| None::<Result<String, String>>);
+
+    let on_change = {
+        let current_password = current_password.clone();
+        let new_password = new_password.clone();
+        let field_errors = field_errors.clone();
+        let message = message.clone();
+
+        Callback::from(move |_| {
+            let current = (*current_password).clone();
+            let new = (*new_password).clone();
+            let current_password = current_password.clone();
+            let new_password = new_password.clone();
+            let field_errors = field_errors.clone();
+            let message = message.clone();
+            let auth = auth.clone();
+
+            wasm_bindgen_futures::spawn_local(async move {
+                match auth.change_password(&current, &new).await {
+                    Ok(()) => {
+                        current_password.set("".to_string());
+                        new_password.set("".to_string());
+                        field_errors.set(ValidationErrors::default());
+                        message.set(Some(Ok("Your password has been changed.".to_string())));
+                    }
+                    Err(RegistrationError::Invalid(errors)) => {
+                        field_errors.set(errors);
+                        message.set(None);
+                    }
+                    Err(RegistrationError::Auth(err)) => {
+                        field_errors.set(ValidationErrors::default());
+                        message.set(Some(Err(err.to_string())));
+                        error!("Couldn't change the password", err.to_string());
+                    }
+                }
+            });
+        })
+    };
+
+    html! {
+        <div class="max-w-xl mx-auto my-8 p-6 bg-white shadow-md rounded-lg">
+            <h2 class="text-xl text-gray-800 font-semibold mb-4">{ "Change password" }</h2>
+            <input
+                type="password"
+                placeholder="Current password"
+                value={(*current_password).clone()}
+                oninput={bind(&current_password)}
+                style={INPUT_STYLE}
+            />
+            { field_error(&field_errors, UserField::CurrentPassword) }
+            <input
+                type="password"
+                placeholder="New password"
+                value={(*new_password).clone()}
+                oninput={bind(&new_password)}
+                style={INPUT_STYLE}
+            />
+            { field_error(&field_errors, UserField::Password) }
+            <button
+                onclick={on_change}
+                class="mt-2 px-4 py-2 bg-blue-500 text-white font-semibold rounded-lg hover:bg-blue-600"
+            >
+                { "Change password" }
+            </button>
+            {
+                match &*message {
+                    Some(Ok(note)) => html! { <p class="text-green-700 mt-4">{ note }</p> },
+                    Some(Err(problem)) => html! { <p class="text-red-700 mt-4">{ problem }</p> },
+                    None => html! {},
+                }
+            }
+        </div>
+    }
+}
+
+// Where turning on two-factor authentication has got to.
+#[derive(Clone, PartialEq)]
+enum TwoFactorStep {
+    // Still asking the provider whether it is on.
+    Loading,
+    Off,
+    // A new secret has been handed out and waits for a code to confirm it.
+    Confirming(TotpSetup),
+    // Just turned on. The recovery codes are only ever shown here.
+    RecoveryCodes(Vec<String>),
+    On,
+}
+
+// Lets the logged-in user turn two-factor authentication with an authenticator app on or off.
+#[function_component]
+fn TwoFactorSettings() -> Html {
+    let auth = use_auth();
+    let user = auth.user();
+    let step = use_state(|| TwoFactorStep::Loading);
+    let code = use_state(|| "".to_string());
+    let error_message = use_state(|| "".to_string());
+
+    // Ask the provider whether two-factor authentication is on once the account is known.
+    {
+        let step = step.clone();
+        let error_message = error_message.clone();
+        use_effect_with(user.clone(), move |user| {
+            if let Some(user) = user.clone() {
+                wasm_bindgen_futures::spawn_local(async move {
+                    match user.two_factor_enabled(&*default_provider()).await {
+                        Ok(true) => step.set(TwoFactorStep::On),
+                        Ok(false) => step.set(TwoFactorStep::Off),
+                        Err(err) => {
+                            error_message.set(err.to_string());
+                            error!("Couldn't check two-factor authentication", err.to_string());
+                        }
+                    }
+                });
+            }
+            || ()
+        });
+    }
+
+    let on_begin = {
+        let user = user.clone();
+        let step = step.clone();
+        let code = code.clone();
+        let error_message = error_message.clone();
+        Callback::from(move |_| {
+            let Some(user) = user.clone() else { return };
+            let step = step.clone();
+            let code = code.clone();
+            let error_message = error_message.clone();
+            wasm_bindgen_futures::spawn_local(async move {
+                match user.begin_two_factor_setup(&*default_provider()).await {
+                    Ok(setup) => {
+                        code.set("".to_string());
+                        error_message.set("".to_string());
+                        step.set(TwoFactorStep::Confirming(setup));
+                    }
+                    Err(err) => {
+                        error_message.set(err.to_string());
+                        error!("Couldn't set up two-factor authentication", err.to_string());
+                    }
+                }
+            });
+        })
+    };
+
+    let on_confirm = {
+        let user = user.clone();
+        let step = step.clone();
+        let code = code.clone();
+        let error_message = error_message.clone();
+        Callback::from(move |_| {
+            let Some(user) = user.clone() else { return };
+            let step = step.clone();
+            let code = code.clone();
+            let error_message = error_message.clone();
+            wasm_bindgen_futures::spawn_local(async move {
+                match user.confirm_two_factor(&*default_provider(), &code).await {
+                    Ok(recovery_codes) => {
+                        error_message.set("".to_string());
+                        step.set(TwoFactorStep::RecoveryCodes(recovery_codes));
+                    }
+                    Err(err) => error_message.set(err.to_string()),
+                }
+                code.set("".to_string());
+            });
+        })
+    };
+
+    let on_disable = {
+        let user = user.clone();
+        let step = step.clone();
+        let code = code.clone();
+        let error_message = error_message.clone();
+        Callback::from(move |_| {
+            let Some(user) = user.clone() else { return };
+            let step = step.clone();
+            let code = code.clone();
+            let error_message = error_message.clone();
+            wasm_bindgen_futures::spawn_local(async move {
+                match user.disable_two_factor(&*default_provider(), &code).await {
+                    Ok(()) => {
+                        error_message.set("".to_string());
+                        step.set(TwoFactorStep::Off);
+                    }
+                    Err(err) => error_message.set(err.to_string()),
+                }
+                code.set("".to_string());
+            });
+        })
+    };
+
+    let on_done = {
+        let step = step.clone();
+        Callback::from(move |_| step.set(TwoFactorStep::On))
+    };
+
+    let code_input = html! {
+        <input
+            type="text"
+            inputmode="numeric"
+            autocomplete="one-time-code"
+            placeholder="Code"
+            value={(*code).clone()}
+            oninput={bind(&code)}
+            style={INPUT_STYLE}
+        />
+    };
+
+    html! {
+        <div class="max-w-xl mx-auto my-8 p-6 bg-white shadow-md rounded-lg">
+            <h2 class="text-xl text-gray-800 font-semibold mb-4">{ "Two-factor authentication" }</h2>
+            {
+                match &*step {
+                    TwoFactorStep::Loading => html! { <p class="text-gray-600">{ "Checking..." }</p> },
+                    TwoFactorStep::Off => html! {
+                        <>
+                            <p class="text-gray-600 mb-4">
+                                { "Ask for a code from an authenticator app after your password when logging in." }
+                            </p>
+                            <button
+                                onclick={on_begin}
+                                class="px-4 py-2 bg-blue-500 text-white font-semibold rounded-lg hover:bg-blue-600"
+                            >
+                                { "Set up two-factor authentication" }
+                            </button>
+                        </>
+                    },
+                    TwoFactorStep::Confirming(setup) => html! {
+                        <>
+                            <p class="text-gray-600 mb-2">
+                                { "Add this account to your authenticator app, then enter the code it shows." }
+                            </p>
+                            <p class="mb-2">
+                                <a href={setup.otpauth_uri.clone()} class="text-blue-600 hover:underline">
+                                    { "Open in authenticator app" }
+                                </a>
+                            </p>
+                            <p class="text-gray-600 mb-4">
+                                { "Or enter the key by hand: " }
+                                <code class="font-mono break-all">{ &setup.secret }</code>
+                            </p>
+                            { code_input }
+                            <button
+                                onclick={on_confirm}
+                                class="mt-2 px-4 py-2 bg-blue-500 text-white font-semibold rounded-lg hover:bg-blue-600"
+                            >
+                                { "Turn on" }
+                            </button>
+                        </>
+                    },
+                    TwoFactorStep::RecoveryCodes(recovery_codes) => html! {
+                        <>
+                            <p class="text-gray-600 mb-2">
+                                { "Two-factor authentication is on. Keep these recovery codes somewhere safe. \
+                                   Each one can be used once instead of a code, and they won't be shown again." }
+                            </p>
+                            <ul class="font-mono grid grid-cols-2 gap-1 mb-4">
+                                { for recovery_codes.iter().map(|code| html! { <li>{ code }</li> }) }
+                            </ul>
+                            <button
+                                onclick={on_done}
+                                class="px-4 py-2 bg-blue-500 text-white font-semibold rounded-lg hover:bg-blue-600"
+                            >
+                                { "I have saved them" }
+                            </button>
+                        </>
+                    },
+                    TwoFactorStep::On => html! {
+                        <>
+                            <p class="text-gray-600 mb-4">
+                                { "Two-factor authentication is on. Enter a code to turn it off." }
+                            </p>
+                            { code_input }
+                            <button
+                                onclick={on_disable}
+                                class="mt-2 px-4 py-2 bg-red-500 text-white font-semibold rounded-lg hover:bg-red-600"
+                            >
+                                { "Turn off" }
+                            </button>
+                        </>
+                    },
+                }
+            }
+            {
+                if !(*error_message).is_empty() {
+                    html! { <p class="text-red-700 mt-4">{ &*error_message }</p> }
+                } else {
+                    html! {}
+                }
+            }
+        </div>
+    }
+}
@@ -0,0 +1,157 @@
+use chrono::NaiveDate;
+use gloo_console::{error, log};
+use yew::{function_component, html, use_state, Callback, Html};
+use yew_router::prelude::*;
+
+use super::form::{bind, field_error, INPUT_STYLE};
+use crate::{
+    app::Route,
+    helpers::{
+        auth::default_provider,
+        auth_context::use_auth,
+        user_manager::{RegistrationError, UserManager},
+        validation::{UserField, ValidationErrors},
+    },
+};
+
+// A sign-up form that creates an account through the auth provider and then logs the new user in.
+#[function_component]
+pub fn RegisterPage() -> Html {
+    let navigator = use_navigator().expect("Couldn't get the navigator");
+    let auth = use_auth();
+
+    // Someone who is already logged in has no business here.
+    if auth.is_logged_in() {
+        log!("Already logged in");
+        navigator.replace(&Route::Home);
+    }
+
+    // One piece of state per input field.
+    let username = use_state(|| "".to_string());
+    let email = use_state(|| "".to_string());
+    let password = use_state(|| "".to_string());
+    let name = use_state(|| "".to_string());
+    let birthday = use_state(|| "".to_string());
+
+    // Errors for individual fields, and one for problems that aren't about a single field.
+    let field_errors = use_state(ValidationErrors::default);
+    let error_message = use_state(|| "".to_string());
+
+    let on_register = {
+        let username = username.clone();
+        let email = email.clone();
+        let password = password.clone();
+        let name = name.clone();
+        let birthday = birthday.clone();
+        let field_errors = field_errors.clone();
+        let error_message = error_message.clone();
+
+        Callback::from(move |_| {
+            let username = (*username).clone();
+            let email = (*email).clone();
+            let password = (*password).clone();
+            let name = (*name).clone();
+            // `<input type="date">` gives us an ISO date, or an empty string if nothing valid was picked.
+            let birthday = NaiveDate::parse_from_str(&birthday, "%Y-%m-%d").ok();
+            let field_errors = field_errors.clone();
+            let error_message = error_message.clone();
+            let navigator = navigator.clone();
+            let auth = auth.clone();
+
+            wasm_bindgen_futures::spawn_local(async move {
+                let result = UserManager::register(
+                    &*default_provider(),
+                    &username,
+                    &email,
+                    &password,
+                    &name,
+                    birthday,
+                )
+                .await;
+
+                match result {
+                    Ok(()) => {
+                        field_errors.set(ValidationErrors::default());
+                        error_message.set("".to_string());
+                        // Log the new user straight in, for this tab only. If that fails they can still use the login page.
+                        match auth.login(email.trim(), &password, false).await {
+                            Ok(_) => navigator.push(&Route::Home),
+                            Err(_) => navigator.push(&Route::Login),
+                        }
+                    }
+                    Err(RegistrationError::Invalid(errors)) => {
+                        field_errors.set(errors);
+                        error_message.set("".to_string());
+                    }
+                    Err(RegistrationError::Auth(err)) => {
+                        field_errors.set(ValidationErrors::default());
+                        error_message.set(err.to_string());
+                        error!("Registration error", err.to_string());
+                    }
+                }
+            });
+        })
+    };
+
+    html! {
+        <div style="font-family: Arial, sans-serif; padding: 20px; background-color: #f8f9fa;">
+            <div style="display: flex; justify-content: center; align-items: center; min-height: 100vh; background-color: #f5f5f5;">
+                <div style="text-align: center; background: white; padding: 40px; border-radius: 8px; box-shadow: 0 4px 6px rgba(0, 0, 0, 0.1); width: 100%; max-width: 360px;">
+                    <h1 style="margin-bottom: 20px;">{ "Create an account" }</h1>
+                    <input
+                        type="text"
+                        placeholder="Username"
+                        value={(*username).clone()}
+                        oninput={bind(&username)}
+                        style={INPUT_STYLE}
+                    />
+                    { field_error(&field_errors, UserField::Username) }
+                    <input
+                        type="email"
+                        placeholder="Email"
+                        value={(*email).clone()}
+                        oninput={bind(&email)}
+                        style={INPUT_STYLE}
+                    />
+                    { field_error(&field_errors, UserField::Email) }
+                    <input
+                        type="password"
+                        placeholder="Password"
+                        value={(*password).clone()}
+                        oninput={bind(&password)}
+                        style={INPUT_STYLE}
+                    />
+                    { field_error(&field_errors, UserField::Password) }
+                    <input
+                        type="text"
+                        placeholder="Full name"
+                        value={(*name).clone()}
+                        oninput={bind(&name)}
+                        style={INPUT_STYLE}
+                    />
+                    { field_error(&field_errors, UserField::Name) }
+                    <label style="display: block; text-align: left; font-size: 14px; color: #555;">{ "Birthday" }</label>
+                    <input
+                        type="date"
+                        value={(*birthday).clone()}
+                        oninput={bind(&birthday)}
+                        style={INPUT_STYLE}
+                    />
+                    { field_error(&field_errors, UserField::Birthday) }
+                    <button onclick={on_register} style="padding: 10px 20px; font-size: 16px; background-color: #5cb85c; color: white; border: none; border-radius: 4px; cursor: pointer;">{ "Sign up" }</button>
+                    {
+                        if !(*error_message).is_empty() {
+                            html! { <p style="color: red; margin-top: 10px;">{ &*error_message }</p> }
+                        } else {
+                            html! {}
+                        }
+                    }
+                    <p style="margin-top: 20px; font-size: 14px;">
+                        { "Already have an account? " }
+                        <Link<Route> to={Route::Login}>{ "Log in" }</Link<Route>>
+                    </p>
+                </div>
+            </div>
+        </div>
+    }
+}
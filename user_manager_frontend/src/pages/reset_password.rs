@@ -0,0 +1,90 @@
+use gloo_console::{error, log};
+use yew::{function_component, html, use_state, Callback, Html, Properties};
+use yew_router::prelude::*;
+
+use super::form::{bind, field_error, INPUT_STYLE};
+use crate::{
+    app::Route,
+    helpers::{
+        auth::default_provider,
+        user_manager::{RegistrationError, UserManager},
+        validation::{UserField, ValidationErrors},
+    },
+};
+
+#[derive(PartialEq, Properties)]
+pub struct ResetPasswordPageProps {
+    // The token from the reset link.
+    pub token: String,
+}
+
+// Where password reset links lead: lets the user pick a new password, then sends them to log in.
+#[function_component]
+pub fn ResetPasswordPage(props: &ResetPasswordPageProps) -> Html {
+    let navigator = use_navigator().expect("Couldn't get the navigator");
+    let password = use_state(|| "".to_string());
+    let field_errors = use_state(ValidationErrors::default);
+    let error_message = use_state(|| "".to_string());
+
+    let on_reset = {
+        let token = props.token.clone();
+        let password = password.clone();
+        let field_errors = field_errors.clone();
+        let error_message = error_message.clone();
+
+        Callback::from(move |_| {
+            let token = token.clone();
+            let password = (*password).clone();
+            let field_errors = field_errors.clone();
+            let error_message = error_message.clone();
+            let navigator = navigator.clone();
+
+            wasm_bindgen_futures::spawn_local(async move {
+                match UserManager::reset_password(&*default_provider(), &token, &password).await {
+                    Ok(()) => {
+                        log!("Password reset, sending the user to log in.");
+                        navigator.push(&Route::Login);
+                    }
+                    Err(RegistrationError::Invalid(errors)) => {
+                        field_errors.set(errors);
+                        error_message.set("".to_string());
+                    }
+                    Err(RegistrationError::Auth(err)) => {
+                        field_errors.set(ValidationErrors::default());
+                        error_message.set(err.to_string());
+                        error!("Password reset error", err.to_string());
+                    }
+                }
+            });
+        })
+    };
+
+    html! {
+        <div style="font-family: Arial, sans-serif; padding: 20px; background-color: #f8f9fa;">
+            <div style="display: flex; justify-content: center; align-items: center; height: 100vh; background-color: #f5f5f5;">
+                <div style="text-align: center; background: white; padding: 40px; border-radius: 8px; box-shadow: 0 4px 6px rgba(0, 0, 0, 0.1); width: 100%; max-width: 360px;">
+                    <h1 style="margin-bottom: 20px;">{ "Choose a new password" }</h1>
+                    <input
+                        type="password"
+                        placeholder="New password"
+                        value={(*password).clone()}
+                        oninput={bind(&password)}
+                        style={INPUT_STYLE}
+                    />
+                    { field_error(&field_errors, UserField::Password) }
+                    <button onclick={on_reset} style="padding: 10px 20px; font-size: 16px; background-color: #5cb85c; color: white; border: none; border-radius: 4px; cursor: pointer;">{ "Set password" }</button>
+                    {
+                        if !(*error_message).is_empty() {
+                            html! { <p style="color: red; margin-top: 10px;">{ &*error_message }</p> }
+                        } else {
+                            html! {}
+                        }
+                    }
+                    <p style="margin-top: 20px; font-size: 14px;">
+                        <Link<Route> to={Route::Login}>{ "Back to logging in" }</Link<Route>>
+                    </p>
+                </div>
+            </div>
+        </div>
+    }
+}
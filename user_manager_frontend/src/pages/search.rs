@@ -0,0 +1,269 @@
+use gloo_console::error;
+use gloo_timers::callback::Timeout;
+use web_sys::HtmlInputElement;
+use yew::{
+    function_component, html, use_effect_with, use_state, Callback, Html, InputEvent, TargetCast,
+};
+use yew_router::prelude::*;
+
+use crate::{
+    app::{Route, SearchQuery},
+    components::{ErrorPanel, PostCard, POST_GRID_CLASSES},
+    helpers::{
+        content::{default_source, search_terms, SearchResult},
+        fetch::{use_fetch, FetchError, FetchState},
+    },
+};
+
+// How long typing has to pause before the search runs.
+const DEBOUNCE_MS: u32 = 300;
+// Shorter searches match too much to be useful.
+const MIN_QUERY_LENGTH: usize = 2;
+const MAX_RESULTS: u32 = 30;
+// Characters of context shown before and after the first match in a snippet.
+const SNIPPET_BEFORE: usize = 60;
+const SNIPPET_AFTER: usize = 140;
+
+async fn search_posts(query: String) -> Result<Vec<SearchResult>, FetchError> {
+    let results = default_source().search(&query, MAX_RESULTS).await;
+    if let Err(err) = &results {
+        error!("Couldn't search the posts", err.to_string());
+    }
+    results
+}
+
+// Where `terms` occur in `text`, ignoring case, as non-overlapping char ranges.
+fn find_matches(text: &[char], terms: &[Vec<char>]) -> Vec<(usize, usize)> {
+    let same = |a: char, b: char| a.to_lowercase().eq(b.to_lowercase());
+    let mut matches = Vec::new();
+    let mut start = 0;
+    while start < text.len() {
+        let found = terms.iter().find(|term| {
+            !term.is_empty()
+                && text.len() - start >= term.len()
+                && term
+                    .iter()
+                    .enumerate()
+                    .all(|(i, c)| same(text[start + i], *c))
+        });
+        match found {
+            Some(term) => {
+                matches.push((start, start + term.len()));
+                start += term.len();
+            }
+            None => start += 1,
+        }
+    }
+    matches
+}
+
+// `text` with the matches of `terms` marked.
+fn highlight(text: &[char], terms: &[Vec<char>]) -> Html {
+    let mut parts = Vec::new();
+    let mut shown = 0;
+    for (start, end) in find_matches(text, terms) {
+        parts.push(html! { { text[shown..start].iter().collect::<String>() } });
+        parts.push(html! {
+            <mark class="bg-yellow-200 rounded-sm">{ text[start..end].iter().collect::<String>() }</mark>
+        });
+        shown = end;
+    }
+    parts.push(html! { { text[shown..].iter().collect::<String>() } });
+    html! { for parts }
+}
+
+// The char range of `text` around the first match of `terms`, or of its beginning if there is
+// none (the title matched). Whole words only.
+fn snippet_range(text: &[char], terms: &[Vec<char>]) -> (usize, usize) {
+    let first = find_matches(text, terms)
+        .first()
+        .map_or(0, |(start, _)| *start);
+    let mut start = first.saturating_sub(SNIPPET_BEFORE);
+    let mut end = (first + SNIPPET_AFTER).min(text.len());
+    // Don't cut words in half.
+    while start > 0 && !text[start - 1].is_whitespace() {
+        start -= 1;
+    }
+    while end < text.len() && !text[end].is_whitespace() {
+        end += 1;
+    }
+    (start, end)
+}
+
+// A piece of `text` around the first match of `terms`, with the matches marked.
+fn snippet(text: &str, terms: &[Vec<char>]) -> Html {
+    let text: Vec<char> = text
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .chars()
+        .collect();
+    let (start, end) = snippet_range(&text, terms);
+
+    html! {
+        <p class="text-sm text-gray-700 mb-4">
+            { if start > 0 { "…" } else { "" } }
+            { highlight(&text[start..end], terms) }
+            { if end < text.len() { "…" } else { "" } }
+        </p>
+    }
+}
+
+// Searches the titles and text of all posts. The search is kept in the URL (`/search?q=`),
+// so results can be shared and survive a reload.
+#[function_component]
+pub fn SearchPage() -> Html {
+    let navigator = use_navigator().expect("Couldn't get the navigator");
+    let location = use_location().expect("Couldn't get the location");
+    let query = location
+        .query::<SearchQuery>()
+        .unwrap_or_default()
+        .q
+        .unwrap_or_default()
+        .trim()
+        .to_string();
+    // What is in the search box, which runs ahead of the URL while the user types.
+    let text = use_state(|| query.clone());
+
+    // Follow the URL when it changes without typing, e.g. going back.
+    {
+        let text = text.clone();
+        use_effect_with(query.clone(), move |query| {
+            if text.trim() != query {
+                text.set(query.clone());
+            }
+            || ()
+        });
+    }
+
+    // Put the search in the URL once typing pauses. Each keystroke drops the previous timer.
+    {
+        let query = query.clone();
+        use_effect_with((*text).clone(), move |text| {
+            let text = text.trim().to_string();
+            let timeout = (text != query).then(|| {
+                Timeout::new(DEBOUNCE_MS, move || {
+                    let search = SearchQuery {
+                        q: (!text.is_empty()).then_some(text),
+                    };
+                    // Replaced, so the back button doesn't step through every keystroke.
+                    if let Err(err) = navigator.replace_with_query(&Route::Search, &search) {
+                        error!("Couldn't update the search in the URL", err.to_string());
+                    }
+                })
+            });
+            move || drop(timeout)
+        });
+    }
+
+    let results = use_fetch(query.clone(), |query| {
+        let query = query.clone();
+        async move {
+            if query.chars().count() < MIN_QUERY_LENGTH {
+                return Ok(Vec::new());
+            }
+            search_posts(query).await
+        }
+    });
+    let on_retry = {
+        let results = results.clone();
+        Callback::from(move |_| results.retry())
+    };
+
+    let terms: Vec<Vec<char>> = search_terms(&query)
+        .iter()
+        .map(|term| term.chars().collect())
+        .collect();
+
+    html! {
+        <div class="max-w-[1200px] mx-auto p-4 box-border">
+            <div class="flex flex-wrap justify-between items-center mb-5">
+                <h1 class="text-[clamp(1.5rem,5vw,2.5rem)] text-gray-900 font-bold m-0">{ "Search" }</h1>
+                <Link<Route> to={Route::Home} classes="text-blue-600 hover:underline">
+                    { "Back to the front page" }
+                </Link<Route>>
+            </div>
+            <input
+                type="search"
+                placeholder="Search articles"
+                value={(*text).clone()}
+                oninput={Callback::from(move |e: InputEvent| text.set(e.target_unchecked_into::<HtmlInputElement>().value()))}
+                class="w-full p-3 mb-6 border border-gray-300 rounded-lg"
+            />
+            {
+                match &*results {
+                    _ if query.chars().count() < MIN_QUERY_LENGTH => html! {
+                        <p class="text-gray-600">{ "Type at least two letters to search the articles." }</p>
+                    },
+                    FetchState::Idle | FetchState::Loading => html! { <p class="text-gray-600">{ "Searching..." }</p> },
+                    FetchState::Err(err) => html! { <ErrorPanel error={err.clone()} on_retry={on_retry} /> },
+                    FetchState::Ok(results) if results.is_empty() => html! {
+                        <p class="text-gray-600">{ format!("No articles match \"{}\".", query) }</p>
+                    },
+                    FetchState::Ok(results) => html! {
+                        <ul class={POST_GRID_CLASSES}>
+                            {
+                                for results.iter().map(|result| html! {
+                                    <PostCard post={result.post.clone()}>
+                                        { snippet(result.text.as_deref().unwrap_or_default(), &terms) }
+                                    </PostCard>
+                                })
+                            }
+                        </ul>
+                    },
+                }
+            }
+        </div>
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chars(text: &str) -> Vec<char> {
+        text.chars().collect()
+    }
+
+    fn terms(terms: &[&str]) -> Vec<Vec<char>> {
+        terms.iter().map(|term| chars(term)).collect()
+    }
+
+    #[test]
+    fn finds_every_term_ignoring_case() {
+        let text = chars("Rust and rusty Yew apps");
+        assert_eq!(
+            find_matches(&text, &terms(&["rust", "yew"])),
+            vec![(0, 4), (9, 13), (15, 18)]
+        );
+        assert_eq!(find_matches(&text, &terms(&["go"])), vec![]);
+    }
+
+    #[test]
+    fn matches_dont_overlap() {
+        // The earlier term wins where two start at the same place.
+        let text = chars("aaaa");
+        assert_eq!(
+            find_matches(&text, &terms(&["aa", "a"])),
+            vec![(0, 2), (2, 4)]
+        );
+        assert_eq!(find_matches(&text, &terms(&["", "aaaaa"])), vec![]);
+    }
+
+    #[test]
+    fn snippets_show_whole_words_around_the_first_match() {
+        let words = (0..100).map(|i| format!("w{}", i)).collect::<Vec<_>>();
+        let text = chars(&words.join(" "));
+        let first = find_matches(&text, &terms(&["w50"]))[0].0;
+        let (start, end) = snippet_range(&text, &terms(&["w50"]));
+        assert!(start <= first - SNIPPET_BEFORE && end >= first + SNIPPET_AFTER);
+        assert!(start > 0 && end < text.len());
+        assert!(text[start - 1] == ' ' && text[end] == ' ');
+    }
+
+    #[test]
+    fn snippets_start_at_the_beginning_without_a_match() {
+        let text = chars("A short text.");
+        assert_eq!(snippet_range(&text, &terms(&["title"])), (0, text.len()));
+    }
+}
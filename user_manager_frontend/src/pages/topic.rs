@@ -0,0 +1,91 @@
+use gloo_console::error;
+use yew::{function_component, html, Html, Properties};
+use yew_router::prelude::*;
+
+use super::home::InfinitePosts;
+use crate::{
+    app::Route,
+    helpers::{
+        content::{default_source, PostFilter},
+        fetch::{use_fetch, FetchError, FetchState},
+    },
+};
+
+// A tag or a category whose posts are listed.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Topic {
+    Tag(String),
+    // Named by its slug.
+    Category(String),
+}
+
+#[derive(PartialEq, Properties)]
+pub struct TopicPageProps {
+    pub topic: Topic,
+}
+
+// Lists the posts with a tag or in a category, newest first, loading more while scrolling.
+#[function_component]
+pub fn TopicPage(props: &TopicPageProps) -> Html {
+    // Categories have a title and maybe a description in the CMS; tags are just their name.
+    let details = use_fetch(props.topic.clone(), |topic| {
+        let topic = topic.clone();
+        async move {
+            let Topic::Category(slug) = topic else {
+                return Ok(None);
+            };
+            let details = match default_source().get_category(&slug).await {
+                Ok(details) => Ok(Some(details)),
+                // Without details the slug stands in for the title.
+                Err(FetchError::NotFound) => Ok(None),
+                Err(err) => Err(err),
+            };
+            if let Err(err) = &details {
+                error!("Couldn't load the category", err.to_string());
+            }
+            details
+        }
+    });
+
+    let (heading, filter, key) = match &props.topic {
+        Topic::Tag(tag) => (
+            format!("Posts tagged #{}", tag),
+            PostFilter::Tag(tag.clone()),
+            format!("tag:{}", tag),
+        ),
+        Topic::Category(slug) => {
+            // The slug stands in for the title until it has loaded, or if the category has none.
+            let title = match &*details {
+                FetchState::Ok(Some(details)) => details.title.clone(),
+                _ => slug.clone(),
+            };
+            (
+                format!("Posts in {}", title),
+                PostFilter::Category(slug.clone()),
+                format!("category:{}", slug),
+            )
+        }
+    };
+    let description = match &*details {
+        FetchState::Ok(Some(details)) => details.description.clone(),
+        _ => None,
+    };
+
+    html! {
+        <div class="max-w-[1200px] mx-auto p-4 box-border">
+            <div class="flex flex-wrap justify-between items-center mb-5">
+                <h1 class="text-[clamp(1.5rem,5vw,2.5rem)] text-gray-900 font-bold m-0">{ heading }</h1>
+                <Link<Route> to={Route::Home} classes="text-blue-600 hover:underline">
+                    { "Back to the front page" }
+                </Link<Route>>
+            </div>
+            {
+                match description {
+                    Some(description) => html! { <p class="text-gray-700 mb-6">{ description }</p> },
+                    None => html! {},
+                }
+            }
+            <InfinitePosts key={key} filter={filter} />
+        </div>
+    }
+}